@@ -0,0 +1,55 @@
+//! Point links reserved at the start of every store
+//!
+//! The encodings built on top of doublets identify their building blocks
+//! by these indices, so a store that uses them must hold each constant as
+//! a point at exactly this index. [`Sequences::reserve`] creates them in
+//! a fresh store and checks them in an existing one.
+//!
+//! * `ZERO` and `ONE` are binary digits
+//! * `UNICODE_SYMBOL` starts the chain of bits of a character
+//! * `UNICODE_SEQUENCE` marks a string, `(UNICODE_SEQUENCE, characters)`
+//! * `NUMBER` and `NEGATIVE_NUMBER` start the chain of bits of an integer
+//! * `TYPE` and `PROPERTY` mark the names declared by a `Schema`
+//!
+//! [`Sequences::reserve`]: crate::Sequences::reserve
+
+use crate::{Doublets, Error, Index, Link, Result};
+
+crate::reserve_constants! {
+  const ZERO = 1;
+  const ONE = 2;
+  const UNICODE_SYMBOL = 3;
+  const UNICODE_SEQUENCE = 4;
+  const NUMBER = 5;
+  const NEGATIVE_NUMBER = 6;
+  const TYPE = 7;
+  const PROPERTY = 8;
+}
+
+/// Create missing constants in `links` and check the existing ones
+pub(crate) fn reserve<T, L>(links: &mut L) -> Result<(), T>
+where
+  T: Index,
+  L: Doublets<T> + ?Sized,
+{
+  for constant in 1..=MAX_RESERVED {
+    let index = get(constant)?;
+    match links.get(index) {
+      Some(link) if link == Link::point(index) => {}
+      Some(_) => return Err(Error::Reserved(index)),
+      None => {
+        let created = links.create_point()?;
+        if created != index {
+          links.delete_link(created)?;
+          return Err(Error::Reserved(index));
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+/// A constant as an index of `T`
+pub(crate) fn get<T: Index>(constant: usize) -> Result<T, T> {
+  T::try_from_usize(constant).ok_or(Error::Overflow)
+}
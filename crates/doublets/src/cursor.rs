@@ -0,0 +1,123 @@
+use crate::{Error, Index, Link, Result};
+
+/// Which links a [`Cursor`] walks and in what order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scan<T> {
+  /// Every link by index
+  All,
+  /// Links with this source by `(source, target, index)`
+  Source(T),
+  /// Links with this target by `(target, source, index)`
+  Target(T),
+}
+
+/// Resumable position in a walk over the links of a store
+///
+/// A cursor remembers the last link it returned rather than an offset, so
+/// it stays valid when links are created or deleted between pages: the
+/// walk continues with the first link ordered after that one. Walk it with
+/// [`Store::resume`](crate::Store::resume) and keep it between requests
+/// as a [`token`](Self::token).
+///
+/// # Examples
+/// ```
+/// use doublets::{Cursor, Doublets, create_heap_store};
+///
+/// let mut store = create_heap_store::<usize>()?;
+/// let a = store.create_point()?;
+/// for _ in 0..5 {
+///   store.create_link(a, a)?;
+/// }
+///
+/// let mut cursor = Cursor::by_source(a);
+/// let first: Vec<_> = store.resume(&mut cursor).take(4).collect();
+/// let token = cursor.token();
+///
+/// let mut cursor = Cursor::from_token(&token)?;
+/// let rest: Vec<_> = store.resume(&mut cursor).collect();
+/// assert_eq!((first.len(), rest.len()), (4, 2));
+/// # Ok::<(), doublets::Error<usize>>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor<T: Index> {
+  pub(crate) scan: Scan<T>,
+  pub(crate) last: Option<Link<T>>,
+}
+
+impl<T: Index> Cursor<T> {
+  const TOKEN_LEN: usize = 2 + 4 * 8;
+
+  /// Walk every link in index order
+  pub fn all() -> Self {
+    Self { scan: Scan::All, last: None }
+  }
+
+  /// Walk links starting at `source`, ordered by target and then index
+  pub fn by_source(source: T) -> Self {
+    Self { scan: Scan::Source(source), last: None }
+  }
+
+  /// Walk links ending at `target`, ordered by source and then index
+  pub fn by_target(target: T) -> Self {
+    Self { scan: Scan::Target(target), last: None }
+  }
+
+  /// Last link returned, `None` before the walk starts
+  pub fn last(&self) -> Option<Link<T>> {
+    self.last
+  }
+
+  /// Encode the cursor as an opaque continuation token
+  pub fn token(&self) -> String {
+    let (kind, key) = match self.scan {
+      Scan::All => (0, T::ZERO),
+      Scan::Source(source) => (1, source),
+      Scan::Target(target) => (2, target),
+    };
+    let last = self.last.unwrap_or(Link::nothing());
+
+    let mut bytes = Vec::with_capacity(Self::TOKEN_LEN);
+    bytes.extend([kind, self.last.is_some() as u8]);
+    for part in [key, last.source, last.target, last.index] {
+      bytes.extend((part.as_usize() as u64).to_le_bytes());
+    }
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+  }
+
+  /// Decode a token produced by [`token`](Self::token)
+  pub fn from_token(token: &str) -> Result<Self, T> {
+    let token = token.as_bytes();
+    if token.len() != Self::TOKEN_LEN * 2 {
+      return Err(Error::InvalidToken);
+    }
+    let bytes = token
+      .chunks_exact(2)
+      .map(|pair| {
+        let pair = core::str::from_utf8(pair).ok()?;
+        u8::from_str_radix(pair, 16).ok()
+      })
+      .collect::<Option<Vec<_>>>()
+      .ok_or(Error::InvalidToken)?;
+
+    let mut parts = [T::ZERO; 4];
+    for (part, word) in parts.iter_mut().zip(bytes[2..].chunks_exact(8)) {
+      let word = u64::from_le_bytes(word.try_into().unwrap());
+      let word = usize::try_from(word).map_err(|_| Error::InvalidToken)?;
+      *part = T::try_from_usize(word).ok_or(Error::InvalidToken)?;
+    }
+    let [key, source, target, index] = parts;
+
+    let scan = match bytes[0] {
+      0 => Scan::All,
+      1 => Scan::Source(key),
+      2 => Scan::Target(key),
+      _ => return Err(Error::InvalidToken),
+    };
+    let last = match bytes[1] {
+      0 => None,
+      1 => Some(Link::new(index, source, target)),
+      _ => return Err(Error::InvalidToken),
+    };
+    Ok(Self { scan, last })
+  }
+}
@@ -1,4 +1,4 @@
-use {crate::Index, core::fmt::Debug, thiserror::Error};
+use {crate::Index, core::fmt::Debug, std::io, thiserror::Error};
 /// Errors that can occur during doublets operations
 #[derive(Debug, Error, Clone, PartialEq, Eq)]
 pub enum Error<T: Index> {
@@ -14,5 +14,41 @@ pub enum Error<T: Index> {
   Overflow,
   #[error("Invalid query parameters")]
   InvalidQuery,
+  #[error("Memory does not contain a compatible store header")]
+  InvalidHeader,
+  #[error("A transaction is already active")]
+  TransactionActive,
+  #[error("No transaction is active")]
+  NoTransaction,
+  #[error("Transaction log is corrupted or does not match the store")]
+  CorruptLog,
+  #[error("Cursor token is malformed")]
+  InvalidToken,
+  #[error("Snapshot stream is malformed or fails its checksum")]
+  InvalidSnapshot,
+  #[error("Snapshots can only be imported into an empty store")]
+  NotEmpty,
+  #[error("Link {0:?} was created out of index order during an import")]
+  OutOfOrder(T),
+  #[error("Links notation is malformed at line {0}")]
+  InvalidNotation(usize),
+  #[error("Link {0:?} is reserved for a constant but holds another link")]
+  Reserved(T),
+  #[error("Link {0:?} is not a well-formed sequence")]
+  InvalidSequence(T),
+  #[error("Link {0:?} is not a well-formed number")]
+  InvalidNumber(T),
+  #[error("Link {0:?} violates the schema")]
+  SchemaViolation(T),
+  #[error("Change to link {0:?} was vetoed by an observer")]
+  Vetoed(T),
+  #[error("I/O failed: {0:?}")]
+  Io(io::ErrorKind),
+}
+
+impl<T: Index> From<io::Error> for Error<T> {
+  fn from(err: io::Error) -> Self {
+    Error::Io(err.kind())
+  }
 }
 pub type Result<R, T> = core::result::Result<R, Error<T>>;
@@ -0,0 +1,523 @@
+use {
+  crate::{
+    Error, Flow, Index, Link, Links, Pattern, Query, Term, transaction::revert,
+  },
+  core::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+  },
+  std::collections::{BTreeMap, BTreeSet},
+};
+
+/// A parsed query language statement
+///
+/// ```text
+/// statement := "match" patterns
+///            | "count" patterns
+///            | "create" link ("," link)*
+///            | "update" pattern "=>" term "->" term ["where" patterns]
+///            | "delete" pattern ["where" patterns]
+/// patterns  := pattern ("," pattern)*
+/// pattern   := [term ":"] term "->" term
+/// link      := number "->" number
+/// term      := number | "?" name | "*"
+/// ```
+///
+/// `?name` is a variable, `*` matches anything, and the optional term
+/// before `:` is the index of the link. `update` and `delete` act on the
+/// links their first pattern matches, for every match of all patterns.
+/// A `*` on the right of `=>` keeps that part of the link as it is.
+///
+/// # Examples
+/// ```
+/// use doublets::{Doublets, Outcome, Statement, create_heap_store};
+///
+/// let mut store = create_heap_store::<usize>()?;
+/// store.create_point()?;
+///
+/// let create: Statement<usize> = "create 1 -> 1, 1 -> 1".parse()?;
+/// create.execute(&mut store)?;
+/// let update: Statement<usize> = "update ?l: 1 -> 1 => ?l -> 1".parse()?;
+/// update.execute(&mut store)?;
+///
+/// // The point and both rewritten links start at themselves
+/// let count: Statement<usize> = "count ?l: ?l -> *".parse()?;
+/// assert_eq!(count.execute(&mut store)?, Outcome::Count(3));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement<T> {
+  /// Bind variables for every match of the patterns
+  Match(Vec<Pattern<T>>),
+  /// Count the matches of the patterns
+  Count(Vec<Pattern<T>>),
+  /// Create a link for every `(source, target)` pair
+  Create(Vec<(T, T)>),
+  /// Rewrite the links `pattern` matches to `source -> target`
+  Update {
+    pattern: Pattern<T>,
+    source: Term<T>,
+    target: Term<T>,
+    filter: Vec<Pattern<T>>,
+  },
+  /// Delete the links `pattern` matches
+  Delete { pattern: Pattern<T>, filter: Vec<Pattern<T>> },
+}
+
+/// What a [`Statement`] produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<T: Index> {
+  /// Values of `variables` for every match, one row each
+  Matches { variables: Vec<String>, rows: Vec<Vec<T>> },
+  /// Number of matches
+  Count(usize),
+  /// Links that were created
+  Created(Vec<Link<T>>),
+  /// Links as they are after the update
+  Updated(Vec<Link<T>>),
+  /// Links as they were before deletion
+  Deleted(Vec<Link<T>>),
+}
+
+/// Why a statement could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+  /// 1-based column of the offending character
+  pub column: usize,
+  /// What the parser wanted there
+  pub message: String,
+}
+
+impl Display for ParseError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{} at column {}", self.message, self.column)
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Variable bound to the index of the link a write acts on
+const TARGET: &str = "";
+
+impl<T: Index> Statement<T> {
+  /// Run the statement against `links`
+  ///
+  /// Writes find all their links first and then change them, so a write
+  /// never sees its own changes. Links matched several times are changed
+  /// once. A write failing partway reverts the changes it made before,
+  /// leaving the links as they were.
+  pub fn execute<L>(&self, links: &mut L) -> Result<Outcome<T>, Error<T>>
+  where
+    L: Links<T> + ?Sized,
+  {
+    match self {
+      Statement::Match(patterns) => {
+        let query = query(patterns.iter().cloned());
+        let rows = query
+          .run(links)
+          .iter()
+          .map(|found| found.iter().map(|(_, value)| value).collect())
+          .collect();
+        Ok(Outcome::Matches { variables: query.variables().to_vec(), rows })
+      }
+      Statement::Count(patterns) => {
+        let mut count = 0;
+        query(patterns.iter().cloned()).each(links, |_| {
+          count += 1;
+          Flow::Continue
+        });
+        Ok(Outcome::Count(count))
+      }
+      Statement::Create(pairs) => atomic(links, |links, record| {
+        let mut created = Vec::with_capacity(pairs.len());
+        for &(source, target) in pairs {
+          let mut index = T::ZERO;
+          links.create([source, target], &mut |before, after: Link<T>| {
+            index = after.index;
+            record(before, after)
+          })?;
+          created.push(Link::new(index, source, target));
+        }
+        Ok(Outcome::Created(created))
+      }),
+      Statement::Update { pattern, source, target, filter } => {
+        let targets = targets(links, pattern, filter);
+        atomic(links, |links, mut record| {
+          let mut updated = Vec::new();
+          for (index, found) in targets {
+            let link = links.get(index).ok_or(Error::NotExists(index))?;
+            // Hand-built statements may use variables no pattern binds
+            let resolve = |term: &Term<T>, current| match term {
+              Term::Const(value) => Ok(*value),
+              Term::Var(name) => {
+                found.get(name).copied().ok_or(Error::InvalidQuery)
+              }
+              Term::Any => Ok(current),
+            };
+            let source = resolve(source, link.source)?;
+            let target = resolve(target, link.target)?;
+            links.update([index], [index, source, target], &mut record)?;
+            updated.push(Link::new(index, source, target));
+          }
+          Ok(Outcome::Updated(updated))
+        })
+      }
+      Statement::Delete { pattern, filter } => {
+        let targets = targets(links, pattern, filter);
+        atomic(links, |links, mut record| {
+          let mut deleted = Vec::new();
+          for (index, _) in targets {
+            let link = links.get(index).ok_or(Error::NotExists(index))?;
+            links.delete([index], &mut record)?;
+            deleted.push(link);
+          }
+          Ok(Outcome::Deleted(deleted))
+        })
+      }
+    }
+  }
+}
+
+impl<T: Index> FromStr for Statement<T> {
+  type Err = ParseError;
+
+  fn from_str(text: &str) -> Result<Self, ParseError> {
+    let end = text.chars().count() + 1;
+    let mut parser = Parser { tokens: tokenize(text)?, next: 0, end };
+    let statement = parser.statement()?;
+    match parser.peek() {
+      None => Ok(statement),
+      Some(token) => Err(parser.error(token, "expected end of statement")),
+    }
+  }
+}
+
+impl<T: Index> Display for Outcome<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Outcome::Matches { variables, rows } => {
+        for row in rows {
+          let mut parts = variables.iter().zip(row);
+          match parts.next() {
+            Some((name, value)) => write!(f, "?{name} = {value:?}")?,
+            None => write!(f, "()")?,
+          }
+          for (name, value) in parts {
+            write!(f, ", ?{name} = {value:?}")?;
+          }
+          writeln!(f)?;
+        }
+        Ok(())
+      }
+      Outcome::Count(count) => writeln!(f, "{count}"),
+      Outcome::Created(links)
+      | Outcome::Updated(links)
+      | Outcome::Deleted(links) => {
+        links.iter().try_for_each(|link| writeln!(f, "({link:?})"))
+      }
+    }
+  }
+}
+
+fn query<T: Index>(patterns: impl Iterator<Item = Pattern<T>>) -> Query<T> {
+  patterns.fold(Query::new(), Query::pattern)
+}
+
+/// Run the writes of `op`, which passes every change to `record`, and
+/// revert all of them if it fails
+fn atomic<T, L, R>(
+  links: &mut L,
+  op: impl FnOnce(
+    &mut L,
+    &mut dyn FnMut(Link<T>, Link<T>) -> Flow,
+  ) -> Result<R, Error<T>>,
+) -> Result<R, Error<T>>
+where
+  T: Index,
+  L: Links<T> + ?Sized,
+{
+  let mut changes = Vec::new();
+  let result = op(links, &mut |before, after| {
+    changes.push((before, after));
+    Flow::Continue
+  });
+  if result.is_err() {
+    revert(links, &changes)?;
+  }
+  result
+}
+
+/// Distinct links `pattern` matches under `filter`, with one match each
+fn targets<T, L>(
+  links: &L,
+  pattern: &Pattern<T>,
+  filter: &[Pattern<T>],
+) -> Vec<(T, BTreeMap<String, T>)>
+where
+  T: Index,
+  L: Links<T> + ?Sized,
+{
+  let mut pattern = pattern.clone();
+  if let Term::Any = pattern.index {
+    pattern.index = Term::var(TARGET);
+  }
+  let index = pattern.index.clone();
+  let query = query([pattern].into_iter().chain(filter.iter().cloned()));
+
+  let mut seen = BTreeSet::new();
+  let mut targets = Vec::new();
+  query.each(links, |found| {
+    let index = match &index {
+      Term::Const(value) => *value,
+      Term::Var(name) => found.get(name).unwrap(),
+      Term::Any => unreachable!("replaced by a variable"),
+    };
+    if seen.insert(index) {
+      let found = found.iter().map(|(name, value)| (name.into(), value));
+      targets.push((index, found.collect()));
+    }
+    Flow::Continue
+  });
+  targets
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+  Word(&'a str),
+  Number(&'a str),
+  Var(&'a str),
+  Any,
+  Arrow,
+  Rewrite,
+  Colon,
+  Comma,
+}
+
+impl Display for Token<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Word(word) | Token::Number(word) => write!(f, "`{word}`"),
+      Token::Var(name) => write!(f, "`?{name}`"),
+      Token::Any => write!(f, "`*`"),
+      Token::Arrow => write!(f, "`->`"),
+      Token::Rewrite => write!(f, "`=>`"),
+      Token::Colon => write!(f, "`:`"),
+      Token::Comma => write!(f, "`,`"),
+    }
+  }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token<'_>)>, ParseError> {
+  let mut tokens = Vec::new();
+  let mut rest = text;
+  loop {
+    rest = rest.trim_start();
+    let column = text[..text.len() - rest.len()].chars().count() + 1;
+    let Some(first) = rest.chars().next() else {
+      return Ok(tokens);
+    };
+    let word_len = |from: usize| {
+      rest[from..]
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map_or(rest.len(), |end| from + end)
+    };
+    let (token, len) = match first {
+      '*' => (Token::Any, 1),
+      ':' => (Token::Colon, 1),
+      ',' => (Token::Comma, 1),
+      '-' if rest.starts_with("->") => (Token::Arrow, 2),
+      '=' if rest.starts_with("=>") => (Token::Rewrite, 2),
+      '?' => {
+        let len = word_len(1);
+        if len == 1 {
+          return Err(ParseError {
+            column,
+            message: "expected a variable name after `?`".into(),
+          });
+        }
+        (Token::Var(&rest[1..len]), len)
+      }
+      c if c.is_ascii_digit() => {
+        let len = word_len(0);
+        (Token::Number(&rest[..len]), len)
+      }
+      c if c.is_alphabetic() => {
+        let len = word_len(0);
+        (Token::Word(&rest[..len]), len)
+      }
+      c => {
+        return Err(ParseError {
+          column,
+          message: format!("unexpected character `{c}`"),
+        });
+      }
+    };
+    tokens.push((column, token));
+    rest = &rest[len..];
+  }
+}
+
+struct Parser<'a> {
+  tokens: Vec<(usize, Token<'a>)>,
+  next: usize,
+  /// Column just past the input, where running out of tokens is reported
+  end: usize,
+}
+
+impl<'a> Parser<'a> {
+  fn peek(&self) -> Option<(usize, Token<'a>)> {
+    self.tokens.get(self.next).copied()
+  }
+
+  fn bump(&mut self) -> Option<(usize, Token<'a>)> {
+    let token = self.peek()?;
+    self.next += 1;
+    Some(token)
+  }
+
+  fn error(
+    &self,
+    (column, token): (usize, Token<'_>),
+    expected: &str,
+  ) -> ParseError {
+    ParseError { column, message: format!("{expected}, found {token}") }
+  }
+
+  fn eat(&mut self, wanted: Token<'_>) -> bool {
+    let found = self.peek().is_some_and(|(_, token)| token == wanted);
+    self.next += found as usize;
+    found
+  }
+
+  fn expect(&mut self, wanted: Token<'_>) -> Result<(), ParseError> {
+    match self.bump() {
+      Some((_, token)) if token == wanted => Ok(()),
+      Some(token) => Err(self.error(token, &format!("expected {wanted}"))),
+      None => Err(ParseError {
+        column: self.end,
+        message: format!("expected {wanted}, found end of statement"),
+      }),
+    }
+  }
+
+  fn statement<T: Index>(&mut self) -> Result<Statement<T>, ParseError> {
+    let keyword = self.bump().ok_or(ParseError {
+      column: self.end,
+      message: "expected a statement, found end of statement".into(),
+    })?;
+    let statement = match keyword.1 {
+      Token::Word("match") => Statement::Match(self.patterns()?),
+      Token::Word("count") => Statement::Count(self.patterns()?),
+      Token::Word("create") => {
+        let mut pairs = vec![self.link()?];
+        while self.eat(Token::Comma) {
+          pairs.push(self.link()?);
+        }
+        Statement::Create(pairs)
+      }
+      Token::Word("update") => {
+        let pattern = self.pattern()?;
+        self.expect(Token::Rewrite)?;
+        let at = self.peek();
+        let source = self.term()?;
+        self.expect(Token::Arrow)?;
+        let target = self.term()?;
+        let filter = self.filter()?;
+
+        let bound = query([&pattern].into_iter().chain(&filter).cloned());
+        for term in [&source, &target] {
+          if let Term::Var(name) = term
+            && !bound.variables().contains(name)
+          {
+            return Err(ParseError {
+              column: at.map_or(self.end, |(column, _)| column),
+              message: format!("variable `?{name}` is not bound by a pattern"),
+            });
+          }
+        }
+        Statement::Update { pattern, source, target, filter }
+      }
+      Token::Word("delete") => {
+        let pattern = self.pattern()?;
+        let filter = self.filter()?;
+        Statement::Delete { pattern, filter }
+      }
+      _ => {
+        return Err(self.error(
+          keyword,
+          "expected `match`, `count`, `create`, `update` or `delete`",
+        ));
+      }
+    };
+    Ok(statement)
+  }
+
+  fn filter<T: Index>(&mut self) -> Result<Vec<Pattern<T>>, ParseError> {
+    if self.eat(Token::Word("where")) {
+      self.patterns()
+    } else {
+      Ok(Vec::new())
+    }
+  }
+
+  fn patterns<T: Index>(&mut self) -> Result<Vec<Pattern<T>>, ParseError> {
+    let mut patterns = vec![self.pattern()?];
+    while self.eat(Token::Comma) {
+      patterns.push(self.pattern()?);
+    }
+    Ok(patterns)
+  }
+
+  fn pattern<T: Index>(&mut self) -> Result<Pattern<T>, ParseError> {
+    let first = self.term()?;
+    if self.eat(Token::Colon) {
+      let source = self.term()?;
+      self.expect(Token::Arrow)?;
+      Ok(Pattern::new(first, source, self.term()?))
+    } else {
+      self.expect(Token::Arrow)?;
+      Ok(Pattern::link(first, self.term()?))
+    }
+  }
+
+  fn link<T: Index>(&mut self) -> Result<(T, T), ParseError> {
+    let source = self.number()?;
+    self.expect(Token::Arrow)?;
+    Ok((source, self.number()?))
+  }
+
+  fn number<T: Index>(&mut self) -> Result<T, ParseError> {
+    match self.term()? {
+      Term::Const(value) => Ok(value),
+      _ => {
+        let token = self.tokens[self.next - 1];
+        Err(self.error(token, "expected a link number"))
+      }
+    }
+  }
+
+  fn term<T: Index>(&mut self) -> Result<Term<T>, ParseError> {
+    match self.bump() {
+      Some((_, Token::Any)) => Ok(Term::Any),
+      Some((_, Token::Var(name))) => Ok(Term::var(name)),
+      Some((column, Token::Number(digits))) => digits
+        .parse()
+        .ok()
+        .and_then(T::try_from_usize)
+        .filter(|value| !value.is_zero())
+        .map(Term::Const)
+        .ok_or(ParseError {
+          column,
+          message: format!("`{digits}` is not a valid link number"),
+        }),
+      Some(token) => {
+        Err(self.error(token, "expected a number, `?name` or `*`"))
+      }
+      None => Err(ParseError {
+        column: self.end,
+        message: "expected a number, `?name` or `*`, found end of statement"
+          .into(),
+      }),
+    }
+  }
+}
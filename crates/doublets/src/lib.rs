@@ -1,18 +1,41 @@
 #![doc = include_str!("../README.md")]
 
+pub mod constants;
+mod cursor;
 mod error;
 mod handler;
+mod language;
 mod link;
+mod lino;
+mod numbers;
+mod observer;
+mod query;
+mod schema;
+mod sequences;
+mod snapshot;
 mod store;
 mod traits;
+mod transaction;
+mod verify;
 
 pub use {
+  cursor::Cursor,
   error::{Error, Result},
   handler::{Flow, IntoFlow, ReadHandler, WriteHandler},
+  language::{Outcome, ParseError, Statement},
   link::{Index, Link},
+  numbers::{Number, Numbers},
+  observer::{Observer, Subscription},
+  query::{Bindings, Pattern, Query, Term},
+  schema::Schema,
+  sequences::Sequences,
+  snapshot::Snapshot,
   store::{
-    ArtStrategy, SbtStrategy, Store, TreeStrategy, create_heap_store,
+    ArtStrategy, BPlusIndex, BPlusStrategy, Integrity, RadixIndex, RawLink,
+    SbtStrategy, Store, TreeStrategy, Uniqueness, create_heap_store,
     create_heap_store_with_strategies,
   },
   traits::{Doublets, Links},
+  transaction::Transactional,
+  verify::{Problem, Report, Side},
 };
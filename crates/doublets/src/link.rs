@@ -11,8 +11,24 @@ use core::{
 /// This trait is implemented for primitives (usize, u64, etc.)
 /// The Repr type is used for memory-efficient tree storage using
 /// NonZero variants.
-pub trait Index:
-  Copy + Clone + Eq + PartialEq + Ord + PartialOrd + Debug + Send + Sync
+///
+/// # Safety
+///
+/// `Repr` must be the NonZero counterpart of `Self`, so that
+/// `Option<Repr>` has the size and alignment of `Self`. Raw links rely
+/// on it to pack tree children into index-sized words and to read them
+/// back as plain bytes.
+pub unsafe trait Index:
+  Copy
+  + Clone
+  + Eq
+  + PartialEq
+  + Ord
+  + PartialOrd
+  + Debug
+  + Send
+  + Sync
+  + bytemuck::Pod
 {
   /// The representation type for tree storage (typically NonZero variant)
   type Repr: Copy
@@ -23,7 +39,9 @@ pub trait Index:
     + PartialOrd
     + Debug
     + Send
-    + Sync;
+    + Sync
+    + bytemuck::PodInOption
+    + trees::Idx;
 
   /// Special constant values known at compile time
   const ZERO: Self;
@@ -34,8 +52,17 @@ pub trait Index:
   fn is_zero(&self) -> bool;
 
   /// Convert from usize
+  ///
+  /// Values that do not fit wrap around, use
+  /// [`try_from_usize`](Self::try_from_usize) where that matters.
   fn from_usize(val: usize) -> Self;
 
+  /// Convert from usize, or `None` if the value does not fit
+  ///
+  /// Signed types only take their non-negative range, so a value that
+  /// would wrap into a negative index is rejected as well.
+  fn try_from_usize(val: usize) -> Option<Self>;
+
   /// Convert to usize
   fn as_usize(&self) -> usize;
 
@@ -54,7 +81,8 @@ pub trait Index:
 
 macro_rules! impl_index {
   ($prim:ty, $nonzero:ty) => {
-    impl Index for $prim {
+    // SAFETY: `$nonzero` is the NonZero counterpart of `$prim`
+    unsafe impl Index for $prim {
       type Repr = $nonzero;
 
       const ZERO: Self = 0;
@@ -71,6 +99,11 @@ macro_rules! impl_index {
         val as Self
       }
 
+      #[inline]
+      fn try_from_usize(val: usize) -> Option<Self> {
+        Self::try_from(val).ok()
+      }
+
       #[inline]
       fn as_usize(&self) -> usize {
         *self as usize
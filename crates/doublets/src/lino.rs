@@ -0,0 +1,172 @@
+use {
+  crate::{Doublets, Error, Index, Link, Links, Result},
+  std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+  },
+};
+
+/// A link read from Links Notation, `index` is `None` for `(source target)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Entry<T> {
+  pub index: Option<T>,
+  pub source: T,
+  pub target: T,
+}
+
+/// Write `links` as Links Notation, one `(index: source target)` per line
+pub(crate) fn write<T, L, W>(links: &L, mut writer: W) -> Result<(), T>
+where
+  T: Index,
+  L: Links<T> + ?Sized,
+  W: Write,
+{
+  let mut all = links.collect_all();
+  all.sort_unstable_by_key(|link| link.index);
+  for link in all {
+    let [index, source, target] =
+      [link.index, link.source, link.target].map(|part| part.as_usize());
+    writeln!(writer, "({index}: {source} {target})")?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+/// Parse Links Notation into entries in text order
+///
+/// Links are `(index: source target)` or `(source target)`, separated by
+/// any whitespace. Errors carry the 1-based line of the offending token.
+pub(crate) fn parse<T: Index>(text: &str) -> Result<Vec<Entry<T>>, T> {
+  let mut tokens = Tokens::new(text);
+  let mut entries = Vec::new();
+
+  while let Some((line, token)) = tokens.next() {
+    if token != "(" {
+      return Err(Error::InvalidNotation(line));
+    }
+    let mut words = Vec::with_capacity(3);
+    let mut named = false;
+    loop {
+      let (line, token) = tokens.next().ok_or(Error::InvalidNotation(line))?;
+      match token {
+        ")" => break,
+        ":" if words.len() == 1 && !named => named = true,
+        word if words.len() < 3 => words.push(number(word, line)?),
+        _ => return Err(Error::InvalidNotation(line)),
+      }
+    }
+    let entry = match (named, words.as_slice()) {
+      (true, &[index, source, target]) if !index.is_zero() => {
+        Entry { index: Some(index), source, target }
+      }
+      (false, &[source, target]) => Entry { index: None, source, target },
+      _ => return Err(Error::InvalidNotation(line)),
+    };
+    entries.push(entry);
+  }
+  Ok(entries)
+}
+
+/// Load Links Notation into `links`, returning where each index went
+///
+/// Every index named in the text is kept when the store can hand it out,
+/// and mapped to a fresh index when it is taken. Reaching free indices
+/// takes placeholder points, at most as many as the text has bytes so
+/// the work stays bounded by the input, and indices further out are
+/// mapped to fresh ones as well. References to indices named in the text
+/// follow that mapping, other references are kept as they are, so
+/// fixtures may point at links that already exist.
+pub(crate) fn load<T, L>(links: &mut L, text: &str) -> Result<BTreeMap<T, T>, T>
+where
+  T: Index,
+  L: Links<T> + ?Sized,
+{
+  let mut entries = parse::<T>(text)?;
+
+  let mut named = BTreeSet::new();
+  for (line, entry) in lines(text).zip(&entries) {
+    if let Some(index) = entry.index
+      && !named.insert(index)
+    {
+      return Err(Error::InvalidNotation(line));
+    }
+  }
+  // Anonymous links go last so that they never take a named index
+  entries.sort_by_key(|entry| entry.index.is_none());
+  entries[..named.len()].sort_unstable_by_key(|entry| entry.index);
+
+  // Every link starts out as a point so that placeholders taken while
+  // reaching a free index are unreferenced when deleted again
+  let mut mapping = BTreeMap::new();
+  let mut created = Vec::with_capacity(entries.len());
+  let mut placeholders = Vec::new();
+  for entry in &entries {
+    let index = match entry.index {
+      Some(wanted) if links.get(wanted).is_none() => loop {
+        let index = links.create_point()?;
+        if index >= wanted || placeholders.len() == text.len() {
+          break index;
+        }
+        placeholders.push(index);
+      },
+      _ => links.create_point()?,
+    };
+    if let Some(wanted) = entry.index {
+      mapping.insert(wanted, index);
+    }
+    created.push(index);
+  }
+  for placeholder in placeholders.into_iter().rev() {
+    links.delete_link(placeholder)?;
+  }
+
+  let resolve = |part: T| mapping.get(&part).copied().unwrap_or(part);
+  for (entry, index) in entries.iter().zip(created) {
+    let (source, target) = (resolve(entry.source), resolve(entry.target));
+    if Link::new(index, source, target) != Link::point(index) {
+      links.update_link(index, source, target)?;
+    }
+  }
+  Ok(mapping)
+}
+
+/// Line of the opening parenthesis of every link in `text`
+fn lines(text: &str) -> impl Iterator<Item = usize> + '_ {
+  Tokens::new(text).filter(|&(_, token)| token == "(").map(|(line, _)| line)
+}
+
+fn number<T: Index>(word: &str, line: usize) -> Result<T, T> {
+  let value: usize = word.parse().map_err(|_| Error::InvalidNotation(line))?;
+  T::try_from_usize(value).ok_or(Error::Overflow)
+}
+
+/// Parentheses, colons and whitespace separated words with their lines
+struct Tokens<'a> {
+  rest: &'a str,
+  line: usize,
+}
+
+impl<'a> Tokens<'a> {
+  fn new(text: &'a str) -> Self {
+    Self { rest: text, line: 1 }
+  }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+  type Item = (usize, &'a str);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let start = self.rest.find(|c: char| !c.is_whitespace())?;
+    self.line += self.rest[..start].matches('\n').count();
+    self.rest = &self.rest[start..];
+
+    let len = match self.rest.find(['(', ')', ':']) {
+      Some(0) => 1,
+      Some(end) => self.rest[..end].find(char::is_whitespace).unwrap_or(end),
+      None => self.rest.find(char::is_whitespace).unwrap_or(self.rest.len()),
+    };
+    let (token, rest) = self.rest.split_at(len);
+    self.rest = rest;
+    Some((self.line, token))
+  }
+}
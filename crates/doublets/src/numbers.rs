@@ -0,0 +1,152 @@
+use crate::{
+  Doublets, Error, Index, Link, Result,
+  constants::{self, NEGATIVE_NUMBER, NUMBER, ONE, ZERO},
+};
+
+/// Integers that [`Numbers`] can store
+pub trait Number: Copy {
+  /// Sign and magnitude of the value
+  fn to_parts(self) -> (bool, u64);
+
+  /// Value with this sign and magnitude, `None` when it does not fit
+  fn from_parts(negative: bool, magnitude: u64) -> Option<Self>;
+}
+
+macro_rules! impl_unsigned {
+  ($($ty:ty)*) => {$(
+    impl Number for $ty {
+      fn to_parts(self) -> (bool, u64) {
+        (false, self as u64)
+      }
+
+      fn from_parts(negative: bool, magnitude: u64) -> Option<Self> {
+        if negative { None } else { Self::try_from(magnitude).ok() }
+      }
+    }
+  )*};
+}
+
+macro_rules! impl_signed {
+  ($($ty:ty)*) => {$(
+    impl Number for $ty {
+      fn to_parts(self) -> (bool, u64) {
+        (self < 0, self.unsigned_abs() as u64)
+      }
+
+      fn from_parts(negative: bool, magnitude: u64) -> Option<Self> {
+        let magnitude = magnitude as i128;
+        Self::try_from(if negative { -magnitude } else { magnitude }).ok()
+      }
+    }
+  )*};
+}
+
+impl_unsigned!(u8 u16 u32 u64 usize);
+impl_signed!(i8 i16 i32 i64 isize);
+
+/// Integers stored as links
+///
+/// A number is a chain that starts at `NUMBER`, or at `NEGATIVE_NUMBER`
+/// for values below zero, and appends the bits of its magnitude as
+/// [`ZERO`](crate::constants::ZERO) and [`ONE`](crate::constants::ONE)
+/// points, most significant first: `6` is `(((NUMBER, 1), 1), 0)`. Each
+/// step goes through [`get_or_create`](Doublets::get_or_create), so a
+/// number is stored once and numbers with the same high bits share links.
+/// A value has the same link whatever integer type it is written from.
+///
+/// # Examples
+/// ```
+/// use doublets::{Numbers, create_heap_store};
+///
+/// let mut store = create_heap_store::<usize>()?;
+/// let answer = store.to_link(42u64)?;
+/// assert_eq!(store.to_link(42i32)?, answer);
+/// assert_eq!(store.from_link::<u8>(answer)?, 42);
+///
+/// let below = store.to_link(-1i64)?;
+/// assert_eq!(store.from_link::<i64>(below)?, -1);
+/// # Ok::<(), doublets::Error<usize>>(())
+/// ```
+pub trait Numbers<T: Index>: Doublets<T> {
+  /// Store `number`, or find the link that already holds it
+  fn to_link<N: Number>(&mut self, number: N) -> Result<T, T> {
+    constants::reserve(self)?;
+    let (negative, magnitude) = number.to_parts();
+    let start = if negative { NEGATIVE_NUMBER } else { NUMBER };
+    create_chain(self, constants::get(start)?, magnitude)
+  }
+
+  /// Read a number stored with [`to_link`](Self::to_link)
+  ///
+  /// Fails with [`Error::InvalidNumber`] when `index` holds no number,
+  /// including chains [`to_link`](Self::to_link) never writes like leading
+  /// zeros or a negative zero, and [`Error::Overflow`] when the number does
+  /// not fit `N`.
+  // Named as the inverse of `to_link`, it reads from the store
+  #[allow(clippy::wrong_self_convention)]
+  fn from_link<N: Number>(&self, index: T) -> Result<N, T> {
+    let negative = constants::get(NEGATIVE_NUMBER)?;
+    let starts = [constants::get(NUMBER)?, negative];
+    let (start, magnitude) = read_chain(self, index, &starts)?;
+    if start == negative && magnitude == 0 {
+      return Err(Error::InvalidNumber(index));
+    }
+    N::from_parts(start == negative, magnitude).ok_or(Error::Overflow)
+  }
+}
+
+impl<T: Index, S: Doublets<T> + ?Sized> Numbers<T> for S {}
+
+/// Append the bits of `value` to `start`, most significant first
+pub(crate) fn create_chain<T, L>(
+  links: &mut L,
+  start: T,
+  value: u64,
+) -> Result<T, T>
+where
+  T: Index,
+  L: Doublets<T> + ?Sized,
+{
+  let (zero, one) = (constants::get(ZERO)?, constants::get(ONE)?);
+  let len = (u64::BITS - value.leading_zeros()).max(1);
+  let mut link = start;
+  for bit in (0..len).rev() {
+    let digit = if value >> bit & 1 == 1 { one } else { zero };
+    link = links.get_or_create(link, digit)?;
+  }
+  Ok(link)
+}
+
+/// Walk a chain back to one of `starts`, returning it and the value
+///
+/// Only chains as [`create_chain`] writes them are accepted, a value with
+/// leading zeros is not one.
+pub(crate) fn read_chain<T, L>(
+  links: &L,
+  index: T,
+  starts: &[T],
+) -> Result<(T, u64), T>
+where
+  T: Index,
+  L: Doublets<T> + ?Sized,
+{
+  let (zero, one) = (constants::get(ZERO)?, constants::get(ONE)?);
+  let (mut value, mut link) = (0u64, index);
+  for bit in 0..u64::BITS {
+    let Link { index: current, source, target } =
+      links.get(link).ok_or(Error::NotExists(link))?;
+    if current == source || (target != zero && target != one) {
+      break;
+    }
+    value |= ((target == one) as u64) << bit;
+    if starts.contains(&source) {
+      let len = (u64::BITS - value.leading_zeros()).max(1);
+      if bit + 1 != len {
+        break;
+      }
+      return Ok((source, value));
+    }
+    link = source;
+  }
+  Err(Error::InvalidNumber(index))
+}
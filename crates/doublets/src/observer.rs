@@ -0,0 +1,144 @@
+use {
+  crate::{Error, Index, Link, Result},
+  std::cell::Cell,
+};
+
+/// Receiver of the changes made to a [`Store`](crate::Store)
+///
+/// A change is the `before`/`after` pair a `WriteHandler` receives: the
+/// null link stands for the state before a create and after a delete.
+/// Closures taking the pair are observers that allow every change.
+pub trait Observer<T: Index> {
+  /// Decide if a change may be applied, `false` rejects it
+  fn allow(&mut self, before: Link<T>, after: Link<T>) -> bool {
+    let _ = (before, after);
+    true
+  }
+
+  /// Receive a change right after it was applied
+  fn notify(&mut self, before: Link<T>, after: Link<T>);
+}
+
+impl<T, F> Observer<T> for F
+where
+  T: Index,
+  F: FnMut(Link<T>, Link<T>),
+{
+  fn notify(&mut self, before: Link<T>, after: Link<T>) {
+    self(before, after)
+  }
+}
+
+/// Handle returned by [`Store::subscribe`](crate::Store::subscribe)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subscription(u64);
+
+type Boxed<T> = Box<dyn Observer<T> + Send + Sync>;
+
+/// Observers of a store with their filters, in subscription order
+pub(crate) struct Observers<T: Index> {
+  next: u64,
+  entries: Vec<(Subscription, [T; 3], Boxed<T>)>,
+  /// Whether `allow` asks the observers at all
+  vetoes: bool,
+}
+
+impl<T: Index> Default for Observers<T> {
+  fn default() -> Self {
+    Self { next: 0, entries: Vec::new(), vetoes: true }
+  }
+}
+
+impl<T: Index> Observers<T> {
+  pub(crate) fn subscribe(
+    &mut self,
+    filter: [T; 3],
+    observer: Boxed<T>,
+  ) -> Subscription {
+    let subscription = Subscription(self.next);
+    self.next += 1;
+    self.entries.push((subscription, filter, observer));
+    subscription
+  }
+
+  pub(crate) fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+    let len = self.entries.len();
+    self.entries.retain(|&(other, ..)| other != subscription);
+    self.entries.len() != len
+  }
+
+  pub(crate) fn set_vetoes(&mut self, enabled: bool) -> bool {
+    core::mem::replace(&mut self.vetoes, enabled)
+  }
+
+  /// Ask every interested observer, failing on the first that rejects
+  pub(crate) fn allow(
+    &mut self,
+    before: Link<T>,
+    after: Link<T>,
+  ) -> Result<(), T> {
+    if !self.vetoes || Undo::running() {
+      return Ok(());
+    }
+    for observer in self.interested(before, after) {
+      if !observer.allow(before, after) {
+        let index = if after.is_null() { before.index } else { after.index };
+        return Err(Error::Vetoed(index));
+      }
+    }
+    Ok(())
+  }
+
+  pub(crate) fn notify(&mut self, before: Link<T>, after: Link<T>) {
+    for observer in self.interested(before, after) {
+      observer.notify(before, after);
+    }
+  }
+
+  /// Observers whose filter matches either side of the change
+  fn interested(
+    &mut self,
+    before: Link<T>,
+    after: Link<T>,
+  ) -> impl Iterator<Item = &mut Boxed<T>> {
+    self.entries.iter_mut().filter_map(move |(_, filter, observer)| {
+      (matches(*filter, before) || matches(*filter, after)).then_some(observer)
+    })
+  }
+}
+
+thread_local! {
+  static UNDOS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Undo running on this thread, whose writes observers cannot veto
+///
+/// Undos may nest, vetoes come back once the outermost one is dropped.
+pub(crate) struct Undo(());
+
+impl Undo {
+  pub(crate) fn start() -> Self {
+    UNDOS.set(UNDOS.get() + 1);
+    Self(())
+  }
+
+  fn running() -> bool {
+    UNDOS.get() != 0
+  }
+}
+
+impl Drop for Undo {
+  fn drop(&mut self) {
+    UNDOS.set(UNDOS.get() - 1);
+  }
+}
+
+/// Check `link` against an `[index, source, target]` query
+fn matches<T: Index>(filter: [T; 3], link: Link<T>) -> bool {
+  let fields = [link.index, link.source, link.target];
+  !link.is_null()
+    && filter
+      .iter()
+      .zip(fields)
+      .all(|(&want, got)| want == T::ANY || want == got)
+}
@@ -0,0 +1,287 @@
+use crate::{Flow, Index, Link, Links};
+
+/// One part of a [`Pattern`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term<T> {
+  /// Exactly this link
+  Const(T),
+  /// Whatever link the variable is bound to, binding it on first use
+  Var(String),
+  /// Any link, without binding anything
+  Any,
+}
+
+impl<T> Term<T> {
+  /// Variable called `name`
+  pub fn var(name: impl Into<String>) -> Self {
+    Term::Var(name.into())
+  }
+}
+
+/// A link shape to match, `[index, source, target]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern<T> {
+  pub index: Term<T>,
+  pub source: Term<T>,
+  pub target: Term<T>,
+}
+
+impl<T> Pattern<T> {
+  /// Pattern over all three parts of a link
+  pub fn new(index: Term<T>, source: Term<T>, target: Term<T>) -> Self {
+    Self { index, source, target }
+  }
+
+  /// Pattern for any link from `source` to `target`, `source -> target`
+  pub fn link(source: Term<T>, target: Term<T>) -> Self {
+    Self::new(Term::Any, source, target)
+  }
+}
+
+/// A pattern part once variables are numbered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot<T> {
+  Const(T),
+  Var(usize),
+  Any,
+}
+
+/// Values bound to the variables of a [`Query`] by one match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bindings<'q, T> {
+  names: &'q [String],
+  values: Vec<T>,
+}
+
+impl<T: Index> Bindings<'_, T> {
+  /// Value of the variable `name`
+  pub fn get(&self, name: &str) -> Option<T> {
+    let slot = self.names.iter().position(|var| var == name)?;
+    Some(self.values[slot])
+  }
+
+  /// Variables with their values, in order of first use in the query
+  pub fn iter(&self) -> impl Iterator<Item = (&str, T)> + '_ {
+    self.names.iter().map(String::as_str).zip(self.values.iter().copied())
+  }
+}
+
+/// Conjunction of link patterns sharing named variables
+///
+/// A match binds every variable to one link such that each pattern,
+/// with its variables replaced, matches a link of the store. Patterns
+/// are joined in the order [`plan`](Self::plan) picks: the next pattern is
+/// one that shares a variable with those before it, if any does, and the
+/// cheapest of them by [`Links::count`] over its constants, with the
+/// variables bound before it counted as constants too. Each pattern
+/// then runs once per partial match with its bound variables filled in,
+/// so the store answers it from the source or target tree, or by index.
+///
+/// # Examples
+/// ```
+/// use doublets::{Doublets, Pattern, Query, Term, create_heap_store};
+///
+/// let mut store = create_heap_store::<usize>()?;
+/// let [a, b, c] = [(); 3].map(|_| store.create_point().unwrap());
+/// store.create_link(a, b)?;
+/// store.create_link(b, c)?;
+/// store.create_link(a, c)?;
+///
+/// // ?x -> ?y, ?y -> c
+/// let query = Query::new()
+///   .pattern(Pattern::link(Term::var("x"), Term::var("y")))
+///   .pattern(Pattern::link(Term::var("y"), Term::Const(c)));
+/// let found: Vec<_> = query
+///   .run(&store)
+///   .iter()
+///   .map(|found| (found.get("x").unwrap(), found.get("y").unwrap()))
+///   // Points are links too, skip the matches that stand still on one
+///   .filter(|&(x, y)| x != y && y != c)
+///   .collect();
+/// assert_eq!(found, [(a, b)]);
+/// # Ok::<(), doublets::Error<usize>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query<T> {
+  names: Vec<String>,
+  patterns: Vec<[Slot<T>; 3]>,
+}
+
+impl<T: Index> Default for Query<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: Index> Query<T> {
+  /// Query without patterns, which matches once with no bindings
+  pub fn new() -> Self {
+    Self { names: Vec::new(), patterns: Vec::new() }
+  }
+
+  /// Add a pattern every match has to satisfy as well
+  #[must_use]
+  pub fn pattern(mut self, pattern: Pattern<T>) -> Self {
+    let Pattern { index, source, target } = pattern;
+    let slots = [index, source, target].map(|term| match term {
+      Term::Const(value) => Slot::Const(value),
+      Term::Any => Slot::Any,
+      Term::Var(name) => {
+        let slot = self.names.iter().position(|var| *var == name);
+        Slot::Var(slot.unwrap_or_else(|| {
+          self.names.push(name);
+          self.names.len() - 1
+        }))
+      }
+    });
+    self.patterns.push(slots);
+    self
+  }
+
+  /// Variables in order of first use
+  pub fn variables(&self) -> &[String] {
+    &self.names
+  }
+
+  /// Order in which the patterns are joined, as positions in the query
+  pub fn plan<L: Links<T> + ?Sized>(&self, links: &L) -> Vec<usize> {
+    let mut bound = vec![false; self.names.len()];
+    let mut left: Vec<_> = (0..self.patterns.len()).collect();
+    let mut order = Vec::with_capacity(left.len());
+
+    while !left.is_empty() {
+      let pick = |&&position: &&usize| {
+        let slots = &self.patterns[position];
+        let joined = slots.iter().any(|slot| match slot {
+          Slot::Var(var) => bound[*var],
+          _ => false,
+        });
+        (!joined, self.estimate(links, slots, &bound))
+      };
+      let next = *left
+        .iter()
+        .min_by(|a, b| {
+          let ((a_far, a_cost), (b_far, b_cost)) = (pick(a), pick(b));
+          a_far.cmp(&b_far).then(a_cost.total_cmp(&b_cost))
+        })
+        .unwrap();
+
+      left.retain(|&position| position != next);
+      for slot in self.patterns[next] {
+        if let Slot::Var(var) = slot {
+          bound[var] = true;
+        }
+      }
+      order.push(next);
+    }
+    order
+  }
+
+  /// Call `handler` with every match until it returns [`Flow::Break`]
+  pub fn each<L, H>(&self, links: &L, mut handler: H) -> Flow
+  where
+    L: Links<T> + ?Sized,
+    H: FnMut(&Bindings<'_, T>) -> Flow,
+  {
+    let order = self.plan(links);
+    let mut values = vec![None; self.names.len()];
+    self.join(links, &order, &mut values, &mut handler)
+  }
+
+  /// Collect every match
+  pub fn run<L: Links<T> + ?Sized>(&self, links: &L) -> Vec<Bindings<'_, T>> {
+    let mut found = Vec::new();
+    self.each(links, |bindings| {
+      found
+        .push(Bindings { names: &self.names, values: bindings.values.clone() });
+      Flow::Continue
+    });
+    found
+  }
+
+  /// Links a pattern is expected to visit, with its index as the cheapest
+  ///
+  /// A bound variable is a constant once the pattern runs. Over all the
+  /// links it may be bound to, it leaves the count over the other parts
+  /// as is, so it is estimated to cut that count by the number of links.
+  fn estimate<L>(&self, links: &L, slots: &[Slot<T>; 3], bound: &[bool]) -> f64
+  where
+    L: Links<T> + ?Sized,
+  {
+    match slots[0] {
+      Slot::Const(_) => return 1.0,
+      Slot::Var(var) if bound[var] => return 1.0,
+      _ => {}
+    }
+    let query = slots.map(|slot| match slot {
+      Slot::Const(value) => value,
+      _ => T::ANY,
+    });
+    let total = links.count([]).as_usize().max(1) as f64;
+    let constants = slots[1..]
+      .iter()
+      .filter(|slot| matches!(slot, Slot::Var(var) if bound[*var]))
+      .count();
+    links.count(query).as_usize() as f64 / total.powi(constants as i32)
+  }
+
+  fn join<L, H>(
+    &self,
+    links: &L,
+    order: &[usize],
+    values: &mut Vec<Option<T>>,
+    handler: &mut H,
+  ) -> Flow
+  where
+    L: Links<T> + ?Sized,
+    H: FnMut(&Bindings<'_, T>) -> Flow,
+  {
+    let Some((&next, rest)) = order.split_first() else {
+      let values = values.iter().map(|value| value.unwrap()).collect();
+      return handler(&Bindings { names: &self.names, values });
+    };
+    let slots = self.patterns[next];
+    let query = slots.map(|slot| match slot {
+      Slot::Const(value) => value,
+      Slot::Var(var) => values[var].unwrap_or(T::ANY),
+      Slot::Any => T::ANY,
+    });
+
+    let mut visit = |link: Link<T>| {
+      let parts = [link.index, link.source, link.target];
+      let mut newly = Vec::new();
+      let mut matches = true;
+      for (slot, part) in slots.iter().zip(parts) {
+        match *slot {
+          Slot::Const(value) => matches &= value == part,
+          Slot::Var(var) => match values[var] {
+            Some(value) => matches &= value == part,
+            None => {
+              values[var] = Some(part);
+              newly.push(var);
+            }
+          },
+          Slot::Any => {}
+        }
+      }
+      let flow = if matches {
+        self.join(links, rest, values, handler)
+      } else {
+        Flow::Continue
+      };
+      for var in newly {
+        values[var] = None;
+      }
+      flow
+    };
+
+    if query[0] != T::ANY {
+      match links.get(query[0]) {
+        Some(link) => visit(link),
+        None => Flow::Continue,
+      }
+    } else {
+      links.each([T::ANY, query[1], query[2]], &mut visit)
+    }
+  }
+}
@@ -0,0 +1,359 @@
+use {
+  crate::{
+    Doublets, Error, Flow, Index, Link, Links, ReadHandler, Result, Sequences,
+    WriteHandler,
+    constants::{self, PROPERTY, TYPE},
+    transaction::revert,
+  },
+  std::marker::PhantomData,
+};
+
+/// Named types and properties kept in the store they describe
+///
+/// Declarations are links, so every service that opens the store sees the
+/// same model:
+///
+/// * a type is `(TYPE, name)` and an entity `e` of type `ty` is `(ty, e)`
+/// * a property is `(PROPERTY, name)`, its domain `(property, domain)`
+///   and its optional range `((property, domain), range)`
+/// * a triple is `((entity, property), value)`
+///
+/// Names are strings stored through [`Sequences`]. Every write made
+/// through the wrapper, including the plain [`Links`] operations, passes
+/// the `before`/`after` pairs its `WriteHandler` receives to the schema
+/// check. A write that attaches a property to an entity outside its
+/// domain, gives it a value outside its range, or removes the type an
+/// existing triple relies on is reverted and fails with
+/// [`Error::SchemaViolation`].
+///
+/// # Examples
+/// ```
+/// use doublets::{Error, Schema, Sequences, create_heap_store};
+///
+/// let mut schema = Schema::new(create_heap_store::<usize>()?)?;
+/// let person = schema.declare_type("person")?;
+/// let city = schema.declare_type("city")?;
+/// let lives_in = schema.declare_property("lives in", person, Some(city))?;
+///
+/// let alice = schema.create_entity(person)?;
+/// let paris = schema.create_entity(city)?;
+/// schema.attach(alice, lives_in, paris)?;
+/// assert_eq!(schema.values(alice, lives_in), [paris]);
+///
+/// // A city does not live anywhere
+/// let result = schema.attach(paris, lives_in, paris);
+/// assert!(matches!(result, Err(Error::SchemaViolation(_))));
+/// # Ok::<(), Error<usize>>(())
+/// ```
+pub struct Schema<T: Index, S: Links<T>> {
+  store: S,
+  _phantom: PhantomData<T>,
+}
+
+impl<T: Index, S: Links<T>> Schema<T, S> {
+  /// Wrap `store`, reserving the [constants](crate::constants) it needs
+  pub fn new(mut store: S) -> Result<Self, T> {
+    constants::reserve(&mut store)?;
+    Ok(Self { store, _phantom: PhantomData })
+  }
+
+  /// Get the wrapped store
+  pub fn store(&self) -> &S {
+    &self.store
+  }
+
+  /// Unwrap the store
+  pub fn into_inner(self) -> S {
+    self.store
+  }
+
+  /// Declare a type, or find the type declared with this name
+  pub fn declare_type(&mut self, name: &str) -> Result<T, T> {
+    let name = self.create_string(name)?;
+    self.get_or_create(constants::get(TYPE)?, name)
+  }
+
+  /// Declare a property of entities of type `domain`
+  ///
+  /// With a `range`, values attached through the property must be
+  /// entities of that type. Declaring an existing property again returns
+  /// it if the domain and range match, and fails with
+  /// [`Error::SchemaViolation`] otherwise.
+  pub fn declare_property(
+    &mut self,
+    name: &str,
+    domain: T,
+    range: Option<T>,
+  ) -> Result<T, T> {
+    for ty in [Some(domain), range].into_iter().flatten() {
+      if !self.is_type(ty) {
+        return Err(Error::SchemaViolation(ty));
+      }
+    }
+    let name = self.create_string(name)?;
+    let property = self.get_or_create(constants::get(PROPERTY)?, name)?;
+
+    match self.signature(property) {
+      Some(existing) if existing == (domain, range) => Ok(property),
+      Some(_) => Err(Error::SchemaViolation(property)),
+      None => {
+        let signature = self.create_link(property, domain)?;
+        if let Some(range) = range {
+          self.create_link(signature, range)?;
+        }
+        Ok(property)
+      }
+    }
+  }
+
+  /// Find the type declared with `name`
+  pub fn type_named(&self, name: &str) -> Option<T> {
+    self.named(TYPE, name)
+  }
+
+  /// Find the property declared with `name`
+  pub fn property_named(&self, name: &str) -> Option<T> {
+    self.named(PROPERTY, name)
+  }
+
+  /// Create a new entity of type `ty`
+  pub fn create_entity(&mut self, ty: T) -> Result<T, T> {
+    if !self.is_type(ty) {
+      return Err(Error::SchemaViolation(ty));
+    }
+    let entity = self.create_point()?;
+    self.create_link(ty, entity)?;
+    Ok(entity)
+  }
+
+  /// Give an existing link the type `ty` as well
+  pub fn set_type(&mut self, entity: T, ty: T) -> Result<T, T> {
+    if !self.is_type(ty) {
+      return Err(Error::SchemaViolation(ty));
+    }
+    self.get_or_create(ty, entity)
+  }
+
+  /// Check whether `entity` has the type `ty`
+  pub fn has_type(&self, entity: T, ty: T) -> bool {
+    self.search(ty, entity).is_some()
+  }
+
+  /// All entities of type `ty`
+  pub fn entities(&self, ty: T) -> Vec<T> {
+    let mut entities = Vec::new();
+    self.each([T::ANY, ty, T::ANY], &mut |link: Link<T>| {
+      entities.push(link.target);
+      Flow::Continue
+    });
+    entities
+  }
+
+  /// Attach `value` to `entity` through `property`, returning the triple
+  pub fn attach(&mut self, entity: T, property: T, value: T) -> Result<T, T> {
+    if !self.is_property(property) {
+      return Err(Error::SchemaViolation(property));
+    }
+    let existing = self.search(entity, property);
+    let pair = match existing {
+      Some(pair) => pair,
+      None => self.create_link(entity, property)?,
+    };
+    let triple = self.get_or_create(pair, value);
+    if triple.is_err() && existing.is_none() {
+      self.delete_link(pair)?;
+    }
+    triple
+  }
+
+  /// Values attached to `entity` through `property`
+  pub fn values(&self, entity: T, property: T) -> Vec<T> {
+    let mut values = Vec::new();
+    if let Some(pair) = self.search(entity, property) {
+      self.each([T::ANY, pair, T::ANY], &mut |link: Link<T>| {
+        values.push(link.target);
+        Flow::Continue
+      });
+    }
+    values
+  }
+
+  fn named(&self, marker: usize, name: &str) -> Option<T> {
+    let marker = constants::get(marker).ok()?;
+    let mut found = None;
+    self.each([T::ANY, marker, T::ANY], &mut |link: Link<T>| {
+      if self.read_string(link.target).is_ok_and(|text| text == name) {
+        found = Some(link.index);
+        return Flow::Break;
+      }
+      Flow::Continue
+    });
+    found
+  }
+
+  fn declared_as(&self, index: T, marker: usize) -> bool {
+    constants::get(marker).is_ok_and(|marker: T| {
+      index != marker
+        && self.get(index).is_some_and(|link| link.source == marker)
+    })
+  }
+
+  fn is_type(&self, index: T) -> bool {
+    self.declared_as(index, TYPE)
+  }
+
+  fn is_property(&self, index: T) -> bool {
+    self.declared_as(index, PROPERTY)
+  }
+
+  /// Domain and range of `property`
+  fn signature(&self, property: T) -> Option<(T, Option<T>)> {
+    let link = self.first([T::ANY, property, T::ANY])?;
+    let range = self.first([T::ANY, link.index, T::ANY]);
+    Some((link.target, range.map(|range| range.target)))
+  }
+
+  fn first<const N: usize>(&self, query: [T; N]) -> Option<Link<T>> {
+    let mut found = None;
+    self.each(query, &mut |link: Link<T>| {
+      found = Some(link);
+      Flow::Break
+    });
+    found
+  }
+
+  /// Check a change the store has already applied
+  fn check(&self, before: Link<T>, after: Link<T>) -> Result<(), T> {
+    if !after.is_null() && self.get(after.index) == Some(after) {
+      self.check_added(after)?;
+    }
+    if !before.is_null()
+      && before != after
+      && self.is_type(before.source)
+      && !self.has_type(before.target, before.source)
+    {
+      self.check_untyped(before)?;
+    }
+    Ok(())
+  }
+
+  /// `(entity, property)` needs the domain, `(pair, value)` the range
+  fn check_added(&self, link: Link<T>) -> Result<(), T> {
+    if self.is_property(link.target)
+      && let Some((domain, _)) = self.signature(link.target)
+      && !self.has_type(link.source, domain)
+    {
+      return Err(Error::SchemaViolation(link.index));
+    }
+    if let Some(pair) = self.get(link.source)
+      && pair.index != pair.source
+      && self.is_property(pair.target)
+      && let Some((_, Some(range))) = self.signature(pair.target)
+      && !self.has_type(link.target, range)
+    {
+      return Err(Error::SchemaViolation(link.index));
+    }
+    Ok(())
+  }
+
+  /// No triple may rely on the type `typing` gave its entity
+  fn check_untyped(&self, typing: Link<T>) -> Result<(), T> {
+    let (ty, entity) = (typing.source, typing.target);
+    let mut valid = true;
+    self.each([T::ANY, entity, T::ANY], &mut |link: Link<T>| {
+      valid = !self.is_property(link.target)
+        || self.signature(link.target).is_none_or(|(domain, _)| domain != ty);
+      Flow::from(valid)
+    });
+    self.each([T::ANY, T::ANY, entity], &mut |link: Link<T>| {
+      valid = valid
+        && self.get(link.source).is_none_or(|pair| {
+          !self.is_property(pair.target)
+            || self
+              .signature(pair.target)
+              .is_none_or(|(_, range)| range != Some(ty))
+        });
+      Flow::from(valid)
+    });
+    if valid { Ok(()) } else { Err(Error::SchemaViolation(typing.index)) }
+  }
+
+  /// Apply a write, reverting it when a change breaks the schema
+  fn write<H, F>(&mut self, handler: &mut H, op: F) -> Result<Flow, T>
+  where
+    H: WriteHandler<T>,
+    F: FnOnce(
+      &mut S,
+      &mut dyn FnMut(Link<T>, Link<T>) -> Flow,
+    ) -> Result<Flow, T>,
+  {
+    let mut changes = Vec::new();
+    let flow = op(&mut self.store, &mut |before, after| {
+      changes.push((before, after));
+      Flow::Continue
+    })?;
+
+    let checked =
+      changes.iter().try_for_each(|&(before, after)| self.check(before, after));
+    if let Err(err) = checked {
+      revert(&mut self.store, &changes)?;
+      return Err(err);
+    }
+
+    for (before, after) in changes {
+      if handler.handle(before, after) == Flow::Break {
+        return Ok(Flow::Break);
+      }
+    }
+    Ok(flow)
+  }
+}
+
+impl<T: Index, S: Links<T>> Links<T> for Schema<T, S> {
+  fn count<const N: usize>(&self, query: [T; N]) -> T {
+    self.store.count(query)
+  }
+
+  fn create<const N: usize, H: WriteHandler<T>>(
+    &mut self,
+    query: [T; N],
+    handler: &mut H,
+  ) -> Result<Flow, T> {
+    self.write(handler, |store, mut handler| store.create(query, &mut handler))
+  }
+
+  fn each<const N: usize, H: ReadHandler<T>>(
+    &self,
+    query: [T; N],
+    handler: &mut H,
+  ) -> Flow {
+    self.store.each(query, handler)
+  }
+
+  fn update<const N1: usize, const N2: usize, H: WriteHandler<T>>(
+    &mut self,
+    query: [T; N1],
+    change: [T; N2],
+    handler: &mut H,
+  ) -> Result<Flow, T> {
+    self.write(handler, |store, mut handler| {
+      store.update(query, change, &mut handler)
+    })
+  }
+
+  fn delete<const N: usize, H: WriteHandler<T>>(
+    &mut self,
+    query: [T; N],
+    handler: &mut H,
+  ) -> Result<Flow, T> {
+    self.write(handler, |store, mut handler| store.delete(query, &mut handler))
+  }
+
+  fn get(&self, index: T) -> Option<Link<T>> {
+    self.store.get(index)
+  }
+
+  fn next_index(&self) -> Option<T> {
+    self.store.next_index()
+  }
+}
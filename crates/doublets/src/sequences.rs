@@ -0,0 +1,139 @@
+use crate::{
+  Doublets, Error, Index, Link, Result,
+  constants::{self, UNICODE_SEQUENCE, UNICODE_SYMBOL},
+  numbers,
+};
+
+/// Deepest pair tree a well-formed sequence of any length can have
+const MAX_DEPTH: usize = usize::BITS as usize + 1;
+
+/// Text and sequences stored as links
+///
+/// A sequence of links is stored as a tree of pairs whose left part always
+/// covers the largest power of two shorter than the sequence. Equal runs
+/// at aligned positions therefore build equal subtrees, and since every
+/// pair goes through [`get_or_create`](Doublets::get_or_create), strings
+/// with a common prefix share the links of that prefix.
+///
+/// Characters are chains that start at `UNICODE_SYMBOL` and append the
+/// bits of the code point, most significant first, as
+/// [`ZERO`](crate::constants::ZERO) and [`ONE`](crate::constants::ONE)
+/// points: `'a'` is `((((((SYMBOL, 1), 1), 0), 0), 0), 0), 1)`. Chains never
+/// pair a point with itself, which a unique store could not hold apart from
+/// the point, and characters with the same high bits share their links.
+/// Strings are `(UNICODE_SEQUENCE, tree)` links over their characters, the
+/// empty string is the `UNICODE_SEQUENCE` point itself.
+///
+/// # Examples
+/// ```
+/// use doublets::{Sequences, create_heap_store};
+///
+/// let mut store = create_heap_store::<usize>()?;
+/// let hello = store.create_string("hello")?;
+/// assert_eq!(store.create_string("hello")?, hello);
+/// assert_eq!(store.read_string(hello)?, "hello");
+/// # Ok::<(), doublets::Error<usize>>(())
+/// ```
+pub trait Sequences<T: Index>: Doublets<T> {
+  /// Make sure every [reserved constant](crate::constants) is in place
+  ///
+  /// Missing constants are created, which only succeeds while they are
+  /// the next indices the store hands out. Fails with
+  /// [`Error::Reserved`] when a constant index holds another link.
+  fn reserve(&mut self) -> Result<(), T> {
+    constants::reserve(self)
+  }
+
+  /// Store a tree of pairs over `items`, `None` when `items` is empty
+  fn create_sequence(&mut self, items: &[T]) -> Result<Option<T>, T> {
+    match items {
+      [] => Ok(None),
+      [item] => Ok(Some(*item)),
+      _ => {
+        let half = 1 << (items.len() - 1).ilog2();
+        let left = self.create_sequence(&items[..half])?;
+        let right = self.create_sequence(&items[half..])?;
+        self.get_or_create(left.unwrap(), right.unwrap()).map(Some)
+      }
+    }
+  }
+
+  /// Store one character
+  fn create_char(&mut self, char: char) -> Result<T, T> {
+    self.reserve()?;
+    let symbol = constants::get(UNICODE_SYMBOL)?;
+    numbers::create_chain(self, symbol, char as u64)
+  }
+
+  /// Store a string, reusing the links of any part stored before
+  fn create_string(&mut self, text: &str) -> Result<T, T> {
+    self.reserve()?;
+    let mut chars = Vec::with_capacity(text.len());
+    for char in text.chars() {
+      chars.push(self.create_char(char)?);
+    }
+    let marker = constants::get(UNICODE_SEQUENCE)?;
+    match self.create_sequence(&chars)? {
+      Some(tree) => self.get_or_create(marker, tree),
+      None => Ok(marker),
+    }
+  }
+
+  /// Read back the elements of a tree built by
+  /// [`create_sequence`](Self::create_sequence)
+  ///
+  /// `is_item` tells elements from pairs, a link it accepts is not split.
+  fn read_sequence(
+    &self,
+    root: T,
+    mut is_item: impl FnMut(Link<T>) -> bool,
+  ) -> Result<Vec<T>, T> {
+    let mut items = Vec::new();
+    let mut stack = vec![(root, 0)];
+    while let Some((index, depth)) = stack.pop() {
+      let link = self.get(index).ok_or(Error::NotExists(index))?;
+      if is_item(link) {
+        items.push(index);
+      } else if depth < MAX_DEPTH && link != Link::point(index) {
+        stack.push((link.target, depth + 1));
+        stack.push((link.source, depth + 1));
+      } else {
+        return Err(Error::InvalidSequence(root));
+      }
+    }
+    Ok(items)
+  }
+
+  /// Read a character stored with [`create_char`](Self::create_char)
+  fn read_char(&self, index: T) -> Result<char, T> {
+    let symbol = constants::get(UNICODE_SYMBOL)?;
+    let (_, code) =
+      numbers::read_chain(self, index, &[symbol]).map_err(|err| match err {
+        Error::InvalidNumber(_) => Error::InvalidSequence(index),
+        err => err,
+      })?;
+    u32::try_from(code)
+      .ok()
+      .and_then(char::from_u32)
+      .ok_or(Error::InvalidSequence(index))
+  }
+
+  /// Read a string stored with [`create_string`](Self::create_string)
+  fn read_string(&self, index: T) -> Result<String, T> {
+    let link = self.get(index).ok_or(Error::NotExists(index))?;
+    let marker = constants::get(UNICODE_SEQUENCE)?;
+    if index == marker {
+      return Ok(String::new());
+    }
+    if link.source != marker {
+      return Err(Error::InvalidSequence(index));
+    }
+    self
+      .read_sequence(link.target, |link| self.read_char(link.index).is_ok())?
+      .into_iter()
+      .map(|char| self.read_char(char))
+      .collect()
+  }
+}
+
+impl<T: Index, S: Doublets<T> + ?Sized> Sequences<T> for S {}
@@ -0,0 +1,370 @@
+use {
+  crate::{Doublets, Error, Index, Link, Links, Result},
+  std::io::{self, Read, Write},
+};
+
+/// Portable binary snapshot of a links store
+///
+/// Snapshots do not depend on the index width, struct layout or byte order
+/// of the store that wrote them, so they move data between machines and
+/// between stores of different index types. The stream is laid out as:
+///
+/// | Field    | Encoding                                              |
+/// |----------|-------------------------------------------------------|
+/// | magic    | `b"DBLS"`                                             |
+/// | version  | one byte, currently `1`                               |
+/// | flags    | one byte, bit 0 set when a checksum follows the links |
+/// | width    | one byte, index width of the writer in bytes          |
+/// | count    | varint number of links                                |
+/// | links    | per link: varint index delta, source and target       |
+/// | checksum | CRC-32 of all preceding bytes, little-endian          |
+///
+/// Varints are unsigned LEB128, and links are written in index order with
+/// each index stored as the distance from the previous one. Freed indices
+/// below the last link show up as gaps, and a stream with more of them
+/// than links is rejected on import, so a small stream cannot make the
+/// store recreate an unbounded number of placeholders.
+///
+/// # Examples
+/// ```
+/// use doublets::{Doublets, create_heap_store};
+///
+/// let mut store = create_heap_store::<u64>()?;
+/// let a = store.create_point()?;
+/// store.create_link(a, a)?;
+///
+/// let mut bytes = Vec::new();
+/// store.export(&mut bytes).unwrap();
+///
+/// let mut narrow = create_heap_store::<u16>().unwrap();
+/// narrow.import(bytes.as_slice()).unwrap();
+/// assert_eq!(narrow.count_all(), 2);
+/// # Ok::<(), doublets::Error<u64>>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+  checksum: bool,
+}
+
+impl Default for Snapshot {
+  fn default() -> Self {
+    Self { checksum: true }
+  }
+}
+
+impl Snapshot {
+  const MAGIC: [u8; 4] = *b"DBLS";
+  const VERSION: u8 = 1;
+  const FLAG_CHECKSUM: u8 = 1;
+
+  /// Snapshot settings with a checksum
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Choose whether exported streams end with a checksum
+  #[must_use]
+  pub fn with_checksum(mut self, checksum: bool) -> Self {
+    self.checksum = checksum;
+    self
+  }
+
+  /// Write every link of `links` to `writer`
+  pub fn export<T, L, W>(self, links: &L, writer: W) -> Result<(), T>
+  where
+    T: Index,
+    L: Links<T> + ?Sized,
+    W: Write,
+  {
+    let mut all = links.collect_all();
+    all.sort_unstable_by_key(|link| link.index);
+
+    let mut out = Crc32Writer { inner: writer, crc: Crc32::new() };
+    let flags = if self.checksum { Self::FLAG_CHECKSUM } else { 0 };
+    out.write_all(&Self::MAGIC)?;
+    out.write_all(&[Self::VERSION, flags, size_of::<T>() as u8])?;
+    write_varint(&mut out, all.len() as u64)?;
+
+    let mut previous = 0;
+    for link in all {
+      let index = link.index.as_usize();
+      write_varint(&mut out, (index - previous) as u64)?;
+      write_varint(&mut out, link.source.as_usize() as u64)?;
+      write_varint(&mut out, link.target.as_usize() as u64)?;
+      previous = index;
+    }
+
+    if self.checksum {
+      let crc = out.crc.finish();
+      out.inner.write_all(&crc.to_le_bytes())?;
+    }
+    out.inner.flush()?;
+    Ok(())
+  }
+
+  /// Read a snapshot from `reader` into the empty store `links`
+  ///
+  /// The whole stream is decoded and its checksum verified before any
+  /// link is written. Links are then created in index order, so every
+  /// index of the store is rebuilt along the way. Indices freed before the
+  /// export are recreated and deleted again, keeping link numbers intact,
+  /// and links to missing indices are restored as they were.
+  ///
+  /// Fails with [`Error::NotEmpty`] unless `links` has never held links,
+  /// [`Error::InvalidSnapshot`] on malformed input and [`Error::Overflow`]
+  /// when an index does not fit `T`. A store that does not hand out
+  /// indices in order fails with [`Error::OutOfOrder`] while they are
+  /// reserved. If writing fails at any point, e.g. on a uniqueness
+  /// conflict, every link created so far is deleted again, leaving the
+  /// store empty.
+  pub fn import<T, L, R>(links: &mut L, reader: R) -> Result<(), T>
+  where
+    T: Index,
+    L: Links<T> + ?Sized,
+    R: Read,
+  {
+    let used = links.next_index().is_some_and(|next| next != T::ONE);
+    if !links.count_all().is_zero() || used {
+      return Err(Error::NotEmpty);
+    }
+    let records = Self::decode::<T, R>(reader)?;
+
+    // Reserve every index as a point first, so placeholders for freed
+    // indices are unreferenced when deleted, then link them up
+    let mut reserved = Vec::new();
+    let result = Self::reserve(links, &records, &mut reserved)
+      .and_then(|()| Self::link_up(links, &records, &reserved));
+    if let Err(err) = result {
+      Self::unreserve(links, &reserved)?;
+      return Err(err);
+    }
+    Ok(())
+  }
+
+  /// Decode and verify a whole stream
+  fn decode<T: Index, R: Read>(reader: R) -> Result<Vec<Link<T>>, T> {
+    let mut input = Crc32Reader { inner: reader, crc: Crc32::new() };
+
+    let mut header = [0; 7];
+    read_exact(&mut input, &mut header)?;
+    let flags = header[5];
+    if header[..4] != Self::MAGIC
+      || header[4] != Self::VERSION
+      || flags & !Self::FLAG_CHECKSUM != 0
+    {
+      return Err(Error::InvalidSnapshot);
+    }
+
+    let count = read_varint(&mut input)?;
+    // Every link takes at least three bytes, which bounds the allocation
+    let mut records = Vec::with_capacity(count.min(1 << 20) as usize);
+    let mut index = 0usize;
+    for _ in 0..count {
+      let delta = read_varint(&mut input)?;
+      if delta == 0 {
+        return Err(Error::InvalidSnapshot);
+      }
+      index = usize::try_from(delta)
+        .ok()
+        .and_then(|delta| index.checked_add(delta))
+        .ok_or(Error::Overflow)?;
+      // Indices below this one that are not links are gaps
+      let gaps = index - records.len() - 1;
+      if gaps as u64 > count {
+        return Err(Error::InvalidSnapshot);
+      }
+      let source = read_varint(&mut input)?;
+      let target = read_varint(&mut input)?;
+      records.push(Link::new(
+        convert(index as u64)?,
+        convert(source)?,
+        convert(target)?,
+      ));
+    }
+
+    if flags & Self::FLAG_CHECKSUM != 0 {
+      let expected = input.crc.finish();
+      let mut crc = [0; 4];
+      read_exact(&mut input.inner, &mut crc)?;
+      if u32::from_le_bytes(crc) != expected {
+        return Err(Error::InvalidSnapshot);
+      }
+    }
+    Ok(records)
+  }
+
+  /// Create a point for every index up to the last record, in order
+  fn reserve<T, L>(
+    links: &mut L,
+    records: &[Link<T>],
+    reserved: &mut Vec<T>,
+  ) -> Result<(), T>
+  where
+    T: Index,
+    L: Links<T> + ?Sized,
+  {
+    let last = records.last().map_or(0, |link| link.index.as_usize());
+    for index in 1..=last {
+      let created = links.create_point()?;
+      reserved.push(created);
+      if created.as_usize() != index {
+        return Err(Error::OutOfOrder(created));
+      }
+    }
+    Ok(())
+  }
+
+  /// Delete the placeholders of gaps and point links at their ends
+  fn link_up<T, L>(
+    links: &mut L,
+    records: &[Link<T>],
+    reserved: &[T],
+  ) -> Result<(), T>
+  where
+    T: Index,
+    L: Links<T> + ?Sized,
+  {
+    // Placeholders left over are the gaps, deleted highest first
+    let mut kept = records.iter().rev().map(|link| link.index).peekable();
+    for &index in reserved.iter().rev() {
+      if kept.next_if_eq(&index).is_none() {
+        links.delete_link(index)?;
+      }
+    }
+    for link in records {
+      if link.source != link.index || link.target != link.index {
+        links.update_link(link.index, link.source, link.target)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Delete the reserved links again, highest first
+  ///
+  /// Placeholders of gaps already deleted are created again from the
+  /// slots they freed and every link is turned back into a point, so
+  /// nothing is referenced when it is deleted and no slot stays freed.
+  fn unreserve<T, L>(links: &mut L, reserved: &[T]) -> Result<(), T>
+  where
+    T: Index,
+    L: Links<T> + ?Sized,
+  {
+    for &index in reserved {
+      match links.get(index) {
+        Some(link) if link.source != index || link.target != index => {
+          links.update_link(index, index, index)?;
+        }
+        Some(_) => {}
+        None => {
+          links.create_point()?;
+        }
+      }
+    }
+    for &index in reserved.iter().rev() {
+      links.delete_link(index)?;
+    }
+    Ok(())
+  }
+}
+
+fn convert<T: Index>(value: u64) -> Result<T, T> {
+  usize::try_from(value).ok().and_then(T::try_from_usize).ok_or(Error::Overflow)
+}
+
+fn read_exact<T: Index>(
+  reader: &mut impl Read,
+  buf: &mut [u8],
+) -> Result<(), T> {
+  reader.read_exact(buf).map_err(|err| match err.kind() {
+    io::ErrorKind::UnexpectedEof => Error::InvalidSnapshot,
+    _ => err.into(),
+  })
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+  let mut buf = [0; 10];
+  let mut len = 0;
+  loop {
+    let byte = (value & 0x7F) as u8;
+    value >>= 7;
+    if value == 0 {
+      buf[len] = byte;
+      len += 1;
+      break;
+    }
+    buf[len] = byte | 0x80;
+    len += 1;
+  }
+  writer.write_all(&buf[..len])
+}
+
+fn read_varint<T: Index>(reader: &mut impl Read) -> Result<u64, T> {
+  let mut value = 0u64;
+  for shift in (0..64).step_by(7) {
+    let mut byte = [0];
+    read_exact(reader, &mut byte)?;
+    let bits = (byte[0] & 0x7F) as u64;
+    if bits << shift >> shift != bits {
+      return Err(Error::InvalidSnapshot);
+    }
+    value |= bits << shift;
+    if byte[0] & 0x80 == 0 {
+      return Ok(value);
+    }
+  }
+  Err(Error::InvalidSnapshot)
+}
+
+/// CRC-32 (IEEE) computed bit by bit, which is plenty for snapshot sizes
+#[derive(Debug, Clone, Copy)]
+struct Crc32(u32);
+
+impl Crc32 {
+  fn new() -> Self {
+    Self(!0)
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.0 ^= byte as u32;
+      for _ in 0..8 {
+        let mask = (self.0 & 1).wrapping_neg();
+        self.0 = (self.0 >> 1) ^ (0xEDB8_8320 & mask);
+      }
+    }
+  }
+
+  fn finish(self) -> u32 {
+    !self.0
+  }
+}
+
+struct Crc32Writer<W> {
+  inner: W,
+  crc: Crc32,
+}
+
+impl<W: Write> Write for Crc32Writer<W> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let written = self.inner.write(buf)?;
+    self.crc.update(&buf[..written]);
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
+
+struct Crc32Reader<R> {
+  inner: R,
+  crc: Crc32,
+}
+
+impl<R: Read> Read for Crc32Reader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.crc.update(&buf[..read]);
+    Ok(read)
+  }
+}
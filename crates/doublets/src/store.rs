@@ -1,221 +1,838 @@
 use crate::{
-  Error, Flow, Index, Link, Links, ReadHandler, Result, WriteHandler,
+  Cursor, Doublets, Error, Flow, Index, Link, Links, Observer, Problem,
+  ReadHandler, Report, Result, Side, Subscription, WriteHandler, cursor::Scan,
+  observer::Observers,
 };
 
 use {
+  core::{cmp::Ordering, ops::ControlFlow},
   mem::{Alloc, RawMem},
-  trees::{AdaptiveRadix, Node, SizeBalanced, Tree},
+  std::collections::VecDeque,
+  trees::{Art, BPlusNode, BPlusTree, Node, SizeBalanced, Tree},
 };
 
 /// Marker trait for tree strategies that can insert and remove from trees
+///
+/// Each index orders links by a composite key: `(source, target)` in the
+/// source index and `(target, source)` in the target index, with the link
+/// index breaking ties between duplicates.
 pub trait TreeStrategy<T: trees::Idx>: Send + Sync {
+  /// Whether the tree embedded in link memory keeps nodes in key order
+  ///
+  /// Ordered trees can be searched and walked by comparing keys, which lets
+  /// the store answer source/target queries without scanning every link.
+  const ORDERED: bool;
+
+  /// Whether tree nodes keep the size of their subtree
+  ///
+  /// Together with [`ORDERED`](Self::ORDERED) this allows counting links
+  /// by rank arithmetic instead of enumerating them.
+  const SIZED: bool;
+
+  /// Whether the whole index lives in link memory
+  ///
+  /// Indexes keeping their nodes in [`State`](Self::State) are rebuilt
+  /// from the links when a store is reopened.
+  const PERSISTENT: bool;
+
+  /// Index data kept outside of link memory
+  type State: Default + Send + Sync;
+
   /// Insert into tree using this strategy
-  fn insert<Tr>(tree: &mut Tr, root: Option<T>, idx: T) -> Option<T>
+  ///
+  /// `key` is the composite key of `idx` in this index. `I` indexes the
+  /// tree embedded in link memory, which is as wide as the store's links.
+  fn insert<I, Tr>(
+    state: &mut Self::State,
+    tree: &mut Tr,
+    root: Option<I>,
+    idx: I,
+    key: [T; 2],
+  ) -> mem::Result<Option<I>>
   where
-    Tr: Tree<T> + SizeBalanced<T> + AdaptiveRadix<T>;
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>;
 
   /// Remove from tree using this strategy
-  fn remove<Tr>(tree: &mut Tr, root: Option<T>, idx: T) -> Option<T>
+  ///
+  /// `key` must be the composite key `idx` was inserted with.
+  fn remove<I, Tr>(
+    state: &mut Self::State,
+    tree: &mut Tr,
+    root: Option<I>,
+    idx: I,
+    key: [T; 2],
+  ) -> Option<I>
   where
-    Tr: Tree<T> + SizeBalanced<T> + AdaptiveRadix<T>;
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>;
+
+  /// Call `handler` with the index of links whose key starts with `prefix`
+  ///
+  /// Only strategies keeping their index in [`State`](Self::State) can
+  /// answer this, others return `None` and are walked by the store.
+  fn each_prefix(
+    state: &Self::State,
+    prefix: &[T],
+    handler: &mut dyn FnMut(usize) -> Flow,
+  ) -> Option<Flow> {
+    let _ = (state, prefix, handler);
+    None
+  }
+
+  /// Like [`each_prefix`](Self::each_prefix), but starting at the first
+  /// key not below `from`
+  ///
+  /// Lets the store resume long walks without passing over earlier keys.
+  /// Strategies that cannot seek return `None`.
+  fn each_prefix_from(
+    state: &Self::State,
+    prefix: &[T],
+    from: [T; 3],
+    handler: &mut dyn FnMut(usize) -> Flow,
+  ) -> Option<Flow> {
+    let _ = (state, prefix, from, handler);
+    None
+  }
 }
 
 /// Size-Balanced Tree strategy marker
 pub struct SbtStrategy;
 
 impl<T: trees::Idx> TreeStrategy<T> for SbtStrategy {
-  fn insert<Tr>(tree: &mut Tr, root: Option<T>, idx: T) -> Option<T>
+  const ORDERED: bool = true;
+  const SIZED: bool = true;
+  const PERSISTENT: bool = true;
+
+  type State = ();
+
+  fn insert<I, Tr>(
+    _: &mut (),
+    tree: &mut Tr,
+    root: Option<I>,
+    idx: I,
+    _: [T; 2],
+  ) -> mem::Result<Option<I>>
   where
-    Tr: Tree<T> + SizeBalanced<T> + AdaptiveRadix<T>,
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>,
   {
-    SizeBalanced::insert_sbt(tree, root, idx)
+    Ok(SizeBalanced::insert_sbt(tree, root, idx))
   }
 
-  fn remove<Tr>(tree: &mut Tr, root: Option<T>, idx: T) -> Option<T>
+  fn remove<I, Tr>(
+    _: &mut (),
+    tree: &mut Tr,
+    root: Option<I>,
+    idx: I,
+    _: [T; 2],
+  ) -> Option<I>
   where
-    Tr: Tree<T> + SizeBalanced<T> + AdaptiveRadix<T>,
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>,
   {
     SizeBalanced::remove_sbt(tree, root, idx)
   }
 }
 
 /// Adaptive Radix Tree strategy marker
+///
+/// Nodes live in a heap-allocated [`RadixIndex`] next to the link memory,
+/// so the tree nodes embedded in links and the root stay unused.
 pub struct ArtStrategy;
 
 impl<T: trees::Idx> TreeStrategy<T> for ArtStrategy {
-  fn insert<Tr>(tree: &mut Tr, root: Option<T>, idx: T) -> Option<T>
+  const ORDERED: bool = false;
+  const SIZED: bool = false;
+  const PERSISTENT: bool = false;
+
+  type State = RadixIndex;
+
+  fn insert<I, Tr>(
+    radix: &mut RadixIndex,
+    _: &mut Tr,
+    _: Option<I>,
+    idx: I,
+    key: [T; 2],
+  ) -> mem::Result<Option<I>>
+  where
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>,
+  {
+    radix.insert(key.map(T::as_usize), idx.as_usize())?;
+    Ok(None)
+  }
+
+  fn remove<I, Tr>(
+    radix: &mut RadixIndex,
+    _: &mut Tr,
+    _: Option<I>,
+    idx: I,
+    key: [T; 2],
+  ) -> Option<I>
+  where
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>,
+  {
+    radix.remove(key.map(T::as_usize), idx.as_usize());
+    None
+  }
+
+  fn each_prefix(
+    radix: &RadixIndex,
+    prefix: &[T],
+    handler: &mut dyn FnMut(usize) -> Flow,
+  ) -> Option<Flow> {
+    let mut bytes = [0; RadixIndex::KEY_LEN];
+    for (chunk, part) in bytes.chunks_exact_mut(8).zip(prefix) {
+      chunk.copy_from_slice(&(part.as_usize() as u64).to_be_bytes());
+    }
+    let prefix = &bytes[..prefix.len() * 8];
+    let flow =
+      radix.art.scan_prefix(prefix, |_, idx| match handler(idx as usize) {
+        Flow::Continue => ControlFlow::Continue(()),
+        Flow::Break => ControlFlow::Break(()),
+      });
+    Some(if flow.is_break() { Flow::Break } else { Flow::Continue })
+  }
+}
+
+/// B+ tree strategy marker
+///
+/// Nodes live in heap-allocated pages of a [`BPlusIndex`] next to the link
+/// memory, so the tree nodes embedded in links and the root stay unused.
+/// The index is heap-only, also for file-backed stores: it is not saved
+/// with the links, and [`Store::open`] rebuilds it from them.
+pub struct BPlusStrategy;
+
+impl<T: trees::Idx> TreeStrategy<T> for BPlusStrategy {
+  const ORDERED: bool = false;
+  const SIZED: bool = false;
+  const PERSISTENT: bool = false;
+
+  type State = BPlusIndex;
+
+  fn insert<I, Tr>(
+    index: &mut BPlusIndex,
+    _: &mut Tr,
+    _: Option<I>,
+    idx: I,
+    [primary, secondary]: [T; 2],
+  ) -> mem::Result<Option<I>>
   where
-    Tr: Tree<T> + SizeBalanced<T> + AdaptiveRadix<T>,
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>,
   {
-    AdaptiveRadix::insert_art(tree, root, idx)
+    let key = [primary.as_usize(), secondary.as_usize(), idx.as_usize()];
+    index.tree.insert(key.map(|part| part as u64), ())?;
+    Ok(None)
   }
 
-  fn remove<Tr>(tree: &mut Tr, root: Option<T>, idx: T) -> Option<T>
+  fn remove<I, Tr>(
+    index: &mut BPlusIndex,
+    _: &mut Tr,
+    _: Option<I>,
+    idx: I,
+    [primary, secondary]: [T; 2],
+  ) -> Option<I>
   where
-    Tr: Tree<T> + SizeBalanced<T> + AdaptiveRadix<T>,
+    I: trees::Idx,
+    Tr: Tree<I> + SizeBalanced<I>,
   {
-    AdaptiveRadix::remove_art(tree, root, idx)
+    let key = [primary.as_usize(), secondary.as_usize(), idx.as_usize()];
+    index.tree.remove(&key.map(|part| part as u64));
+    None
+  }
+
+  fn each_prefix(
+    index: &BPlusIndex,
+    prefix: &[T],
+    handler: &mut dyn FnMut(usize) -> Flow,
+  ) -> Option<Flow> {
+    let mut start = [0; 3];
+    for (word, part) in start.iter_mut().zip(prefix) {
+      *word = part.as_usize() as u64;
+    }
+    Some(index.each_from(prefix.len(), start, handler))
+  }
+
+  fn each_prefix_from(
+    index: &BPlusIndex,
+    prefix: &[T],
+    from: [T; 3],
+    handler: &mut dyn FnMut(usize) -> Flow,
+  ) -> Option<Flow> {
+    let start = from.map(|part| part.as_usize() as u64);
+    for (word, part) in start.iter().zip(prefix) {
+      if *word != part.as_usize() as u64 {
+        return Some(Flow::Continue);
+      }
+    }
+    Some(index.each_from(prefix.len(), start, handler))
+  }
+}
+
+/// B+ tree index of links used by [`BPlusStrategy`]
+///
+/// Keys are `(primary, secondary, index)` triples, so links sharing a source
+/// (or a whole doublet) are adjacent in the linked leaves and lookups
+/// become range scans.
+#[derive(Debug, Default)]
+pub struct BPlusIndex {
+  tree: BPlusTree<[u64; 3], (), Alloc<BPlusNode>>,
+}
+
+impl BPlusIndex {
+  /// Walk keys from `start` while their first `len` parts match it
+  fn each_from(
+    &self,
+    len: usize,
+    start: [u64; 3],
+    handler: &mut dyn FnMut(usize) -> Flow,
+  ) -> Flow {
+    for (key, ()) in self.tree.range(start..) {
+      if key[..len] != start[..len] {
+        break;
+      }
+      if handler(key[2] as usize) == Flow::Break {
+        return Flow::Break;
+      }
+    }
+    Flow::Continue
+  }
+
+  /// Number of indexed links
+  pub fn len(&self) -> usize {
+    self.tree.len()
+  }
+
+  /// Check if no links are indexed
+  pub fn is_empty(&self) -> bool {
+    self.tree.is_empty()
+  }
+}
+
+/// Radix index of links used by [`ArtStrategy`]
+///
+/// Keys are the big-endian bytes of `(primary, secondary, index)`, so links
+/// sharing a source (or a whole doublet) are adjacent and lookups become
+/// prefix walks over the [`Art`].
+#[derive(Debug)]
+pub struct RadixIndex {
+  art: Art<Alloc<u64>>,
+}
+
+impl RadixIndex {
+  const KEY_LEN: usize = 3 * size_of::<u64>();
+
+  fn key([primary, secondary]: [usize; 2], idx: usize) -> [u8; Self::KEY_LEN] {
+    let mut key = [0; Self::KEY_LEN];
+    for (chunk, part) in key.chunks_exact_mut(8).zip([primary, secondary, idx])
+    {
+      chunk.copy_from_slice(&(part as u64).to_be_bytes());
+    }
+    key
+  }
+
+  fn insert(&mut self, key: [usize; 2], idx: usize) -> mem::Result<()> {
+    self.art.insert(&Self::key(key, idx), idx as u64).map(drop)
+  }
+
+  fn remove(&mut self, key: [usize; 2], idx: usize) {
+    self.art.remove(&Self::key(key, idx));
+  }
+
+  /// Number of indexed links
+  pub fn len(&self) -> usize {
+    self.art.len()
+  }
+
+  /// Check if no links are indexed
+  pub fn is_empty(&self) -> bool {
+    self.art.is_empty()
   }
 }
 
+impl Default for RadixIndex {
+  fn default() -> Self {
+    Self { art: Art::new(Alloc::new(), Self::KEY_LEN) }
+  }
+}
+
+/// Referential integrity policy applied when deleting links
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrity {
+  /// Refuse to delete links still used as source or target by other links
+  #[default]
+  Strict,
+  /// Delete links unconditionally, possibly leaving dangling indices
+  ///
+  /// Intended for bulk loaders that restore consistency on their own.
+  Unchecked,
+}
+
+/// Policy for links sharing the same (source, target) pair
+///
+/// Both trees order links by their key and break ties by link index,
+/// so duplicates are indexed correctly in either mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Uniqueness {
+  /// Allow several links with the same source and target
+  #[default]
+  NonUnique,
+  /// Reject duplicate doublets on create and update
+  /// with [`Error::AlreadyExists`]
+  Unique,
+}
+
 /// Query/change array arity constants for method signatures
 const NC_SOURCE: usize = 2; // Change includes source
 const NC_TARGET: usize = 3; // Change includes target
 
+/// Magic number identifying memory that holds a doublets store ("dblt")
+///
+/// Narrow index types keep as many of its low bytes as they can hold.
+const MAGIC: usize = 0x6462_6C74;
+/// Version of the on-memory layout, bumped on incompatible changes
+const FORMAT_VERSION: usize = 2;
+/// Number of links reserved by a freshly created store
+const INITIAL_CAPACITY: usize = 1024;
+
+/// Store header persisted in the reserved zero slot of the link memory
+///
+/// Index `0` never holds a link, so its `RawLink` is reused to keep the
+/// counters, free-list head and tree roots next to the data they describe.
+/// That is what allows a file-backed store to be reopened.
+///
+/// The slot is read as eight index-sized words: magic, format version and
+/// index width, capacity, allocated, free count, free-list head and the
+/// two tree roots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+  magic: usize,
+  version: usize,
+  index_width: usize,
+  capacity: usize,
+  allocated: usize,
+  free_count: usize,
+  first_free: Option<usize>,
+  source_root: Option<usize>,
+  target_root: Option<usize>,
+}
+
+impl Header {
+  /// Read a header written by a compatible store
+  ///
+  /// Magic, version and index width are checked before the rest of the
+  /// slot is interpreted, so foreign memory is rejected early.
+  fn read<T: Index>(raw: &RawLink<T>) -> Option<Self> {
+    let words = bytemuck::cast_ref::<_, [T; 8]>(raw).map(RawLink::word);
+    let version = words[1] >> 5;
+    let index_width = words[1] & 0x1F;
+    if words[0] != RawLink::word(T::from_usize(MAGIC))
+      || version != FORMAT_VERSION
+      || index_width != size_of::<T>()
+    {
+      return None;
+    }
+
+    let non_zero = |word: usize| (word != 0).then_some(word);
+    let header = Self {
+      magic: MAGIC,
+      version,
+      index_width: size_of::<T>(),
+      capacity: words[2],
+      allocated: words[3],
+      free_count: words[4],
+      first_free: non_zero(words[5]),
+      source_root: non_zero(words[6]),
+      target_root: non_zero(words[7]),
+    };
+    let slots = [header.first_free, header.source_root, header.target_root];
+    let consistent = header.allocated >= 1
+      && header.allocated <= header.capacity
+      && header.free_count < header.allocated
+      && slots.into_iter().flatten().all(|slot| slot < header.allocated);
+    consistent.then_some(header)
+  }
+
+  fn write<T: Index>(self, raw: &mut RawLink<T>) {
+    let words = [
+      self.magic,
+      self.version << 5 | self.index_width,
+      self.capacity,
+      self.allocated,
+      self.free_count,
+      self.first_free.unwrap_or(0),
+      self.source_root.unwrap_or(0),
+      self.target_root.unwrap_or(0),
+    ];
+    *bytemuck::cast_mut::<_, [T; 8]>(raw) = words.map(T::from_usize);
+  }
+}
+
 /// Raw link data stored in memory with tree navigation
 ///
 /// Stores source, target, and tree index information for efficient
 /// searching by source and target using size-balanced trees.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///
+/// Every field is as wide as the index type: tree children are
+/// `Option<T::Repr>`, whose NonZero niche encodes `None` as zero. A link
+/// thus takes eight indices, 32 bytes for `u32` and 64 bytes for `u64`.
+///
+/// Free slots are marked by both children of their source tree node
+/// pointing at link 1. A node in a tree never has one link as both
+/// children, so the marker takes no bits from indices or sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
-pub struct RawLink {
-  source: usize,
-  target: usize,
+pub struct RawLink<T: Index = usize> {
+  source: T,
+  target: T,
   /// Tree node for indexing by source
-  source_tree: Node<usize>,
+  source_tree: RawNode<T>,
   /// Tree node for indexing by target
-  target_tree: Node<usize>,
-  /// Special marker: usize::MAX if in free list, 0 otherwise
-  is_free: usize,
+  target_tree: RawNode<T>,
+}
+
+// SAFETY: every field is `T` or `Option<T::Repr>`, and the unsafe trait
+// `Index` requires `Repr` to be the NonZero counterpart of `T`, so all
+// fields share one size and alignment, `repr(C)` adds no padding and any
+// bits are valid
+unsafe impl<T: Index> bytemuck::Zeroable for RawLink<T> {}
+unsafe impl<T: Index> bytemuck::Pod for RawLink<T> {}
+
+impl<T: Index> Default for RawLink<T> {
+  fn default() -> Self {
+    bytemuck::Zeroable::zeroed()
+  }
+}
+
+impl<T: Index> RawLink<T> {
+  /// Bits of an index word that survive a round trip through `usize`
+  const BITS: u32 = if size_of::<T>() < size_of::<usize>() {
+    size_of::<T>() as u32 * 8
+  } else {
+    usize::BITS
+  };
+
+  /// Largest link index an index word of `T` can hold
+  ///
+  /// This is the largest unsigned value of the word: a store of `u8`
+  /// holds up to 255 links, one of `u16` up to 65535. Signed types stop
+  /// at their largest positive value, which is where
+  /// [`Index::try_from_usize`] starts to fail.
+  pub const MAX_INDEX: usize = usize::MAX >> (usize::BITS - Self::BITS);
+
+  /// Value of an index word read back as unsigned
+  #[inline]
+  fn word(value: T) -> usize {
+    value.as_usize() & Self::MAX_INDEX
+  }
+
+  #[inline]
+  fn source(&self) -> usize {
+    self.source.as_usize()
+  }
+
+  #[inline]
+  fn target(&self) -> usize {
+    self.target.as_usize()
+  }
+
+  #[inline]
+  fn set_source(&mut self, source: usize) {
+    self.source = T::from_usize(source);
+  }
+
+  #[inline]
+  fn set_target(&mut self, target: usize) {
+    self.target = T::from_usize(target);
+  }
+
+  #[inline]
+  fn is_free(&self) -> bool {
+    let node = &self.source_tree;
+    node.left.is_some() && node.left == node.right
+  }
+
+  /// Mark the link as free, clearing both tree nodes
+  fn set_free(&mut self) {
+    self.source_tree = RawNode::empty();
+    self.target_tree = RawNode::empty();
+    self.source_tree.left = Some(slot::<T>(1));
+    self.source_tree.right = Some(slot::<T>(1));
+  }
+
+  /// Node of the source tree with indices widened to `usize`
+  #[inline]
+  fn source_node(&self) -> Node<usize> {
+    self.source_tree.widen()
+  }
+
+  /// Node of the target tree with indices widened to `usize`
+  #[inline]
+  fn target_node(&self) -> Node<usize> {
+    self.target_tree.widen()
+  }
+}
+
+/// Tree node packed into index-sized words
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+struct RawNode<T: Index> {
+  size: T,
+  left: Option<T::Repr>,
+  right: Option<T::Repr>,
+}
+
+impl<T: Index> RawNode<T> {
+  fn empty() -> Self {
+    Self { size: T::ZERO, left: None, right: None }
+  }
+
+  #[inline]
+  fn size(&self) -> usize {
+    RawLink::word(self.size)
+  }
+
+  #[inline]
+  fn get(&self) -> Node<T::Repr> {
+    Node { size: self.size(), left: self.left, right: self.right }
+  }
+
+  #[inline]
+  fn set(&mut self, node: Node<T::Repr>) {
+    self.size = T::from_usize(node.size);
+    self.left = node.left;
+    self.right = node.right;
+  }
+
+  #[inline]
+  fn widen(&self) -> Node<usize> {
+    let widen = |slot: T::Repr| T::from_repr(slot).as_usize();
+    Node {
+      size: self.size(),
+      left: self.left.map(widen),
+      right: self.right.map(widen),
+    }
+  }
+}
+
+/// Tree slot of a link, which is never zero since links start at 1
+#[inline]
+fn slot<T: Index>(index: usize) -> T::Repr {
+  T::from_usize(index).to_repr().expect("links are indexed from 1")
 }
 
-unsafe impl bytemuck::Pod for RawLink {}
-unsafe impl bytemuck::Zeroable for RawLink {}
+/// Link index of a tree slot
+#[inline]
+fn unslot<T: Index>(slot: T::Repr) -> usize {
+  T::from_repr(slot).as_usize()
+}
 
 /// Helper struct to implement Tree trait for source indexing with
 /// configurable strategy
-struct SourceTree<'a, M: RawMem<Item = RawLink>, S> {
+struct SourceTree<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> {
   mem: &'a mut M,
-  _strategy: core::marker::PhantomData<S>,
+  _strategy: core::marker::PhantomData<(T, S)>,
 }
 
-impl<'a, M: RawMem<Item = RawLink>, S> SourceTree<'a, M, S> {
+impl<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> SourceTree<'a, T, M, S> {
   fn new(mem: &'a mut M) -> Self {
     Self { mem, _strategy: core::marker::PhantomData }
   }
 }
 
-impl<'a, M: RawMem<Item = RawLink>, S> Tree<usize> for SourceTree<'a, M, S> {
-  fn get(&self, idx: usize) -> Option<Node<usize>> {
+impl<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> Tree<T::Repr>
+  for SourceTree<'a, T, M, S>
+{
+  fn get(&self, idx: T::Repr) -> Option<Node<T::Repr>> {
     let slice = self.mem.as_slice();
-    slice.get(idx).map(|raw| raw.source_tree)
+    slice.get(unslot::<T>(idx)).map(|raw| raw.source_tree.get())
   }
 
-  fn set(&mut self, idx: usize, node: Node<usize>) {
+  fn set(&mut self, idx: T::Repr, node: Node<T::Repr>) {
     let slice = self.mem.as_mut_slice();
-    if let Some(raw) = slice.get_mut(idx) {
-      raw.source_tree = node;
+    if let Some(raw) = slice.get_mut(unslot::<T>(idx)) {
+      raw.source_tree.set(node);
     }
   }
 
-  fn left_mut(&mut self, idx: usize) -> Option<&mut usize> {
+  fn left_mut(&mut self, idx: T::Repr) -> Option<&mut T::Repr> {
     let slice = self.mem.as_mut_slice();
-    slice.get_mut(idx).and_then(|raw| raw.source_tree.left.as_mut())
+    slice
+      .get_mut(unslot::<T>(idx))
+      .and_then(|raw| raw.source_tree.left.as_mut())
   }
 
-  fn right_mut(&mut self, idx: usize) -> Option<&mut usize> {
+  fn right_mut(&mut self, idx: T::Repr) -> Option<&mut T::Repr> {
     let slice = self.mem.as_mut_slice();
-    slice.get_mut(idx).and_then(|raw| raw.source_tree.right.as_mut())
+    slice
+      .get_mut(unslot::<T>(idx))
+      .and_then(|raw| raw.source_tree.right.as_mut())
   }
 
-  fn is_left_of(&self, first: usize, second: usize) -> bool {
+  fn is_left_of(&self, first: T::Repr, second: T::Repr) -> bool {
     let slice = self.mem.as_slice();
+    let (first, second) = (unslot::<T>(first), unslot::<T>(second));
     if let (Some(a), Some(b)) = (slice.get(first), slice.get(second)) {
-      // Compare by (source, target) tuple for source tree
-      (a.source, a.target) < (b.source, b.target)
+      // Compare by (source, target) tuple for source tree,
+      // breaking ties by index so duplicate doublets stay distinct
+      (a.source(), a.target(), first) < (b.source(), b.target(), second)
     } else {
       first < second
     }
   }
 
-  fn insert(&mut self, root: Option<usize>, idx: usize) -> Option<usize> {
+  fn insert(&mut self, root: Option<T::Repr>, idx: T::Repr) -> Option<T::Repr> {
     SizeBalanced::insert_sbt(self, root, idx)
   }
 
-  fn remove(&mut self, root: Option<usize>, idx: usize) -> Option<usize> {
+  fn remove(&mut self, root: Option<T::Repr>, idx: T::Repr) -> Option<T::Repr> {
     SizeBalanced::remove_sbt(self, root, idx)
   }
 }
 
 // Implement SizeBalanced for all strategies (required by trait bounds)
-impl<'a, M: RawMem<Item = RawLink>, S> SizeBalanced<usize>
-  for SourceTree<'a, M, S>
-{
-}
-
-// Implement AdaptiveRadix for all strategies (required by trait bounds)
-impl<'a, M: RawMem<Item = RawLink>, S> AdaptiveRadix<usize>
-  for SourceTree<'a, M, S>
+impl<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> SizeBalanced<T::Repr>
+  for SourceTree<'a, T, M, S>
 {
 }
 
 /// Helper struct to implement Tree trait for target indexing with
 /// configurable strategy
-struct TargetTree<'a, M: RawMem<Item = RawLink>, S> {
+struct TargetTree<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> {
   mem: &'a mut M,
-  _strategy: core::marker::PhantomData<S>,
+  _strategy: core::marker::PhantomData<(T, S)>,
 }
 
-impl<'a, M: RawMem<Item = RawLink>, S> TargetTree<'a, M, S> {
+impl<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> TargetTree<'a, T, M, S> {
   fn new(mem: &'a mut M) -> Self {
     Self { mem, _strategy: core::marker::PhantomData }
   }
 }
 
-impl<'a, M: RawMem<Item = RawLink>, S> Tree<usize> for TargetTree<'a, M, S> {
-  fn get(&self, idx: usize) -> Option<Node<usize>> {
+impl<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> Tree<T::Repr>
+  for TargetTree<'a, T, M, S>
+{
+  fn get(&self, idx: T::Repr) -> Option<Node<T::Repr>> {
     let slice = self.mem.as_slice();
-    slice.get(idx).map(|raw| raw.target_tree)
+    slice.get(unslot::<T>(idx)).map(|raw| raw.target_tree.get())
   }
 
-  fn set(&mut self, idx: usize, node: Node<usize>) {
+  fn set(&mut self, idx: T::Repr, node: Node<T::Repr>) {
     let slice = self.mem.as_mut_slice();
-    if let Some(raw) = slice.get_mut(idx) {
-      raw.target_tree = node;
+    if let Some(raw) = slice.get_mut(unslot::<T>(idx)) {
+      raw.target_tree.set(node);
     }
   }
 
-  fn left_mut(&mut self, idx: usize) -> Option<&mut usize> {
+  fn left_mut(&mut self, idx: T::Repr) -> Option<&mut T::Repr> {
     let slice = self.mem.as_mut_slice();
-    slice.get_mut(idx).and_then(|raw| raw.target_tree.left.as_mut())
+    slice
+      .get_mut(unslot::<T>(idx))
+      .and_then(|raw| raw.target_tree.left.as_mut())
   }
 
-  fn right_mut(&mut self, idx: usize) -> Option<&mut usize> {
+  fn right_mut(&mut self, idx: T::Repr) -> Option<&mut T::Repr> {
     let slice = self.mem.as_mut_slice();
-    slice.get_mut(idx).and_then(|raw| raw.target_tree.right.as_mut())
+    slice
+      .get_mut(unslot::<T>(idx))
+      .and_then(|raw| raw.target_tree.right.as_mut())
   }
 
-  fn is_left_of(&self, first: usize, second: usize) -> bool {
+  fn is_left_of(&self, first: T::Repr, second: T::Repr) -> bool {
     let slice = self.mem.as_slice();
+    let (first, second) = (unslot::<T>(first), unslot::<T>(second));
     if let (Some(a), Some(b)) = (slice.get(first), slice.get(second)) {
-      // Compare by (target, source) tuple for target tree
-      (a.target, a.source) < (b.target, b.source)
+      // Compare by (target, source) tuple for target tree,
+      // breaking ties by index so duplicate doublets stay distinct
+      (a.target(), a.source(), first) < (b.target(), b.source(), second)
     } else {
       first < second
     }
   }
 
-  fn insert(&mut self, root: Option<usize>, idx: usize) -> Option<usize> {
+  fn insert(&mut self, root: Option<T::Repr>, idx: T::Repr) -> Option<T::Repr> {
     SizeBalanced::insert_sbt(self, root, idx)
   }
 
-  fn remove(&mut self, root: Option<usize>, idx: usize) -> Option<usize> {
+  fn remove(&mut self, root: Option<T::Repr>, idx: T::Repr) -> Option<T::Repr> {
     SizeBalanced::remove_sbt(self, root, idx)
   }
 }
 
 // Implement SizeBalanced for all strategies (required by trait bounds)
-impl<'a, M: RawMem<Item = RawLink>, S> SizeBalanced<usize>
-  for TargetTree<'a, M, S>
+impl<'a, T: Index, M: RawMem<Item = RawLink<T>>, S> SizeBalanced<T::Repr>
+  for TargetTree<'a, T, M, S>
 {
 }
 
-// Implement AdaptiveRadix for all strategies (required by trait bounds)
-impl<'a, M: RawMem<Item = RawLink>, S> AdaptiveRadix<usize>
-  for TargetTree<'a, M, S>
-{
+/// Read-only view of an index tree for rank queries
+///
+/// Slots `0` and `usize::MAX` never hold links, so they stand in for the
+/// `lo` and `hi` probe keys counted between by [`Ranks::count`]. Keys are
+/// compared with the link index appended, like in the tree itself.
+/// The view never changes the tree, so its mutators do nothing.
+struct Ranks<'a, T: Index> {
+  links: &'a [RawLink<T>],
+  node: fn(&RawLink<T>) -> Node<usize>,
+  key: fn(&RawLink<T>) -> (usize, usize),
+  lo: (usize, usize, usize),
+  hi: (usize, usize, usize),
+}
+
+impl<T: Index> Ranks<'_, T> {
+  const LO: usize = 0;
+  const HI: usize = usize::MAX;
+
+  /// Count links with keys in `lo..hi`
+  fn count(&self, root: Option<usize>) -> usize {
+    self.count_range(root, Self::LO, Self::HI)
+  }
+
+  fn key_of(&self, idx: usize) -> Option<(usize, usize, usize)> {
+    match idx {
+      Self::LO => Some(self.lo),
+      Self::HI => Some(self.hi),
+      _ => self.links.get(idx).map(|raw| {
+        let (first, second) = (self.key)(raw);
+        (first, second, idx)
+      }),
+    }
+  }
+}
+
+impl<T: Index> Tree<usize> for Ranks<'_, T> {
+  fn get(&self, idx: usize) -> Option<Node<usize>> {
+    self.links.get(idx).map(self.node)
+  }
+
+  fn set(&mut self, _: usize, _: Node<usize>) {}
+
+  fn left_mut(&mut self, _: usize) -> Option<&mut usize> {
+    None
+  }
+
+  fn right_mut(&mut self, _: usize) -> Option<&mut usize> {
+    None
+  }
+
+  fn is_left_of(&self, first: usize, second: usize) -> bool {
+    self.key_of(first) < self.key_of(second)
+  }
+
+  fn insert(&mut self, root: Option<usize>, _: usize) -> Option<usize> {
+    root
+  }
+
+  fn remove(&mut self, root: Option<usize>, _: usize) -> Option<usize> {
+    root
+  }
 }
 
+impl<T: Index> SizeBalanced<usize> for Ranks<'_, T> {}
+
 /// Doublets store implementation using tree-based indexing
 ///
 /// Generic over tree strategies for both source and target indexing.
@@ -244,12 +861,12 @@ impl<'a, M: RawMem<Item = RawLink>, S> AdaptiveRadix<usize>
 /// ```
 pub struct Store<
   T,
-  M = Alloc<RawLink>,
+  M = Alloc<RawLink<T>>,
   SourceStrategy = SbtStrategy,
   TargetStrategy = SbtStrategy,
 > where
   T: Index,
-  M: RawMem<Item = RawLink> + Send + Sync,
+  M: RawMem<Item = RawLink<T>> + Send + Sync,
   SourceStrategy: TreeStrategy<usize>,
   TargetStrategy: TreeStrategy<usize>,
 {
@@ -261,6 +878,13 @@ pub struct Store<
   source_root: Option<usize>,
   /// Root of tree indexing links by target
   target_root: Option<usize>,
+  /// Out-of-line data of the source index
+  source_index: SourceStrategy::State,
+  /// Out-of-line data of the target index
+  target_index: TargetStrategy::State,
+  integrity: Integrity,
+  uniqueness: Uniqueness,
+  observers: Observers<T>,
   _phantom: core::marker::PhantomData<(T, SourceStrategy, TargetStrategy)>,
 }
 
@@ -268,35 +892,524 @@ impl<T, M, SourceStrategy, TargetStrategy>
   Store<T, M, SourceStrategy, TargetStrategy>
 where
   T: Index,
-  M: RawMem<Item = RawLink> + Send + Sync,
+  M: RawMem<Item = RawLink<T>> + Send + Sync,
   SourceStrategy: TreeStrategy<usize>,
   TargetStrategy: TreeStrategy<usize>,
 {
   /// Create a new doublets store with default capacity
+  ///
+  /// Any content already present in `mem` is overwritten.
+  /// Use [`Store::open`] to restore a previously persisted store.
   pub fn new(mut mem: M) -> Result<Self, T> {
-    mem.grow(1024).map_err(|_| Error::AllocationFailed)?.zeroed();
+    let len = mem.as_slice().len();
+    mem.as_mut_slice().fill(RawLink::default());
+    let capacity =
+      INITIAL_CAPACITY.min(RawLink::<T>::MAX_INDEX.saturating_add(1));
+    if len < capacity {
+      mem.grow(capacity - len).map_err(|_| Error::AllocationFailed)?.zeroed();
+    }
 
-    Ok(Self {
+    let mut store = Self {
       mem,
       allocated: 1,
       free_count: 0,
       first_free: None,
       source_root: None,
       target_root: None,
+      source_index: Default::default(),
+      target_index: Default::default(),
+      integrity: Integrity::default(),
+      uniqueness: Uniqueness::default(),
+      observers: Observers::default(),
+      _phantom: core::marker::PhantomData,
+    };
+    store.sync_header();
+    Ok(store)
+  }
+
+  /// Open a store persisted in `mem`, or create a new one if it is blank
+  ///
+  /// The header kept in the zero slot is validated and the counters,
+  /// free list and tree roots are restored from it. Memory that starts
+  /// with zeroes (e.g. a freshly created file) is initialized as an empty
+  /// store. Anything else is rejected with [`Error::InvalidHeader`].
+  ///
+  /// Memory is grown with [`RawMem::grow_stored`], so `mem::FileMapped`
+  /// exposes the links kept in its file while heap memory that holds
+  /// nothing yet starts out blank.
+  ///
+  /// # Examples
+  /// ```ignore
+  /// use {doublets::{Doublets, Store}, mem::FileMapped};
+  ///
+  /// let mem = FileMapped::from_path("db.links")?;
+  /// let mut store: Store<usize, _> = Store::open(mem)?;
+  /// store.create_point()?;
+  /// ```
+  pub fn open(mut mem: M) -> Result<Self, T> {
+    if mem.as_slice().is_empty() {
+      mem.grow_stored(1).map_err(|_| Error::AllocationFailed)?;
+    }
+
+    let slot = &mem.as_slice()[0];
+    if slot.source() == 0 {
+      return Self::new(mem);
+    }
+    let header = Header::read::<T>(slot).ok_or(Error::InvalidHeader)?;
+    // Growing past what the storage holds would zero-extend a truncated
+    // file and read the zeroes as links
+    if mem.stored().is_some_and(|stored| stored < header.capacity) {
+      return Err(Error::InvalidHeader);
+    }
+
+    let len = mem.as_slice().len();
+    if len < header.capacity {
+      mem
+        .grow_stored(header.capacity - len)
+        .map_err(|_| Error::AllocationFailed)?;
+    }
+
+    let mut store = Self {
+      mem,
+      allocated: header.allocated,
+      free_count: header.free_count,
+      first_free: header.first_free,
+      source_root: header.source_root,
+      target_root: header.target_root,
+      source_index: Default::default(),
+      target_index: Default::default(),
+      integrity: Integrity::default(),
+      uniqueness: Uniqueness::default(),
+      observers: Observers::default(),
       _phantom: core::marker::PhantomData,
+    };
+    store.restore_volatile_indexes()?;
+    Ok(store)
+  }
+
+  /// Rebuild indexes that do not live in link memory
+  fn restore_volatile_indexes(&mut self) -> Result<(), T> {
+    for idx in 1..self.allocated {
+      if !self.exists(T::from_usize(idx)) {
+        continue;
+      }
+      if !SourceStrategy::PERSISTENT {
+        self.attach_to_source_tree(idx)?;
+      }
+      if !TargetStrategy::PERSISTENT {
+        self.attach_to_target_tree(idx)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Check the indexes, free list and counters against the link records
+  ///
+  /// Nothing is trusted beyond the allocated count: trees are walked with
+  /// their key bounds and a record of every node reached, so cycles and
+  /// stray pointers are reported rather than followed forever. The store
+  /// is left as it is, [`rebuild_indexes`](Self::rebuild_indexes) repairs
+  /// everything but dangling references.
+  ///
+  /// # Examples
+  /// ```
+  /// use doublets::{Doublets, create_heap_store};
+  ///
+  /// let mut store = create_heap_store::<usize>()?;
+  /// let a = store.create_point()?;
+  /// store.create_link(a, a)?;
+  ///
+  /// let report = store.verify();
+  /// assert!(report.is_ok());
+  /// assert_eq!(report.links, 2);
+  /// # Ok::<(), doublets::Error<usize>>(())
+  /// ```
+  pub fn verify(&self) -> Report<T> {
+    let slice = self.mem.as_slice();
+    let end = self.allocated.min(slice.len());
+    let live: Vec<_> =
+      (0..end).map(|idx| self.exists(T::from_usize(idx))).collect();
+    let mut problems = Vec::new();
+
+    for idx in (1..end).filter(|&idx| live[idx]) {
+      let raw = &slice[idx];
+      for reference in [raw.source(), raw.target()] {
+        if reference != 0 && !live.get(reference).is_some_and(|&live| live) {
+          problems.push(Problem::Dangling {
+            link: T::from_usize(idx),
+            reference: T::from_usize(reference),
+          });
+        }
+      }
+    }
+
+    self.verify_index::<SourceStrategy>(
+      Side::Source,
+      &self.source_index,
+      self.source_root,
+      |raw| (raw.source_node(), [raw.source(), raw.target()]),
+      &live,
+      &mut problems,
+    );
+    self.verify_index::<TargetStrategy>(
+      Side::Target,
+      &self.target_index,
+      self.target_root,
+      |raw| (raw.target_node(), [raw.target(), raw.source()]),
+      &live,
+      &mut problems,
+    );
+    self.verify_free_list(&live, &mut problems);
+
+    let links = live.iter().filter(|&&live| live).count();
+    Report { links, problems }
+  }
+
+  /// Rebuild both indexes, the free list and its count from the records
+  ///
+  /// Every allocated slot is a link or, by its free marker, a free slot.
+  /// Tree nodes and out-of-line indexes are discarded and every link is
+  /// inserted again, free slots are relinked with the lowest first.
+  pub fn rebuild_indexes(&mut self) -> Result<(), T> {
+    self.source_root = None;
+    self.target_root = None;
+    self.source_index = Default::default();
+    self.target_index = Default::default();
+    self.first_free = None;
+    self.free_count = 0;
+
+    for idx in (1..self.allocated).rev() {
+      if self.mem.as_slice()[idx].is_free() {
+        self.free_index(T::from_usize(idx));
+      } else if let Some(raw) = self.repr_mut_at(idx) {
+        raw.source_tree = RawNode::empty();
+        raw.target_tree = RawNode::empty();
+      }
+    }
+    for idx in 1..self.allocated {
+      if self.exists(T::from_usize(idx)) {
+        self.attach_to_source_tree(idx)?;
+        self.attach_to_target_tree(idx)?;
+      }
+    }
+    self.sync_header();
+    Ok(())
+  }
+
+  /// Check that an index holds every live link once, in key order
+  ///
+  /// Trees in link memory are walked from `root`, other indexes are
+  /// listed through [`TreeStrategy::each_prefix`] and every link is then
+  /// looked up by its own key. `view` gives the tree node and key of a
+  /// record in this index.
+  fn verify_index<S: TreeStrategy<usize>>(
+    &self,
+    side: Side,
+    state: &S::State,
+    root: Option<usize>,
+    view: impl Fn(&RawLink<T>) -> (Node<usize>, [usize; 2]),
+    live: &[bool],
+    problems: &mut Vec<Problem<T>>,
+  ) {
+    let slice = self.mem.as_slice();
+    let link = T::from_usize;
+    let mut seen = vec![false; live.len()];
+    let mut reach = |idx: usize, problems: &mut Vec<_>| {
+      let problem = if !live.get(idx).is_some_and(|&live| live) {
+        Problem::Stale { side, link: link(idx) }
+      } else if core::mem::replace(&mut seen[idx], true) {
+        Problem::Repeated { side, link: link(idx) }
+      } else {
+        return true;
+      };
+      problems.push(problem);
+      false
+    };
+
+    if S::ORDERED {
+      // Pre-order, with the keys each node has to fall between
+      let mut order = Vec::new();
+      let mut stack: Vec<_> =
+        root.map(|root| (root, None, None, None)).into_iter().collect();
+      while let Some((idx, parent, lower, upper)) = stack.pop() {
+        if !reach(idx, problems) {
+          continue;
+        }
+        let (node, [primary, secondary]) = view(&slice[idx]);
+        let key = (primary, secondary, idx);
+        if lower.is_some_and(|lower| key <= lower)
+          || upper.is_some_and(|upper| key >= upper)
+        {
+          problems.push(Problem::Misplaced { side, link: link(idx) });
+        }
+        order.push((idx, parent));
+        stack
+          .extend(node.right.map(|right| (right, Some(idx), Some(key), upper)));
+        stack.extend(node.left.map(|left| (left, Some(idx), lower, Some(key))));
+      }
+
+      if S::SIZED {
+        // Reversed pre-order reaches every node after its subtree
+        let mut sizes = vec![0; live.len()];
+        for &(idx, parent) in order.iter().rev() {
+          sizes[idx] += 1;
+          if let Some(parent) = parent {
+            sizes[parent] += sizes[idx];
+          }
+          let stored = view(&slice[idx]).0.size;
+          if stored != sizes[idx] {
+            let node = link(idx);
+            let actual = sizes[idx];
+            problems.push(Problem::WrongSize { side, node, stored, actual });
+          }
+        }
+      }
+    } else {
+      let mut listed = Vec::new();
+      let walked = S::each_prefix(state, &[], &mut |idx| {
+        listed.push(idx);
+        Flow::Continue
+      });
+      if walked.is_none() {
+        return;
+      }
+      for idx in listed {
+        if !reach(idx, problems) {
+          continue;
+        }
+        let (_, key) = view(&slice[idx]);
+        let mut found = false;
+        S::each_prefix(state, &key, &mut |other| {
+          found = other == idx;
+          Flow::from(!found)
+        });
+        if !found {
+          problems.push(Problem::Misplaced { side, link: link(idx) });
+        }
+      }
+    }
+
+    for idx in (1..live.len()).filter(|&idx| live[idx] && !seen[idx]) {
+      problems.push(Problem::Missing { side, link: link(idx) });
+    }
+  }
+
+  /// Check that the free list holds every free slot once
+  fn verify_free_list(&self, live: &[bool], problems: &mut Vec<Problem<T>>) {
+    let slice = self.mem.as_slice();
+    let mut listed = vec![false; live.len()];
+    let mut count = 0;
+    let mut next = self.first_free;
+    while let Some(idx) = next {
+      let free = idx < live.len() && slice[idx].is_free();
+      if !free {
+        problems.push(Problem::FreeListBroken(T::from_usize(idx)));
+        break;
+      }
+      if core::mem::replace(&mut listed[idx], true) {
+        problems.push(Problem::FreeListCycle(T::from_usize(idx)));
+        break;
+      }
+      count += 1;
+      next = Some(slice[idx].source()).filter(|&next| next != 0);
+    }
+
+    for idx in 1..live.len() {
+      if slice[idx].is_free() && !listed[idx] {
+        problems.push(Problem::FreeSlotLost(T::from_usize(idx)));
+      }
+    }
+    if count != self.free_count {
+      let stored = self.free_count;
+      problems.push(Problem::FreeCount { stored, actual: count });
+    }
+  }
+
+  /// Set the referential integrity policy used by `delete`
+  #[must_use]
+  pub fn with_integrity(mut self, integrity: Integrity) -> Self {
+    self.integrity = integrity;
+    self
+  }
+
+  /// Referential integrity policy used by `delete`
+  pub fn integrity(&self) -> Integrity {
+    self.integrity
+  }
+
+  /// Change the referential integrity policy used by `delete`
+  pub fn set_integrity(&mut self, integrity: Integrity) {
+    self.integrity = integrity;
+  }
+
+  /// Set the policy for duplicate doublets used by `create` and `update`
+  #[must_use]
+  pub fn with_uniqueness(mut self, uniqueness: Uniqueness) -> Self {
+    self.uniqueness = uniqueness;
+    self
+  }
+
+  /// Policy for duplicate doublets used by `create` and `update`
+  pub fn uniqueness(&self) -> Uniqueness {
+    self.uniqueness
+  }
+
+  /// Change the policy for duplicate doublets used by `create` and `update`
+  pub fn set_uniqueness(&mut self, uniqueness: Uniqueness) {
+    self.uniqueness = uniqueness;
+  }
+
+  /// Pass every change to a link matching `query` to `observer`
+  ///
+  /// The query is `[index?, source?, target?]` with `T::ANY` for
+  /// wildcards, as in `each`, and a change matches if its `before` or its
+  /// `after` state does. Each change is offered to
+  /// [`Observer::allow`] of every matching observer before it is applied,
+  /// the first to reject it fails the operation with [`Error::Vetoed`] and
+  /// leaves the store as it was. Once applied, the change is passed to
+  /// [`Observer::notify`] before the `WriteHandler` of the operation sees
+  /// it. Updates that keep the source and target are not changes.
+  ///
+  /// Undoing changes, as transactions and [`Schema`](crate::Schema) do,
+  /// goes through the same operations, so observers see the undo as well,
+  /// but cannot veto it: a rejected undo would leave the store halfway.
+  /// Writes made on the same thread while an undo runs, such as those of
+  /// an observer, are not vetoed either.
+  ///
+  /// # Examples
+  /// ```
+  /// use {
+  ///   doublets::{Doublets, Index, Link, create_heap_store},
+  ///   std::sync::{Arc, Mutex},
+  /// };
+  ///
+  /// let mut store = create_heap_store::<usize>()?;
+  /// let a = store.create_point()?;
+  ///
+  /// let changes = Arc::new(Mutex::new(Vec::new()));
+  /// let feed = Arc::clone(&changes);
+  /// let subscription = store.subscribe(
+  ///   [usize::ANY, a],
+  ///   move |before: Link<usize>, after: Link<usize>| {
+  ///     feed.lock().unwrap().push((before, after));
+  ///   },
+  /// );
+  ///
+  /// let link = store.create_link(a, a)?;
+  /// store.create_point()?;
+  /// store.unsubscribe(subscription);
+  /// store.delete_link(link)?;
+  ///
+  /// let after = Link::new(link, a, a);
+  /// assert_eq!(*changes.lock().unwrap(), [(Link::nothing(), after)]);
+  /// # Ok::<(), doublets::Error<usize>>(())
+  /// ```
+  pub fn subscribe<const N: usize, O>(
+    &mut self,
+    query: [T; N],
+    observer: O,
+  ) -> Subscription
+  where
+    O: Observer<T> + Send + Sync + 'static,
+  {
+    let filter =
+      core::array::from_fn(|i| query.get(i).copied().unwrap_or(T::ANY));
+    self.observers.subscribe(filter, Box::new(observer))
+  }
+
+  /// Stop passing changes to an observer, `false` if it was already gone
+  pub fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+    self.observers.unsubscribe(subscription)
+  }
+
+  /// Let observers reject changes, returning whether they could before
+  ///
+  /// With vetoes disabled every change is applied and passed to
+  /// [`Observer::notify`] without asking [`Observer::allow`] first.
+  pub fn set_vetoes(&mut self, enabled: bool) -> bool {
+    self.observers.set_vetoes(enabled)
+  }
+
+  /// Reject a (source, target) pair already used by a link other than `index`
+  fn ensure_unique(&self, index: T, source: T, target: T) -> Result<(), T> {
+    if self.uniqueness == Uniqueness::Unique
+      && let Some(existing) = self.find_exact(source, target)
+      && existing != index
+    {
+      return Err(Error::AlreadyExists(existing, source, target));
+    }
+    Ok(())
+  }
+
+  /// Get the underlying memory
+  pub fn mem(&self) -> &M {
+    &self.mem
+  }
+
+  /// Consume the store and return the underlying memory
+  ///
+  /// The header is kept up to date after every operation, so the returned
+  /// memory can be passed back to [`Store::open`].
+  pub fn into_mem(self) -> M {
+    self.mem
+  }
+
+  /// Walk links from where `cursor` stopped, advancing it on every link
+  ///
+  /// Links are produced lazily in batches, each found by seeking the
+  /// index to the cursor position, so long walks never hold the whole
+  /// result. Drop the iterator at any point and keep the cursor to
+  /// continue later, even after the store was modified.
+  pub fn resume<'a>(
+    &'a self,
+    cursor: &'a mut Cursor<T>,
+  ) -> impl Iterator<Item = Link<T>> + 'a {
+    const BATCH: usize = 256;
+
+    let mut batch = VecDeque::new();
+    core::iter::from_fn(move || {
+      if batch.is_empty() {
+        batch.extend(self.fetch_after(cursor, BATCH));
+      }
+      let link = batch.pop_front()?;
+      cursor.last = Some(link);
+      Some(link)
     })
   }
 
+  /// Write the current counters and tree roots into the header slot
+  fn sync_header(&mut self) {
+    let header = Header {
+      magic: MAGIC,
+      version: FORMAT_VERSION,
+      index_width: size_of::<T>(),
+      capacity: self.capacity(),
+      allocated: self.allocated,
+      free_count: self.free_count,
+      first_free: self.first_free,
+      source_root: self.source_root,
+      target_root: self.target_root,
+    };
+    if let Some(raw) = self.repr_mut_at(0) {
+      header.write(raw);
+    }
+  }
+
+  /// Number of link slots, never counting slots past the largest index
+  fn capacity(&self) -> usize {
+    self.mem.as_slice().len().min(RawLink::<T>::MAX_INDEX.saturating_add(1))
+  }
+
   /// Get a raw link from memory
   #[inline]
-  fn repr_at(&self, index: usize) -> Option<&RawLink> {
+  fn repr_at(&self, index: usize) -> Option<&RawLink<T>> {
     let slice = self.mem.as_slice();
     slice.get(index)
   }
 
   /// Get a mutable raw link from memory
   #[inline]
-  fn repr_mut_at(&mut self, index: usize) -> Option<&mut RawLink> {
+  fn repr_mut_at(&mut self, index: usize) -> Option<&mut RawLink<T>> {
     let slice = self.mem.as_mut_slice();
     slice.get_mut(index)
   }
@@ -308,24 +1421,20 @@ where
       return false;
     }
 
-    if let Some(raw) = self.repr_at(idx) {
-      raw.is_free != usize::MAX
-    } else {
-      false
-    }
+    if let Some(raw) = self.repr_at(idx) { !raw.is_free() } else { false }
   }
 
   /// Allocate a new link index
   fn allocate_index(&mut self) -> Result<T, T> {
     if let Some(free_index) = self.first_free {
       let next_free = if let Some(raw) = self.repr_at(free_index) {
-        if raw.source == 0 { None } else { Some(raw.source) }
+        if raw.source() == 0 { None } else { Some(raw.source()) }
       } else {
         None
       };
 
       if let Some(raw) = self.repr_mut_at(free_index) {
-        raw.is_free = 0;
+        *raw = RawLink::default();
       }
 
       self.first_free = next_free;
@@ -334,21 +1443,23 @@ where
     }
 
     let index = self.allocated;
+    let link = T::try_from_usize(index)
+      .filter(|_| index <= RawLink::<T>::MAX_INDEX)
+      .ok_or(Error::Overflow)?;
     self.allocated += 1;
 
-    if self.allocated >= self.mem.as_slice().len() {
-      let current_len = self.mem.as_slice().len();
-      let addition = current_len;
+    let capacity = self.capacity();
+    if self.allocated >= capacity && capacity <= RawLink::<T>::MAX_INDEX {
+      // Double, but never past the slot of the largest index
+      let addition = capacity.min(RawLink::<T>::MAX_INDEX - capacity + 1);
       self.mem.grow(addition).map_err(|_| Error::AllocationFailed)?.zeroed();
     }
 
     if let Some(raw) = self.repr_mut_at(index) {
-      raw.source = 0;
-      raw.target = 0;
-      raw.is_free = 0;
+      *raw = RawLink::default();
     }
 
-    Ok(T::from_usize(index))
+    Ok(link)
   }
 
   /// Free a link index
@@ -357,69 +1468,92 @@ where
     let next_free = self.first_free.unwrap_or(0);
 
     if let Some(raw) = self.repr_mut_at(idx) {
-      raw.source = next_free;
-      raw.target = 0;
-      raw.is_free = usize::MAX;
-      // Clear tree nodes
-      raw.source_tree = Node::default();
-      raw.target_tree = Node::default();
+      *raw = RawLink::default();
+      raw.set_source(next_free);
+      raw.set_free();
     }
 
     self.first_free = Some(idx);
     self.free_count += 1;
   }
 
+  /// Composite key of a link in the source index
+  fn source_key(&self, index: usize) -> [usize; 2] {
+    self.repr_at(index).map_or([0; 2], |raw| [raw.source(), raw.target()])
+  }
+
   /// Attach a link to the source tree
-  fn attach_to_source_tree(&mut self, index: usize)
-  where
-    for<'a> SourceTree<'a, M, SourceStrategy>:
-      SizeBalanced<usize> + AdaptiveRadix<usize>,
-  {
-    let mut tree = SourceTree::<M, SourceStrategy>::new(&mut self.mem);
-    self.source_root =
-      SourceStrategy::insert(&mut tree, self.source_root, index);
+  fn attach_to_source_tree(&mut self, index: usize) -> Result<(), T> {
+    let key = self.source_key(index);
+    let mut tree = SourceTree::<T, M, SourceStrategy>::new(&mut self.mem);
+    let root = SourceStrategy::insert(
+      &mut self.source_index,
+      &mut tree,
+      self.source_root.map(slot::<T>),
+      slot::<T>(index),
+      key,
+    )
+    .map_err(|_| Error::AllocationFailed)?;
+    self.source_root = root.map(unslot::<T>);
+    Ok(())
   }
 
   /// Detach a link from the source tree
-  fn detach_from_source_tree(&mut self, index: usize)
-  where
-    for<'a> SourceTree<'a, M, SourceStrategy>:
-      SizeBalanced<usize> + AdaptiveRadix<usize>,
-  {
-    let mut tree = SourceTree::<M, SourceStrategy>::new(&mut self.mem);
-    self.source_root =
-      SourceStrategy::remove(&mut tree, self.source_root, index);
+  fn detach_from_source_tree(&mut self, index: usize) {
+    let key = self.source_key(index);
+    let mut tree = SourceTree::<T, M, SourceStrategy>::new(&mut self.mem);
+    let root = SourceStrategy::remove(
+      &mut self.source_index,
+      &mut tree,
+      self.source_root.map(slot::<T>),
+      slot::<T>(index),
+      key,
+    );
+    self.source_root = root.map(unslot::<T>);
 
     // Clear the node's tree pointers after removal
     if let Some(raw) = self.repr_mut_at(index) {
-      raw.source_tree = Node::default();
+      raw.source_tree = RawNode::empty();
     }
   }
 
+  /// Composite key of a link in the target index
+  fn target_key(&self, index: usize) -> [usize; 2] {
+    self.repr_at(index).map_or([0; 2], |raw| [raw.target(), raw.source()])
+  }
+
   /// Attach a link to the target tree
-  fn attach_to_target_tree(&mut self, index: usize)
-  where
-    for<'a> TargetTree<'a, M, TargetStrategy>:
-      SizeBalanced<usize> + AdaptiveRadix<usize>,
-  {
-    let mut tree = TargetTree::<M, TargetStrategy>::new(&mut self.mem);
-    self.target_root =
-      TargetStrategy::insert(&mut tree, self.target_root, index);
+  fn attach_to_target_tree(&mut self, index: usize) -> Result<(), T> {
+    let key = self.target_key(index);
+    let mut tree = TargetTree::<T, M, TargetStrategy>::new(&mut self.mem);
+    let root = TargetStrategy::insert(
+      &mut self.target_index,
+      &mut tree,
+      self.target_root.map(slot::<T>),
+      slot::<T>(index),
+      key,
+    )
+    .map_err(|_| Error::AllocationFailed)?;
+    self.target_root = root.map(unslot::<T>);
+    Ok(())
   }
 
   /// Detach a link from the target tree
-  fn detach_from_target_tree(&mut self, index: usize)
-  where
-    for<'a> TargetTree<'a, M, TargetStrategy>:
-      SizeBalanced<usize> + AdaptiveRadix<usize>,
-  {
-    let mut tree = TargetTree::<M, TargetStrategy>::new(&mut self.mem);
-    self.target_root =
-      TargetStrategy::remove(&mut tree, self.target_root, index);
+  fn detach_from_target_tree(&mut self, index: usize) {
+    let key = self.target_key(index);
+    let mut tree = TargetTree::<T, M, TargetStrategy>::new(&mut self.mem);
+    let root = TargetStrategy::remove(
+      &mut self.target_index,
+      &mut tree,
+      self.target_root.map(slot::<T>),
+      slot::<T>(index),
+      key,
+    );
+    self.target_root = root.map(unslot::<T>);
 
     // Clear the node's tree pointers after removal
     if let Some(raw) = self.repr_mut_at(index) {
-      raw.target_tree = Node::default();
+      raw.target_tree = RawNode::empty();
     }
   }
 
@@ -435,270 +1569,364 @@ where
     loop {
       let raw = slice.get(current)?;
 
-      match (source, target).cmp(&(raw.source, raw.target)) {
+      match (source, target).cmp(&(raw.source(), raw.target())) {
+        core::cmp::Ordering::Equal => return Some(current),
+        core::cmp::Ordering::Less => {
+          current = raw.source_node().left?;
+        }
+        core::cmp::Ordering::Greater => {
+          current = raw.source_node().right?;
+        }
+      }
+    }
+  }
+
+  /// Search for a link with exact source and target in target tree
+  fn search_in_target_tree(
+    &self,
+    source: usize,
+    target: usize,
+  ) -> Option<usize> {
+    let mut current = self.target_root?;
+    let slice = self.mem.as_slice();
+
+    loop {
+      let raw = slice.get(current)?;
+
+      match (target, source).cmp(&(raw.target(), raw.source())) {
         core::cmp::Ordering::Equal => return Some(current),
         core::cmp::Ordering::Less => {
-          current = raw.source_tree.left?;
+          current = raw.target_node().left?;
         }
         core::cmp::Ordering::Greater => {
-          current = raw.source_tree.right?;
+          current = raw.target_node().right?;
         }
       }
     }
   }
 
-  /// Traverse source tree calling handler for all links with matching source
-  #[allow(dead_code)]
+  /// Call handler for all links with matching source
   fn each_by_source<H: ReadHandler<T>>(
     &self,
     source: usize,
     handler: &mut H,
   ) -> Flow {
-    self.traverse_source_tree(self.source_root, source, usize::MAX, handler)
+    if SourceStrategy::ORDERED {
+      return self.traverse_source_tree(source, usize::MAX, handler);
+    }
+    if let Some(flow) = self.each_in_source_index(&[source], handler) {
+      return flow;
+    }
+    self.each_linear(T::from_usize(source), T::ANY, handler)
   }
 
-  /// Traverse target tree calling handler for all links with matching target
-  #[allow(dead_code)]
+  /// Call handler for all links with matching target
   fn each_by_target<H: ReadHandler<T>>(
     &self,
     target: usize,
     handler: &mut H,
   ) -> Flow {
-    self.traverse_target_tree(self.target_root, target, usize::MAX, handler)
+    if TargetStrategy::ORDERED {
+      return self.traverse_target_tree(target, usize::MAX, handler);
+    }
+    if let Some(flow) = self.each_in_target_index(&[target], handler) {
+      return flow;
+    }
+    self.each_linear(T::ANY, T::from_usize(target), handler)
   }
 
-  /// Recursively traverse source tree for links with matching source
-  #[allow(dead_code)]
-  fn traverse_source_tree<H: ReadHandler<T>>(
+  /// Walk the out-of-line source index for keys starting with `prefix`
+  fn each_in_source_index<H: ReadHandler<T>>(
     &self,
-    current: Option<usize>,
-    source: usize,
-    target: usize,
+    prefix: &[usize],
     handler: &mut H,
-  ) -> Flow {
-    let idx = match current {
-      Some(i) => i,
-      None => return Flow::Continue,
-    };
+  ) -> Option<Flow> {
+    SourceStrategy::each_prefix(&self.source_index, prefix, &mut |idx| {
+      handler.handle(self.link_at(idx))
+    })
+  }
 
-    let slice = self.mem.as_slice();
-    let raw = match slice.get(idx) {
-      Some(r) => r,
-      None => return Flow::Continue,
-    };
+  /// Walk the out-of-line target index for keys starting with `prefix`
+  fn each_in_target_index<H: ReadHandler<T>>(
+    &self,
+    prefix: &[usize],
+    handler: &mut H,
+  ) -> Option<Flow> {
+    TargetStrategy::each_prefix(&self.target_index, prefix, &mut |idx| {
+      handler.handle(self.link_at(idx))
+    })
+  }
 
-    // When searching by source with wildcard target
-    if target == usize::MAX {
-      // Do an in-order traversal, visiting only nodes where source matches
-      // Since tree is ordered by (source, target), matching nodes are
-      // contiguous in in-order traversal
-
-      // If current node's source < search source, go right
-      if raw.source < source {
-        return self.traverse_source_tree(
-          raw.source_tree.right,
-          source,
-          target,
-          handler,
-        );
-      }
-
-      // If current node's source > search source, go left
-      if raw.source > source {
-        return self.traverse_source_tree(
-          raw.source_tree.left,
-          source,
-          target,
-          handler,
-        );
-      }
-
-      // Current node's source == search source, traverse both subtrees
-      if self.traverse_source_tree(
-        raw.source_tree.left,
-        source,
-        target,
-        handler,
-      ) == Flow::Break
-      {
-        return Flow::Break;
+  /// Up to `limit` links ordered after the position of `cursor`
+  fn fetch_after(&self, cursor: &Cursor<T>, limit: usize) -> Vec<Link<T>> {
+    let (key, by_source) = match cursor.scan {
+      Scan::All => {
+        let start = cursor.last.map_or(1, |link| link.index.as_usize() + 1);
+        return (start..self.allocated)
+          .filter(|&idx| self.exists(T::from_usize(idx)))
+          .take(limit)
+          .map(|idx| self.link_at(idx))
+          .collect();
       }
+      Scan::Source(source) => (source.as_usize(), true),
+      Scan::Target(target) => (target.as_usize(), false),
+    };
 
-      // Check current node
-      let link = Link::new(
-        T::from_usize(idx),
-        T::from_usize(raw.source),
-        T::from_usize(raw.target),
-      );
-      if handler.handle(link) == Flow::Break {
-        return Flow::Break;
+    let slice = self.mem.as_slice();
+    let key_of = |idx: usize| {
+      let raw = &slice[idx];
+      if by_source {
+        [raw.source(), raw.target(), idx]
+      } else {
+        [raw.target(), raw.source(), idx]
       }
+    };
+    let after = cursor.last.map(|link| {
+      let [source, target, index] =
+        [link.source, link.target, link.index].map(|part| part.as_usize());
+      if by_source { [source, target, index] } else { [target, source, index] }
+    });
+    let past = |idx: usize| after.is_none_or(|after| key_of(idx) > after);
+
+    let ordered =
+      if by_source { SourceStrategy::ORDERED } else { TargetStrategy::ORDERED };
+    if ordered {
+      let root = if by_source { self.source_root } else { self.target_root };
+      let tree =
+        if by_source { RawLink::source_node } else { RawLink::target_node };
+      let cmp = |idx: usize| match key_of(idx)[0].cmp(&key) {
+        Ordering::Equal if !past(idx) => Ordering::Less,
+        order => order,
+      };
+      return trees::Iter::new(root, |idx| slice.get(idx).map(tree), cmp)
+        .take(limit)
+        .map(|idx| self.link_at(idx))
+        .collect();
+    }
 
-      // Continue to right subtree
-      return self.traverse_source_tree(
-        raw.source_tree.right,
-        source,
-        target,
-        handler,
-      );
-    } else {
-      // Exact (source, target) search - can prune efficiently
-      // Traverse left subtree if it might contain matches
-      if (source, target) < (raw.source, raw.target)
-        && self.traverse_source_tree(
-          raw.source_tree.left,
-          source,
-          target,
-          handler,
-        ) == Flow::Break
-      {
-        return Flow::Break;
+    // Out-of-line indexes seek past the cursor when they can,
+    // otherwise earlier keys are walked and skipped
+    let mut found = Vec::new();
+    let mut handler = |idx: usize| {
+      if past(idx) {
+        found.push(self.link_at(idx));
       }
+      if found.len() < limit { Flow::Continue } else { Flow::Break }
+    };
+    let from = after.map_or([key, 0, 0], |[a, b, idx]| [a, b, idx + 1]);
+    let flow = if by_source {
+      SourceStrategy::each_prefix_from(
+        &self.source_index,
+        &[key],
+        from,
+        &mut handler,
+      )
+      .or_else(|| {
+        SourceStrategy::each_prefix(&self.source_index, &[key], &mut handler)
+      })
+    } else {
+      TargetStrategy::each_prefix_from(
+        &self.target_index,
+        &[key],
+        from,
+        &mut handler,
+      )
+      .or_else(|| {
+        TargetStrategy::each_prefix(&self.target_index, &[key], &mut handler)
+      })
+    };
+    if flow.is_some() {
+      return found;
+    }
 
-      // Check current node
-      if raw.source == source && raw.target == target {
-        let link = Link::new(
-          T::from_usize(idx),
-          T::from_usize(raw.source),
-          T::from_usize(raw.target),
-        );
-        if handler.handle(link) == Flow::Break {
-          return Flow::Break;
-        }
-      }
+    let mut found: Vec<_> = (1..self.allocated)
+      .filter(|&idx| self.exists(T::from_usize(idx)))
+      .filter(|&idx| key_of(idx)[0] == key && past(idx))
+      .collect();
+    found.sort_unstable_by_key(|&idx| key_of(idx));
+    found.into_iter().take(limit).map(|idx| self.link_at(idx)).collect()
+  }
 
-      // Traverse right subtree if it might contain matches
-      if (source, target) > (raw.source, raw.target)
-        && self.traverse_source_tree(
-          raw.source_tree.right,
-          source,
-          target,
-          handler,
-        ) == Flow::Break
-      {
-        return Flow::Break;
-      }
-    }
+  /// Link stored at an allocated index
+  fn link_at(&self, idx: usize) -> Link<T> {
+    let raw = &self.mem.as_slice()[idx];
+    Link::new(
+      T::from_usize(idx),
+      T::from_usize(raw.source()),
+      T::from_usize(raw.target()),
+    )
+  }
 
-    Flow::Continue
+  /// Stream links of the source tree matching `source` and `target` in
+  /// (source, target) order, `usize::MAX` as target matches any
+  fn traverse_source_tree<H: ReadHandler<T>>(
+    &self,
+    source: usize,
+    target: usize,
+    handler: &mut H,
+  ) -> Flow {
+    self.traverse_tree(
+      self.source_root,
+      |raw| (raw.source_node(), (raw.source(), raw.target())),
+      (source, target),
+      handler,
+    )
   }
 
-  /// Recursively traverse target tree for links with matching target
-  #[allow(dead_code)]
+  /// Stream links of the target tree matching `target` and `source` in
+  /// (target, source) order, `usize::MAX` as source matches any
   fn traverse_target_tree<H: ReadHandler<T>>(
     &self,
-    current: Option<usize>,
     target: usize,
     source: usize,
     handler: &mut H,
   ) -> Flow {
-    let idx = match current {
-      Some(i) => i,
-      None => return Flow::Continue,
-    };
+    self.traverse_tree(
+      self.target_root,
+      |raw| (raw.target_node(), (raw.target(), raw.source())),
+      (target, source),
+      handler,
+    )
+  }
 
+  /// Walk an ordered tree embedded in link memory over keys equal to
+  /// `query`, where `usize::MAX` as its second part matches any
+  ///
+  /// Matching keys are contiguous in tree order, so the iterator descends
+  /// straight to the first one and stops after the last.
+  fn traverse_tree<H: ReadHandler<T>>(
+    &self,
+    root: Option<usize>,
+    view: impl Fn(&RawLink<T>) -> (Node<usize>, (usize, usize)),
+    query: (usize, usize),
+    handler: &mut H,
+  ) -> Flow {
     let slice = self.mem.as_slice();
-    let raw = match slice.get(idx) {
-      Some(r) => r,
-      None => return Flow::Continue,
+    let nodes = |idx: usize| slice.get(idx).map(|raw| view(raw).0);
+    let cmp = |idx: usize| {
+      let (_, key) = view(&slice[idx]);
+      if query.1 == usize::MAX { key.0.cmp(&query.0) } else { key.cmp(&query) }
     };
 
-    // When searching by target with wildcard source
-    if source == usize::MAX {
-      // Do an in-order traversal, visiting only nodes where target matches
-      // Since tree is ordered by (target, source), matching nodes are
-      // contiguous in in-order traversal
-
-      // If current node's target < search target, go right
-      if raw.target < target {
-        return self.traverse_target_tree(
-          raw.target_tree.right,
-          target,
-          source,
-          handler,
-        );
-      }
-
-      // If current node's target > search target, go left
-      if raw.target > target {
-        return self.traverse_target_tree(
-          raw.target_tree.left,
-          target,
-          source,
-          handler,
-        );
-      }
-
-      // Current node's target == search target, traverse both subtrees
-      if self.traverse_target_tree(
-        raw.target_tree.left,
-        target,
-        source,
-        handler,
-      ) == Flow::Break
-      {
+    for idx in trees::Iter::new(root, nodes, cmp) {
+      if handler.handle(self.link_at(idx)) == Flow::Break {
         return Flow::Break;
       }
+    }
+    Flow::Continue
+  }
 
-      // Check current node
-      let link = Link::new(
-        T::from_usize(idx),
-        T::from_usize(raw.source),
-        T::from_usize(raw.target),
-      );
-      if handler.handle(link) == Flow::Break {
-        return Flow::Break;
-      }
+  /// Find any link with exact source and target
+  fn find_exact(&self, source: T, target: T) -> Option<T> {
+    let (s, t) = (source.as_usize(), target.as_usize());
+    if SourceStrategy::ORDERED {
+      self.search_in_source_tree(s, t).map(T::from_usize)
+    } else if TargetStrategy::ORDERED {
+      self.search_in_target_tree(s, t).map(T::from_usize)
+    } else {
+      let mut found = None;
+      self.each_exact(source, target, &mut |link: Link<T>| {
+        found = Some(link.index);
+        Flow::Break
+      });
+      found
+    }
+  }
 
-      // Continue to right subtree
-      return self.traverse_target_tree(
-        raw.target_tree.right,
-        target,
-        source,
-        handler,
-      );
+  /// Call handler for all links with exact source and target
+  fn each_exact<H: ReadHandler<T>>(
+    &self,
+    source: T,
+    target: T,
+    handler: &mut H,
+  ) -> Flow {
+    let (s, t) = (source.as_usize(), target.as_usize());
+    if SourceStrategy::ORDERED {
+      self.traverse_source_tree(s, t, handler)
+    } else if TargetStrategy::ORDERED {
+      self.traverse_target_tree(t, s, handler)
+    } else if let Some(flow) = self.each_in_source_index(&[s, t], handler) {
+      flow
+    } else if let Some(flow) = self.each_in_target_index(&[t, s], handler) {
+      flow
     } else {
-      // Exact (target, source) search - can prune efficiently
-      // Traverse left subtree if it might contain matches
-      if (target, source) < (raw.target, raw.source)
-        && self.traverse_target_tree(
-          raw.target_tree.left,
-          target,
-          source,
-          handler,
-        ) == Flow::Break
+      self.each_linear(source, target, handler)
+    }
+  }
+
+  /// Scan all links calling handler for ones matching source and target
+  fn each_linear<H: ReadHandler<T>>(
+    &self,
+    source: T,
+    target: T,
+    handler: &mut H,
+  ) -> Flow {
+    for i in 1..self.allocated {
+      let index = T::from_usize(i);
+      if self.exists(index)
+        && let Some(raw) = self.repr_at(i)
       {
-        return Flow::Break;
-      }
+        let raw_source = T::from_usize(raw.source());
+        let raw_target = T::from_usize(raw.target());
+
+        let matches = (source == T::ANY || source == raw_source)
+          && (target == T::ANY || target == raw_target);
 
-      // Check current node
-      if raw.target == target && raw.source == source {
-        let link = Link::new(
-          T::from_usize(idx),
-          T::from_usize(raw.source),
-          T::from_usize(raw.target),
-        );
-        if handler.handle(link) == Flow::Break {
+        if matches
+          && handler.handle(Link::new(index, raw_source, raw_target))
+            == Flow::Break
+        {
           return Flow::Break;
         }
       }
-
-      // Traverse right subtree if it might contain matches
-      if (target, source) > (raw.target, raw.source)
-        && self.traverse_target_tree(
-          raw.target_tree.right,
-          target,
-          source,
-          handler,
-        ) == Flow::Break
-      {
-        return Flow::Break;
-      }
     }
-
     Flow::Continue
   }
 
+  /// Count links by source and/or target using subtree sizes
+  ///
+  /// Answers with [`SizeBalanced::count_range`] between probe keys placed
+  /// before and after every matching link.
+  /// Returns `None` when the relevant tree is not ordered and sized.
+  fn count_by_rank(&self, source: T, target: T) -> Option<usize> {
+    let (s, t) = (source.as_usize(), target.as_usize());
+    let by_source = SourceStrategy::ORDERED && SourceStrategy::SIZED;
+    let by_target = TargetStrategy::ORDERED && TargetStrategy::SIZED;
+    let links = self.mem.as_slice();
+    let sources = |lo, hi| Ranks {
+      links,
+      node: RawLink::source_node,
+      key: |raw| (raw.source(), raw.target()),
+      lo,
+      hi,
+    };
+    let targets = |lo, hi| Ranks {
+      links,
+      node: RawLink::target_node,
+      key: |raw| (raw.target(), raw.source()),
+      lo,
+      hi,
+    };
+
+    let count = match (source != T::ANY, target != T::ANY) {
+      (false, false) => self.count_total(),
+      (true, true) if by_source => {
+        sources((s, t, 0), (s, t, usize::MAX)).count(self.source_root)
+      }
+      (true, true) if by_target => {
+        targets((t, s, 0), (t, s, usize::MAX)).count(self.target_root)
+      }
+      (true, false) if by_source => {
+        sources((s, 0, 0), (s, usize::MAX, usize::MAX)).count(self.source_root)
+      }
+      (false, true) if by_target => {
+        targets((t, 0, 0), (t, usize::MAX, usize::MAX)).count(self.target_root)
+      }
+      _ => return None,
+    };
+    Some(count)
+  }
+
   /// Count all non-free links
   fn count_total(&self) -> usize {
     self.allocated - self.free_count - 1
@@ -709,7 +1937,7 @@ impl<T, M, SourceStrategy, TargetStrategy> Links<T>
   for Store<T, M, SourceStrategy, TargetStrategy>
 where
   T: Index,
-  M: RawMem<Item = RawLink> + Send + Sync,
+  M: RawMem<Item = RawLink<T>> + Send + Sync,
   SourceStrategy: TreeStrategy<usize>,
   TargetStrategy: TreeStrategy<usize>,
 {
@@ -727,6 +1955,14 @@ where
         }
       }
       _ => {
+        let source = query[1];
+        let target = if N >= 3 { query[2] } else { T::ANY };
+        if query[0] == T::ANY
+          && let Some(count) = self.count_by_rank(source, target)
+        {
+          return T::from_usize(count);
+        }
+
         let mut count = 0;
         self.each(query, &mut |_| {
           count += 1;
@@ -742,30 +1978,37 @@ where
     query: [T; N],
     handler: &mut H,
   ) -> Result<Flow, T> {
-    let index = self.allocate_index()?;
-    let before = Link::nothing();
-
     let (source, target) = match N {
       0 => (T::ZERO, T::ZERO),
       1 => (query[0], query[0]),
       _ => (query[0], query[1]),
     };
+    self.ensure_unique(T::ZERO, source, target)?;
+
+    let before = Link::nothing();
+    // Peek at the index so a vetoed create leaves the free list as it was
+    let next = self.next_index().ok_or(Error::Overflow)?;
+    self.observers.allow(before, Link::new(next, source, target))?;
+
+    let index = self.allocate_index()?;
 
     let idx = index.as_usize();
 
     if let Some(raw) = self.repr_mut_at(idx) {
-      raw.source = source.as_usize();
-      raw.target = target.as_usize();
-      raw.is_free = 0;
-      raw.source_tree = Node::default();
-      raw.target_tree = Node::default();
+      raw.set_source(source.as_usize());
+      raw.set_target(target.as_usize());
+      raw.source_tree = RawNode::empty();
+      raw.target_tree = RawNode::empty();
     }
 
     // Attach to both trees for efficient searching
-    self.attach_to_source_tree(idx);
-    self.attach_to_target_tree(idx);
+    self.attach_to_source_tree(idx)?;
+    self.attach_to_target_tree(idx)?;
+
+    self.sync_header();
 
     let after = Link::new(index, source, target);
+    self.observers.notify(before, after);
     Ok(handler.handle(before, after))
   }
 
@@ -781,8 +2024,8 @@ where
         if self.exists(index)
           && let Some(raw) = self.repr_at(i)
         {
-          let source = T::from_usize(raw.source);
-          let target = T::from_usize(raw.target);
+          let source = T::from_usize(raw.source());
+          let target = T::from_usize(raw.target());
           let link = Link::new(index, source, target);
           if handler.handle(link) == Flow::Break {
             return Flow::Break;
@@ -800,8 +2043,8 @@ where
       } else if self.exists(index_query)
         && let Some(raw) = self.repr_at(index_query.as_usize())
       {
-        let source = T::from_usize(raw.source);
-        let target = T::from_usize(raw.target);
+        let source = T::from_usize(raw.source());
+        let target = T::from_usize(raw.target());
         return handler.handle(Link::new(index_query, source, target));
       }
       return Flow::Continue;
@@ -812,49 +2055,12 @@ where
 
     // Use tree-based search when possible for better performance
     if index_query == T::ANY {
-      // Query by source and/or target
-      if source != T::ANY && target != T::ANY {
-        // Exact (source, target) search - use tree
-        if let Some(idx) =
-          self.search_in_source_tree(source.as_usize(), target.as_usize())
-          && self.exists(T::from_usize(idx))
-        {
-          let raw = self.repr_at(idx).unwrap();
-          let link = Link::new(
-            T::from_usize(idx),
-            T::from_usize(raw.source),
-            T::from_usize(raw.target),
-          );
-          return handler.handle(link);
-        }
-        return Flow::Continue;
-      } else if source != T::ANY || target != T::ANY {
-        // Wildcard queries - use linear scan due to SBT corruption bugs
-        // TODO: Fix SBT remove bugs or switch to ART to enable tree traversal
-        for i in 1..self.allocated {
-          let index = T::from_usize(i);
-          if self.exists(index)
-            && let Some(raw) = self.repr_at(i)
-          {
-            let raw_source = T::from_usize(raw.source);
-            let raw_target = T::from_usize(raw.target);
-
-            let matches = (source == T::ANY || source == raw_source)
-              && (target == T::ANY || target == raw_target);
-
-            if matches {
-              let link = Link::new(index, raw_source, raw_target);
-              if handler.handle(link) == Flow::Break {
-                return Flow::Break;
-              }
-            }
-          }
-        }
-        return Flow::Continue;
-      } else {
-        // No constraints - enumerate all
-        return self.each([], handler);
-      }
+      return match (source != T::ANY, target != T::ANY) {
+        (true, true) => self.each_exact(source, target, handler),
+        (true, false) => self.each_by_source(source.as_usize(), handler),
+        (false, true) => self.each_by_target(target.as_usize(), handler),
+        (false, false) => self.each([], handler),
+      };
     }
 
     // Query with specific index - direct lookup
@@ -867,8 +2073,8 @@ where
       None => return Flow::Continue,
     };
 
-    let raw_source = T::from_usize(raw.source);
-    let raw_target = T::from_usize(raw.target);
+    let raw_source = T::from_usize(raw.source());
+    let raw_target = T::from_usize(raw.target());
 
     let matches = (source == T::ANY || source == raw_source)
       && (target == T::ANY || target == raw_target);
@@ -904,23 +2110,28 @@ where
     let idx = index.as_usize();
 
     // If source or target changed, update tree positions
-    if new_source != before.source || new_target != before.target {
+    let after = Link::new(index, new_source, new_target);
+    if after != before {
+      self.ensure_unique(index, new_source, new_target)?;
+      self.observers.allow(before, after)?;
+
       // Detach from old positions in both trees
       self.detach_from_source_tree(idx);
       self.detach_from_target_tree(idx);
 
       // Update the link data
       if let Some(raw) = self.repr_mut_at(idx) {
-        raw.source = new_source.as_usize();
-        raw.target = new_target.as_usize();
+        raw.set_source(new_source.as_usize());
+        raw.set_target(new_target.as_usize());
       }
 
       // Reattach to new positions in both trees
-      self.attach_to_source_tree(idx);
-      self.attach_to_target_tree(idx);
+      self.attach_to_source_tree(idx)?;
+      self.attach_to_target_tree(idx)?;
+      self.sync_header();
+      self.observers.notify(before, after);
     }
 
-    let after = Link::new(index, new_source, new_target);
     Ok(handler.handle(before, after))
   }
 
@@ -940,14 +2151,22 @@ where
 
     let before = self.get(index).ok_or(Error::NotExists(index))?;
 
+    if self.integrity == Integrity::Strict && self.has_usages(index) {
+      return Err(Error::HasUsages(index));
+    }
+
+    let after = Link::nothing();
+    self.observers.allow(before, after)?;
+
     // Detach from both trees before freeing
     let idx = index.as_usize();
     self.detach_from_source_tree(idx);
     self.detach_from_target_tree(idx);
 
     self.free_index(index);
+    self.sync_header();
 
-    let after = Link::nothing();
+    self.observers.notify(before, after);
     Ok(handler.handle(before, after))
   }
 
@@ -957,16 +2176,23 @@ where
     }
 
     let raw = self.repr_at(index.as_usize())?;
-    let source = T::from_usize(raw.source);
-    let target = T::from_usize(raw.target);
+    let source = T::from_usize(raw.source());
+    let target = T::from_usize(raw.target());
     Some(Link::new(index, source, target))
   }
+
+  fn next_index(&self) -> Option<T> {
+    let index = self.first_free.unwrap_or(self.allocated);
+    T::try_from_usize(index).filter(|_| index <= RawLink::<T>::MAX_INDEX)
+  }
 }
 
+/// Heap memory backing stores made by the `create_heap_store` functions
+type HeapMem<T> = Alloc<RawLink<T>>;
+
 /// Create a doublets store with heap allocation using SBT
 /// (Size-Balanced Tree) for both source and target trees
-pub fn create_heap_store<T>()
--> Result<Store<T, Alloc<RawLink>, SbtStrategy, SbtStrategy>, T>
+pub fn create_heap_store<T>() -> Result<Store<T>, T>
 where
   T: Index,
 {
@@ -975,7 +2201,7 @@ where
 
 /// Create a doublets store with heap allocation and custom tree strategies
 pub fn create_heap_store_with_strategies<T, SourceStrategy, TargetStrategy>()
--> Result<Store<T, Alloc<RawLink>, SourceStrategy, TargetStrategy>, T>
+-> Result<Store<T, HeapMem<T>, SourceStrategy, TargetStrategy>, T>
 where
   T: Index,
   SourceStrategy: TreeStrategy<usize>,
@@ -1,4 +1,12 @@
-use crate::{Error, Flow, Index, Link, ReadHandler, Result, WriteHandler};
+use {
+  crate::{
+    Error, Flow, Index, Link, ReadHandler, Result, Snapshot, WriteHandler, lino,
+  },
+  std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Write},
+  },
+};
 
 /// Core trait for doublets storage operations
 ///
@@ -47,6 +55,15 @@ pub trait Links<T: Index>: Send + Sync {
 
   /// Get a specific link by index
   fn get(&self, index: T) -> Option<Link<T>>;
+
+  /// Index the next `create` will give its link, if the store can tell
+  ///
+  /// Transactions use it to log a create before it is applied. Without
+  /// it, which is the default, creates are logged right after they are
+  /// applied instead. `None` is also the answer once no index is left.
+  fn next_index(&self) -> Option<T> {
+    None
+  }
 }
 
 /// High-level doublets operations
@@ -196,6 +213,75 @@ pub trait Doublets<T: Index>: Links<T> {
     }
   }
 
+  /// Delete a link together with every link that depends on it
+  ///
+  /// Dependents (links using the index as source or target, transitively)
+  /// are removed before the links they use, and each removal is reported
+  /// through `handler`. Returning [`Flow::Break`] stops the cascade.
+  ///
+  /// Links referencing each other in a cycle cannot be ordered, so their
+  /// remaining references are first pointed back at themselves, and each
+  /// such rewrite is reported through `handler` as an update.
+  ///
+  /// A break leaves the cascade where it stopped: the links deleted and
+  /// rewritten so far stay as they are, the rest are untouched.
+  fn delete_cascade<H: WriteHandler<T>>(
+    &mut self,
+    index: T,
+    handler: &mut H,
+  ) -> Result<Flow, T> {
+    let _ = self.get(index).ok_or(Error::NotExists(index))?;
+
+    // Post-order walk over dependents: a link is emitted after all its users
+    let mut order = Vec::new();
+    let mut visited = BTreeSet::from([index]);
+    let mut stack = vec![(index, false)];
+    while let Some((link, expanded)) = stack.pop() {
+      if expanded {
+        order.push(link);
+        continue;
+      }
+      stack.push((link, true));
+      let mut visit = |user: Link<T>| {
+        if user.index != link && visited.insert(user.index) {
+          stack.push((user.index, false));
+        }
+        Flow::Continue
+      };
+      self.each([T::ANY, link, T::ANY], &mut visit);
+      self.each([T::ANY, T::ANY, link], &mut visit);
+    }
+
+    for link in order {
+      let mut users = Vec::new();
+      let mut collect = |user: Link<T>| {
+        if user.index != link {
+          users.push(user);
+        }
+        Flow::Continue
+      };
+      self.each([T::ANY, link, T::ANY], &mut collect);
+      self.each([T::ANY, T::ANY, link], &mut collect);
+      users.sort_by_key(|user| user.index);
+      users.dedup_by_key(|user| user.index);
+
+      for user in users {
+        let source = if user.source == link { user.index } else { user.source };
+        let target = if user.target == link { user.index } else { user.target };
+        let change = [user.index, source, target];
+        if self.update([user.index], change, handler)? == Flow::Break {
+          return Ok(Flow::Break);
+        }
+      }
+
+      if self.delete([link], handler)? == Flow::Break {
+        return Ok(Flow::Break);
+      }
+    }
+
+    Ok(Flow::Continue)
+  }
+
   /// Collect all links into a vector
   fn collect_all(&self) -> Vec<Link<T>> {
     let count = self.count_all().as_usize();
@@ -211,6 +297,38 @@ pub trait Doublets<T: Index>: Links<T> {
   fn iter(&self) -> impl Iterator<Item = Link<T>> {
     self.collect_all().into_iter()
   }
+
+  /// Write all links to `writer` as a checksummed [`Snapshot`]
+  fn export<W: Write>(&self, writer: W) -> Result<(), T> {
+    Snapshot::new().export(self, writer)
+  }
+
+  /// Load a [`Snapshot`] from `reader` into this empty store
+  fn import<R: Read>(&mut self, reader: R) -> Result<(), T> {
+    Snapshot::import(self, reader)
+  }
+
+  /// Render all links as Links Notation, one `(index: source target)` per
+  /// line in index order
+  fn to_lino(&self) -> String {
+    let mut text = Vec::new();
+    self.write_lino(&mut text).expect("writing to a vector cannot fail");
+    String::from_utf8(text).expect("links notation is ASCII")
+  }
+
+  /// Write all links to `writer` as Links Notation
+  fn write_lino<W: Write>(&self, writer: W) -> Result<(), T> {
+    lino::write(self, writer)
+  }
+
+  /// Load links written as `(index: source target)` or `(source target)`
+  ///
+  /// Named indices are kept where they are free and remapped to fresh
+  /// indices where they collide with existing links, references to them
+  /// follow along. Returns the index each named link received.
+  fn load_lino(&mut self, text: &str) -> Result<BTreeMap<T, T>, T> {
+    lino::load(self, text)
+  }
 }
 
 impl<T: Index, S: Links<T> + ?Sized> Doublets<T> for S {}
@@ -0,0 +1,427 @@
+use {
+  crate::{
+    Error, Flow, Index, Link, Links, ReadHandler, Result, WriteHandler,
+    observer::Undo,
+  },
+  std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+  },
+};
+
+/// Size of a single log record: tag byte followed by `before` and `after`
+const RECORD_SIZE: usize = 1 + 6 * size_of::<u64>();
+
+/// Entry of the transaction log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Record<T: Index> {
+  Begin,
+  Change(Link<T>, Link<T>),
+  Commit,
+  Rollback,
+}
+
+impl<T: Index> Record<T> {
+  fn encode(self) -> [u8; RECORD_SIZE] {
+    let (tag, before, after) = match self {
+      Self::Begin => (0, Link::nothing(), Link::nothing()),
+      Self::Change(before, after) => (1, before, after),
+      Self::Commit => (2, Link::nothing(), Link::nothing()),
+      Self::Rollback => (3, Link::nothing(), Link::nothing()),
+    };
+
+    let mut bytes = [0; RECORD_SIZE];
+    bytes[0] = tag;
+    let words = [before, after]
+      .into_iter()
+      .flat_map(|link| [link.index, link.source, link.target]);
+    for (chunk, word) in bytes[1..].chunks_exact_mut(8).zip(words) {
+      chunk.copy_from_slice(&(word.as_usize() as u64).to_le_bytes());
+    }
+    bytes
+  }
+
+  fn decode(bytes: &[u8]) -> Option<Self> {
+    let mut words = bytes[1..].chunks_exact(8).map(|chunk| {
+      T::from_usize(u64::from_le_bytes(chunk.try_into().unwrap()) as usize)
+    });
+    let mut link = || {
+      let [index, source, target] =
+        [words.next(), words.next(), words.next()].map(Option::unwrap);
+      Link::new(index, source, target)
+    };
+
+    match bytes[0] {
+      0 => Some(Self::Begin),
+      1 => Some(Self::Change(link(), link())),
+      2 => Some(Self::Commit),
+      3 => Some(Self::Rollback),
+      _ => None,
+    }
+  }
+}
+
+/// Append-only file of fixed-size log records
+struct Log {
+  file: File,
+}
+
+impl Log {
+  fn open(path: &Path) -> io::Result<Self> {
+    let file = OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .read(true)
+      .append(true)
+      .open(path)?;
+    Ok(Self { file })
+  }
+
+  fn append<T: Index>(&mut self, record: Record<T>) -> io::Result<()> {
+    self.file.write_all(&record.encode())
+  }
+
+  fn sync(&mut self) -> io::Result<()> {
+    self.file.sync_data()
+  }
+
+  /// Read every complete record, ignoring a torn trailing write
+  fn read_all<T: Index>(&mut self) -> io::Result<Option<Vec<Record<T>>>> {
+    let mut bytes = Vec::new();
+    self.file.seek(SeekFrom::Start(0))?;
+    self.file.read_to_end(&mut bytes)?;
+
+    Ok(bytes.chunks_exact(RECORD_SIZE).map(Record::decode).collect())
+  }
+
+  fn clear(&mut self) -> io::Result<()> {
+    self.file.set_len(0)?;
+    self.file.sync_all()
+  }
+}
+
+/// Revert `changes` received by a `WriteHandler`, in reverse order
+///
+/// Each step only applies if the link still matches its `after` state,
+/// so a partially applied or interrupted undo can safely be repeated.
+/// Observers are not asked to allow the steps while it runs.
+pub(crate) fn revert<T, S>(
+  store: &mut S,
+  changes: &[(Link<T>, Link<T>)],
+) -> Result<(), T>
+where
+  T: Index,
+  S: Links<T> + ?Sized,
+{
+  let mut ignore = |_: Link<T>, _: Link<T>| Flow::Continue;
+
+  let _undo = Undo::start();
+  for &(before, after) in changes.iter().rev() {
+    let index = if after.is_null() { before.index } else { after.index };
+    let current = store.get(index);
+    match (before.is_null(), after.is_null()) {
+      // Undo create
+      (true, false) if current == Some(after) => {
+        store.delete([after.index], &mut ignore)?;
+      }
+      // Undo delete: the freed index is on top of the free list
+      (false, true) if current.is_none() => {
+        let mut index = T::ZERO;
+        let mut created = |_: Link<T>, after: Link<T>| {
+          index = after.index;
+          Flow::Continue
+        };
+        store.create([before.source, before.target], &mut created)?;
+        if index != before.index {
+          return Err(Error::CorruptLog);
+        }
+      }
+      // Undo update
+      (false, false) if current == Some(after) => {
+        store.update(
+          [before.index],
+          [before.index, before.source, before.target],
+          &mut ignore,
+        )?;
+      }
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+/// Transactional wrapper around a links store
+///
+/// Groups `create`/`update`/`delete` calls into atomic units. While a
+/// transaction is active every change is written ahead to a log file as
+/// the `before`/`after` pair the `WriteHandler` will receive, and synced
+/// before the store is touched. Rolling back, or opening the log after
+/// the process died mid-transaction, undoes those changes in reverse
+/// order, so the store returns to the state of the last committed
+/// transaction. The log is emptied once a transaction ends.
+///
+/// Changes made outside of a transaction are applied directly.
+///
+/// Commit guarantees atomicity across process crashes. Durability against
+/// power loss additionally depends on the memory backend being flushed.
+///
+/// # Examples
+/// ```
+/// use doublets::{Doublets, Transactional, create_heap_store};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let store = create_heap_store::<usize>()?;
+/// let mut store = Transactional::open(store, dir.path().join("db.log"))?;
+///
+/// let a = store.create_point()?;
+/// let result = store.transaction(|store| {
+///   store.create_link(a, a)?;
+///   store.delete_link(a)
+/// });
+///
+/// // `a` is still used by the new link, so the whole transaction is undone
+/// assert!(result.is_err());
+/// assert_eq!(store.count_all(), 1);
+/// # Ok::<_, doublets::Error<usize>>(())
+/// ```
+pub struct Transactional<T: Index, S: Links<T>> {
+  store: S,
+  log: Log,
+  /// Changes of the active transaction, if any
+  active: Option<Vec<(Link<T>, Link<T>)>>,
+  _phantom: PhantomData<T>,
+}
+
+impl<T: Index, S: Links<T>> Transactional<T, S> {
+  /// Wrap `store` with the log at `path`, recovering unfinished work
+  ///
+  /// A transaction left open in the log (the process died before it was
+  /// committed or rolled back) is undone before the store is returned.
+  /// The log is then cleared, since committed changes live in the store.
+  pub fn open<P: AsRef<Path>>(store: S, path: P) -> Result<Self, T> {
+    let mut log = Log::open(path.as_ref())?;
+    let records = log.read_all::<T>()?.ok_or(Error::CorruptLog)?;
+
+    let mut this = Self { store, log, active: None, _phantom: PhantomData };
+
+    let mut pending = None;
+    for record in records {
+      match record {
+        Record::Begin => pending = Some(Vec::new()),
+        Record::Change(before, after) => {
+          pending.as_mut().ok_or(Error::CorruptLog)?.push((before, after))
+        }
+        Record::Commit | Record::Rollback => pending = None,
+      }
+    }
+    if let Some(changes) = pending {
+      this.undo(&changes)?;
+    }
+
+    this.log.clear()?;
+    Ok(this)
+  }
+
+  /// Get the wrapped store
+  pub fn store(&self) -> &S {
+    &self.store
+  }
+
+  /// Unwrap the store, discarding the log handle
+  ///
+  /// Fails with [`Error::TransactionActive`] if a transaction is active.
+  pub fn into_inner(self) -> Result<S, T> {
+    if self.active.is_some() {
+      return Err(Error::TransactionActive);
+    }
+    Ok(self.store)
+  }
+
+  /// Check whether a transaction is active
+  pub fn in_transaction(&self) -> bool {
+    self.active.is_some()
+  }
+
+  /// Start a transaction
+  pub fn begin(&mut self) -> Result<(), T> {
+    if self.active.is_some() {
+      return Err(Error::TransactionActive);
+    }
+    self.log.append::<T>(Record::Begin)?;
+    self.active = Some(Vec::new());
+    Ok(())
+  }
+
+  /// Make the changes of the active transaction permanent
+  pub fn commit(&mut self) -> Result<(), T> {
+    if self.active.is_none() {
+      return Err(Error::NoTransaction);
+    }
+    self.log.append::<T>(Record::Commit)?;
+    self.log.sync()?;
+    self.active = None;
+    self.log.clear()?;
+    Ok(())
+  }
+
+  /// Undo the changes of the active transaction
+  pub fn rollback(&mut self) -> Result<(), T> {
+    let changes = self.active.take().ok_or(Error::NoTransaction)?;
+    self.undo(&changes)?;
+    self.log.append::<T>(Record::Rollback)?;
+    self.log.sync()?;
+    self.log.clear()?;
+    Ok(())
+  }
+
+  /// Run `f` in a transaction
+  ///
+  /// Commits if `f` succeeds and rolls back if it returns an error.
+  pub fn transaction<R, F>(&mut self, f: F) -> Result<R, T>
+  where
+    F: FnOnce(&mut Self) -> Result<R, T>,
+  {
+    self.begin()?;
+    match f(self) {
+      Ok(value) => {
+        self.commit()?;
+        Ok(value)
+      }
+      Err(err) => {
+        self.rollback()?;
+        Err(err)
+      }
+    }
+  }
+
+  /// Revert changes in reverse order
+  fn undo(&mut self, changes: &[(Link<T>, Link<T>)]) -> Result<(), T> {
+    revert(&mut self.store, changes)
+  }
+
+  /// Apply a write, logging its changes ahead if a transaction is active
+  ///
+  /// The change `expect` predicts is appended and synced before `op`
+  /// touches the store, changes that turn out different or could not be
+  /// predicted are appended after it. Undo only applies to links still in
+  /// their `after` state, so a logged change that never happened is
+  /// skipped on recovery.
+  fn write<H, E, F>(
+    &mut self,
+    handler: &mut H,
+    expect: E,
+    op: F,
+  ) -> Result<Flow, T>
+  where
+    H: WriteHandler<T>,
+    E: FnOnce(&S) -> Option<(Link<T>, Link<T>)>,
+    F: FnOnce(
+      &mut S,
+      &mut dyn FnMut(Link<T>, Link<T>) -> Flow,
+    ) -> Result<Flow, T>,
+  {
+    let Some(active) = self.active.as_mut() else {
+      return op(&mut self.store, &mut |before, after| {
+        handler.handle(before, after)
+      });
+    };
+
+    let mut expected = expect(&self.store);
+    if let Some((before, after)) = expected {
+      self.log.append(Record::Change(before, after))?;
+      self.log.sync()?;
+    }
+
+    let start = active.len();
+    let result = op(&mut self.store, &mut |before, after| {
+      active.push((before, after));
+      handler.handle(before, after)
+    });
+
+    let mut unexpected = false;
+    for &change in &active[start..] {
+      if expected.take_if(|&mut expected| expected == change).is_none() {
+        self.log.append(Record::Change(change.0, change.1))?;
+        unexpected = true;
+      }
+    }
+    if unexpected {
+      self.log.sync()?;
+    }
+    result
+  }
+}
+
+impl<T: Index, S: Links<T>> Links<T> for Transactional<T, S> {
+  fn count<const N: usize>(&self, query: [T; N]) -> T {
+    self.store.count(query)
+  }
+
+  fn create<const N: usize, H: WriteHandler<T>>(
+    &mut self,
+    query: [T; N],
+    handler: &mut H,
+  ) -> Result<Flow, T> {
+    let (source, target) = match N {
+      0 => (T::ZERO, T::ZERO),
+      1 => (query[0], query[0]),
+      _ => (query[0], query[1]),
+    };
+    let expect = |store: &S| {
+      let index = store.next_index()?;
+      Some((Link::nothing(), Link::new(index, source, target)))
+    };
+    self.write(handler, expect, |store, mut handler| {
+      store.create(query, &mut handler)
+    })
+  }
+
+  fn each<const N: usize, H: ReadHandler<T>>(
+    &self,
+    query: [T; N],
+    handler: &mut H,
+  ) -> Flow {
+    self.store.each(query, handler)
+  }
+
+  fn update<const N1: usize, const N2: usize, H: WriteHandler<T>>(
+    &mut self,
+    query: [T; N1],
+    change: [T; N2],
+    handler: &mut H,
+  ) -> Result<Flow, T> {
+    let expect = |store: &S| {
+      let before = store.get(*query.first()?)?;
+      let source = change.get(1).copied().unwrap_or(before.source);
+      let target = change.get(2).copied().unwrap_or(before.target);
+      Some((before, Link::new(before.index, source, target)))
+    };
+    self.write(handler, expect, |store, mut handler| {
+      store.update(query, change, &mut handler)
+    })
+  }
+
+  fn delete<const N: usize, H: WriteHandler<T>>(
+    &mut self,
+    query: [T; N],
+    handler: &mut H,
+  ) -> Result<Flow, T> {
+    let expect = |store: &S| {
+      let before = store.get(*query.first()?)?;
+      Some((before, Link::nothing()))
+    };
+    self.write(handler, expect, |store, mut handler| {
+      store.delete(query, &mut handler)
+    })
+  }
+
+  fn get(&self, index: T) -> Option<Link<T>> {
+    self.store.get(index)
+  }
+
+  fn next_index(&self) -> Option<T> {
+    self.store.next_index()
+  }
+}
@@ -0,0 +1,114 @@
+use {crate::Index, core::fmt};
+
+/// One of the two indexes a store keeps over its links
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+  /// Links ordered by `(source, target, index)`
+  Source,
+  /// Links ordered by `(target, source, index)`
+  Target,
+}
+
+/// Inconsistency found by [`Store::verify`](crate::Store::verify)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem<T> {
+  /// A tree node keeps a size other than the number of nodes below it
+  WrongSize { side: Side, node: T, stored: usize, actual: usize },
+  /// A link is indexed under a key out of order with its neighbours
+  Misplaced { side: Side, link: T },
+  /// A live link cannot be reached in the index
+  Missing { side: Side, link: T },
+  /// A link is reached more than once, through a cycle or shared subtree
+  Repeated { side: Side, link: T },
+  /// The index holds a slot that is free or was never allocated
+  Stale { side: Side, link: T },
+  /// The free list reaches a live link or a slot past the allocated ones
+  FreeListBroken(T),
+  /// The free list reaches a slot it already passed
+  FreeListCycle(T),
+  /// A slot is marked free but cannot be reached from the free list
+  FreeSlotLost(T),
+  /// The header counts a different number of free slots than are listed
+  FreeCount { stored: usize, actual: usize },
+  /// A live link uses an index that holds no link
+  Dangling { link: T, reference: T },
+}
+
+impl fmt::Display for Side {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Side::Source => "source",
+      Side::Target => "target",
+    })
+  }
+}
+
+impl<T: Index> fmt::Display for Problem<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match *self {
+      Problem::WrongSize { side, node, stored, actual } => write!(
+        f,
+        "{side} tree node {node:?} keeps size {stored}, its subtree has \
+         {actual}"
+      ),
+      Problem::Misplaced { side, link } => {
+        write!(f, "link {link:?} is out of order in the {side} index")
+      }
+      Problem::Missing { side, link } => {
+        write!(f, "link {link:?} is missing from the {side} index")
+      }
+      Problem::Repeated { side, link } => {
+        write!(f, "link {link:?} is reached twice in the {side} index")
+      }
+      Problem::Stale { side, link } => {
+        write!(f, "{side} index holds {link:?}, which is not a link")
+      }
+      Problem::FreeListBroken(slot) => {
+        write!(f, "free list reaches {slot:?}, which is not a free slot")
+      }
+      Problem::FreeListCycle(slot) => {
+        write!(f, "free list reaches {slot:?} twice")
+      }
+      Problem::FreeSlotLost(slot) => {
+        write!(f, "free slot {slot:?} is not in the free list")
+      }
+      Problem::FreeCount { stored, actual } => {
+        write!(
+          f,
+          "header counts {stored} free slots, the free list has {actual}"
+        )
+      }
+      Problem::Dangling { link, reference } => {
+        write!(f, "link {link:?} uses {reference:?}, which is not a link")
+      }
+    }
+  }
+}
+
+/// Findings of [`Store::verify`](crate::Store::verify)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report<T> {
+  /// Live links found by scanning every allocated slot
+  pub links: usize,
+  /// Every inconsistency, in the order it was found
+  pub problems: Vec<Problem<T>>,
+}
+
+impl<T> Report<T> {
+  /// Check if nothing is wrong
+  pub fn is_ok(&self) -> bool {
+    self.problems.is_empty()
+  }
+
+  /// Check if [`Store::rebuild_indexes`](crate::Store::rebuild_indexes)
+  /// would fix every problem
+  ///
+  /// Dangling references are part of the links themselves, anything else
+  /// is rebuilt from the link records.
+  pub fn is_repairable(&self) -> bool {
+    self
+      .problems
+      .iter()
+      .all(|problem| !matches!(problem, Problem::Dangling { .. }))
+  }
+}
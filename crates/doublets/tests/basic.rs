@@ -1,4 +1,6 @@
-use doublets::{Doublets, Flow, Link, Links, Result, create_heap_store};
+use doublets::{
+  Doublets, Flow, Integrity, Link, Links, Result, Uniqueness, create_heap_store,
+};
 
 #[test]
 fn test_create_point() -> Result<(), usize> {
@@ -176,3 +178,158 @@ fn test_get_or_create() -> Result<(), usize> {
   assert_eq!(store.count_all(), 3);
   Ok(())
 }
+
+#[test]
+fn test_delete_with_usages() -> Result<(), usize> {
+  let mut store = create_heap_store::<usize>()?;
+
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+
+  assert_eq!(store.delete_link(a), Err(doublets::Error::HasUsages(a)));
+  assert_eq!(store.delete_link(b), Err(doublets::Error::HasUsages(b)));
+  assert_eq!(store.get(a), Some(Link::point(a)));
+
+  store.delete_link(c)?;
+  store.delete_link(a)?;
+  assert!(store.get(a).is_none());
+  Ok(())
+}
+
+#[test]
+fn test_unchecked_delete() -> Result<(), usize> {
+  let mut store =
+    create_heap_store::<usize>()?.with_integrity(Integrity::Unchecked);
+
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+
+  store.delete_link(a)?;
+  assert!(store.get(a).is_none());
+  assert_eq!(store.get(c), Some(Link::new(c, a, b)));
+  Ok(())
+}
+
+#[test]
+fn test_delete_cascade() -> Result<(), usize> {
+  let mut store = create_heap_store::<usize>()?;
+
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+  let d = store.create_link(c, b)?;
+  let e = store.create_link(b, d)?;
+
+  let mut deleted = Vec::new();
+  store.delete_cascade(a, &mut |before: Link<usize>, _after| {
+    deleted.push(before.index);
+    Flow::Continue
+  })?;
+
+  assert_eq!(deleted, vec![e, d, c, a]);
+  assert_eq!(store.collect_all(), vec![Link::point(b)]);
+  Ok(())
+}
+
+#[test]
+fn test_delete_cascade_cycle() -> Result<(), usize> {
+  let mut store = create_heap_store::<usize>()?;
+
+  let a = store.create_point()?;
+  let b = store.create_link(a, a)?;
+  let c = store.create_link(b, b)?;
+  store.update_link(b, c, a)?;
+
+  let mut changes = Vec::new();
+  store.delete_cascade(a, &mut |before, after| {
+    changes.push((before, after));
+    Flow::Continue
+  })?;
+
+  // `b` stops using `c` by pointing at itself before `c` is deleted
+  assert_eq!(
+    changes,
+    [
+      (Link::new(b, c, a), Link::new(b, b, a)),
+      (Link::new(c, b, b), Link::nothing()),
+      (Link::new(b, b, a), Link::nothing()),
+      (Link::point(a), Link::nothing()),
+    ]
+  );
+  assert_eq!(store.count_all(), 0);
+  Ok(())
+}
+
+#[test]
+fn test_delete_cascade_break() -> Result<(), usize> {
+  let mut store = create_heap_store::<usize>()?;
+
+  let a = store.create_point()?;
+  let b = store.create_link(a, a)?;
+  let c = store.create_link(b, b)?;
+  store.update_link(b, c, a)?;
+
+  // Stopping at the rewrite keeps it, with nothing deleted yet
+  let flow = store.delete_cascade(a, &mut |_before, _after| Flow::Break)?;
+  assert_eq!(flow, Flow::Break);
+  assert_eq!(
+    store.collect_all(),
+    [Link::point(a), Link::new(b, b, a), Link::new(c, b, b)]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_duplicates_are_indexed() -> Result<(), usize> {
+  let mut store = create_heap_store::<usize>()?;
+
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+  let d = store.create_link(a, b)?;
+  let e = store.create_link(a, b)?;
+
+  let mut found = Vec::new();
+  store.each([0, a, b], &mut |link: Link<usize>| {
+    found.push(link.index);
+    Flow::Continue
+  });
+  found.sort();
+
+  assert_eq!(found, vec![c, d, e]);
+  assert_eq!(store.count([0, a, b]), 3);
+  assert_eq!(store.count([0, 0, b]), 4);
+
+  store.delete_link(d)?;
+  assert_eq!(store.count([0, a, b]), 2);
+  assert_eq!(store.count([0, a, 0]), 3);
+  Ok(())
+}
+
+#[test]
+fn test_unique_rejects_duplicates() -> Result<(), usize> {
+  let mut store =
+    create_heap_store::<usize>()?.with_uniqueness(Uniqueness::Unique);
+
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+  let d = store.create_link(b, a)?;
+
+  assert_eq!(
+    store.create_link(a, b),
+    Err(doublets::Error::AlreadyExists(c, a, b))
+  );
+  assert_eq!(
+    store.update_link(d, a, b),
+    Err(doublets::Error::AlreadyExists(c, a, b))
+  );
+  assert_eq!(store.get(d), Some(Link::new(d, b, a)));
+  assert_eq!(store.count_all(), 4);
+
+  // Updating a link to its own pair is not a duplicate
+  store.update_link(c, a, b)?;
+  Ok(())
+}
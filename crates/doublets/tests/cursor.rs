@@ -0,0 +1,73 @@
+// Tests for walking a store in pages with resumable cursors
+
+use doublets::{Cursor, Doublets, Error, Link, create_heap_store};
+
+#[test]
+fn test_walk_all_in_pages() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  for _ in 0..1000 {
+    store.create_point()?;
+  }
+
+  let mut cursor = Cursor::all();
+  let mut pages = Vec::new();
+  loop {
+    let page: Vec<_> = store.resume(&mut cursor).take(128).collect();
+    if page.is_empty() {
+      break;
+    }
+    pages.push(page);
+  }
+
+  assert_eq!(pages.len(), 8);
+  assert_eq!(pages.concat(), store.collect_all());
+  Ok(())
+}
+
+#[test]
+fn test_all_skips_deleted() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let points: Vec<_> =
+    (0..10).map(|_| store.create_point()).collect::<Result<_, _>>()?;
+
+  let mut cursor = Cursor::all();
+  let first: Vec<_> = store.resume(&mut cursor).take(3).collect();
+  assert_eq!(first.last().map(|link| link.index), Some(points[2]));
+
+  store.delete_link(points[2])?;
+  store.delete_link(points[3])?;
+  let rest: Vec<_> = store.resume(&mut cursor).map(|link| link.index).collect();
+  assert_eq!(rest, points[4..]);
+  Ok(())
+}
+
+#[test]
+fn test_token_roundtrip() -> Result<(), Error<usize>> {
+  let fresh = Cursor::by_target(7usize);
+  assert_eq!(Cursor::from_token(&fresh.token())?, fresh);
+
+  let mut store = create_heap_store::<usize>()?;
+  let a = store.create_point()?;
+  let b = store.create_link(a, a)?;
+  let mut cursor = Cursor::by_source(a);
+  assert_eq!(store.resume(&mut cursor).nth(1), Some(Link::new(b, a, a)));
+
+  let token = cursor.token();
+  assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+  assert_eq!(Cursor::from_token(&token)?, cursor);
+  Ok(())
+}
+
+#[test]
+fn test_reject_malformed_token() {
+  let token = Cursor::<usize>::all().token();
+  let invalid = [
+    String::new(),
+    token[2..].to_string(),
+    format!("09{}", &token[2..]),
+    format!("zz{}", &token[2..]),
+  ];
+  for token in invalid {
+    assert_eq!(Cursor::<usize>::from_token(&token), Err(Error::InvalidToken));
+  }
+}
@@ -0,0 +1,234 @@
+// Tests for the textual query language
+//
+// Statements parse into the same patterns the query engine runs, and
+// malformed input must point at the column where parsing went wrong.
+
+use doublets::{
+  Doublets, Error, Integrity, Link, Links, Outcome, ParseError, Pattern,
+  Statement, Store, Term, Uniqueness, create_heap_store,
+};
+
+fn parse(text: &str) -> Statement<usize> {
+  text.parse().unwrap_or_else(|err| panic!("{text:?}: {err}"))
+}
+
+fn run(
+  store: &mut Store<usize>,
+  text: &str,
+) -> Result<Outcome<usize>, Error<usize>> {
+  parse(text).execute(store)
+}
+
+fn error(text: &str) -> (usize, String) {
+  let err = text.parse::<Statement<usize>>().unwrap_err();
+  (err.column, err.message)
+}
+
+/// Points 1 to 3 with `1 -> 2` at 4, `2 -> 3` at 5 and `1 -> 3` at 6
+fn store() -> Result<Store<usize>, Error<usize>> {
+  let mut store = create_heap_store()?;
+  for _ in 0..3 {
+    store.create_point()?;
+  }
+  run(&mut store, "create 1 -> 2, 2 -> 3, 1 -> 3")?;
+  Ok(store)
+}
+
+#[test]
+fn test_parse() {
+  assert_eq!(
+    parse("match ?x -> ?y, ?e: ?y -> 3"),
+    Statement::Match(vec![
+      Pattern::link(Term::var("x"), Term::var("y")),
+      Pattern::new(Term::var("e"), Term::var("y"), Term::Const(3)),
+    ])
+  );
+  assert_eq!(
+    parse("  update * -> 2 =>   ?x ->  * where ?x -> ?x "),
+    Statement::Update {
+      pattern: Pattern::link(Term::Any, Term::Const(2)),
+      source: Term::var("x"),
+      target: Term::Any,
+      filter: vec![Pattern::link(Term::var("x"), Term::var("x"))],
+    }
+  );
+  assert_eq!(parse("create 1 -> 2"), Statement::Create(vec![(1, 2)]));
+  assert_eq!(
+    parse("delete 4: * -> *"),
+    Statement::Delete {
+      pattern: Pattern::new(Term::Const(4), Term::Any, Term::Any),
+      filter: vec![],
+    }
+  );
+}
+
+#[test]
+fn test_parse_errors() {
+  assert_eq!(
+    error(""),
+    (1, "expected a statement, found end of statement".into())
+  );
+  assert_eq!(
+    error("find ?x -> ?y"),
+    (
+      1,
+      "expected `match`, `count`, `create`, `update` or `delete`, found `find`"
+        .into()
+    )
+  );
+  assert_eq!(error("match ?x ?y"), (10, "expected `->`, found `?y`".into()));
+  assert_eq!(
+    error("match ?x ->"),
+    (12, "expected a number, `?name` or `*`, found end of statement".into())
+  );
+  assert_eq!(
+    error("match ? -> 1"),
+    (7, "expected a variable name after `?`".into())
+  );
+  assert_eq!(
+    error("count 1 -> 2 3"),
+    (14, "expected end of statement, found `3`".into())
+  );
+  assert_eq!(
+    error("create ?x -> 2"),
+    (8, "expected a link number, found `?x`".into())
+  );
+  assert_eq!(
+    error("match 0 -> 1"),
+    (7, "`0` is not a valid link number".into())
+  );
+  assert_eq!(
+    error("match 1 -> 2 & 3"),
+    (14, "unexpected character `&`".into())
+  );
+  assert_eq!(
+    error("update ?l: 1 -> * => ?z -> 1"),
+    (22, "variable `?z` is not bound by a pattern".into())
+  );
+  // Columns count characters, not bytes
+  assert_eq!(error("match ?ключ ?y"), (13, "expected `->`, found `?y`".into()));
+  assert_eq!(
+    error("match ?ключ ->"),
+    (15, "expected a number, `?name` or `*`, found end of statement".into())
+  );
+  assert_eq!(
+    "match 1 -> 300".parse::<Statement<u8>>(),
+    Err(ParseError {
+      column: 12,
+      message: "`300` is not a valid link number".into()
+    })
+  );
+}
+
+#[test]
+fn test_match_and_count() -> Result<(), Error<usize>> {
+  let mut store = store()?;
+
+  let outcome = run(&mut store, "match ?e: 1 -> ?to, ?to -> 3")?;
+  assert_eq!(
+    outcome,
+    Outcome::Matches {
+      variables: vec!["e".into(), "to".into()],
+      rows: vec![vec![1, 1], vec![4, 2], vec![6, 3]],
+    }
+  );
+  assert_eq!(
+    outcome.to_string(),
+    "?e = 1, ?to = 1\n?e = 4, ?to = 2\n?e = 6, ?to = 3\n"
+  );
+
+  assert_eq!(run(&mut store, "count * -> 3")?, Outcome::Count(3));
+  assert_eq!(run(&mut store, "count ?x -> ?x")?, Outcome::Count(3));
+  assert_eq!(run(&mut store, "count 9 -> *")?, Outcome::Count(0));
+  Ok(())
+}
+
+#[test]
+fn test_create() -> Result<(), Error<usize>> {
+  let mut store = store()?;
+  let outcome = run(&mut store, "create 3 -> 1, 4 -> 5")?;
+
+  assert_eq!(
+    outcome,
+    Outcome::Created(vec![Link::new(7, 3, 1), Link::new(8, 4, 5)])
+  );
+  assert_eq!(outcome.to_string(), "(7: 3 1)\n(8: 4 5)\n");
+  assert_eq!(store.count_all(), 8);
+  Ok(())
+}
+
+#[test]
+fn test_update() -> Result<(), Error<usize>> {
+  let mut store = store()?;
+
+  // Reverse the edges into 3 from links that also point at 1 and 2
+  let outcome = run(
+    &mut store,
+    "update ?e: ?from -> 3 => 3 -> ?from where ?from -> 2, ?from -> 1",
+  )?;
+  assert_eq!(outcome, Outcome::Updated(vec![Link::new(6, 3, 1)]));
+
+  let outcome = run(&mut store, "update 5: * -> * => 1 -> *")?;
+  assert_eq!(outcome, Outcome::Updated(vec![Link::new(5, 1, 3)]));
+  assert_eq!(store.get(5), Some(Link::new(5, 1, 3)));
+
+  // No match, no change
+  assert_eq!(
+    run(&mut store, "update 9 -> * => 1 -> 1")?,
+    Outcome::Updated(vec![])
+  );
+  Ok(())
+}
+
+#[test]
+fn test_delete() -> Result<(), Error<usize>> {
+  let mut store = store()?;
+
+  // Joins may match a link several times, it is deleted once
+  let outcome = run(&mut store, "delete 1 -> ?x where ?y -> ?x, 2 -> ?x")?;
+  let Outcome::Deleted(deleted) = outcome else { panic!() };
+  assert_eq!(deleted, [Link::new(4, 1, 2), Link::new(6, 1, 3)]);
+  assert_eq!(store.count_all(), 4);
+  Ok(())
+}
+
+#[test]
+fn test_write_errors() -> Result<(), Error<usize>> {
+  let mut store = store()?.with_integrity(Integrity::Strict);
+  assert_eq!(run(&mut store, "delete 2: * -> *"), Err(Error::HasUsages(2)));
+
+  // The parser rejects unbound variables, hand-built statements fail
+  let unbound = Statement::Update {
+    pattern: Pattern::new(Term::var("l"), Term::Const(1), Term::Any),
+    source: Term::var("z"),
+    target: Term::Any,
+    filter: vec![],
+  };
+  let links = store.collect_all();
+  assert_eq!(unbound.execute(&mut store), Err(Error::InvalidQuery));
+  assert_eq!(store.collect_all(), links);
+  Ok(())
+}
+
+#[test]
+fn test_failed_write_changes_nothing() -> Result<(), Error<usize>> {
+  // 6 can go, but 7 still uses 5
+  let mut strict = store()?.with_integrity(Integrity::Strict);
+  run(&mut strict, "create 5 -> 5")?;
+  let links = strict.collect_all();
+  assert_eq!(run(&mut strict, "delete * -> 3"), Err(Error::HasUsages(5)));
+  assert_eq!(strict.collect_all(), links);
+
+  // 4 becomes 3 -> 2, but 6 would duplicate the point 3
+  let mut store = store()?.with_uniqueness(Uniqueness::Unique);
+  let links = store.collect_all();
+  let result = run(&mut store, "update 1 -> ?x => 3 -> ?x");
+  assert!(matches!(result, Err(Error::AlreadyExists(..))));
+  assert_eq!(store.collect_all(), links);
+
+  // The second link is a duplicate of the first
+  let result = run(&mut store, "create 2 -> 1, 2 -> 1");
+  assert!(matches!(result, Err(Error::AlreadyExists(..))));
+  assert_eq!(store.collect_all(), links);
+  Ok(())
+}
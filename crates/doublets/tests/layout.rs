@@ -0,0 +1,73 @@
+// Tests for the raw link record, which is sized to the index type
+
+use {
+  doublets::{Doublets, Error, Link, Links, RawLink, Store, create_heap_store},
+  mem::FileMapped,
+};
+
+#[test]
+fn test_record_size_per_index_width() {
+  // Source, target and two tree nodes of size, left and right
+  assert_eq!(size_of::<RawLink<u8>>(), 8);
+  assert_eq!(size_of::<RawLink<u16>>(), 16);
+  assert_eq!(size_of::<RawLink<u32>>(), 32);
+  assert_eq!(size_of::<RawLink<u64>>(), 64);
+  assert_eq!(size_of::<RawLink<usize>>(), size_of::<[usize; 8]>());
+  assert_eq!(size_of::<RawLink<u128>>(), 128);
+
+  assert_eq!(align_of::<RawLink<u32>>(), align_of::<u32>());
+}
+
+#[test]
+fn test_narrow_store_operations() -> Result<(), Error<u32>> {
+  let mut store = create_heap_store::<u32>()?;
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  // Grow past the initial capacity
+  let links: Vec<_> = (0..3000)
+    .map(|i| store.create_link(a, if i % 2 == 0 { a } else { b }))
+    .collect::<Result<_, _>>()?;
+
+  assert_eq!(store.count([0, a, b]), 1500);
+  assert_eq!(store.search(a, b), Some(links[1]));
+
+  for &link in links.iter().step_by(3) {
+    store.delete_link(link)?;
+  }
+  assert_eq!(store.count_all(), 2002);
+  assert_eq!(store.count([0, a, 0]), 2001);
+
+  // Freed slots are reused
+  let c = store.create_link(b, a)?;
+  assert_eq!(c, links[2997]);
+  assert_eq!(store.get(c), Some(Link::new(c, b, a)));
+  Ok(())
+}
+
+#[test]
+fn test_reopen_narrow_store() -> Result<(), Error<u32>> {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+  let open = || {
+    let mem = FileMapped::<RawLink<u32>>::from_path(&path).unwrap();
+    Store::<u32, _>::open(mem)
+  };
+
+  let snapshot = {
+    let mut store = open()?;
+    let a = store.create_point()?;
+    let b = store.create_point()?;
+    store.create_link(a, b)?;
+    let c = store.create_link(b, b)?;
+    store.delete_link(c)?;
+    store.collect_all()
+  };
+
+  let store = open()?;
+  assert_eq!(store.collect_all(), snapshot);
+  assert_eq!(store.count([0, 0, 2]), 2);
+  // File holds 32 bytes per link
+  let len = std::fs::metadata(&path).unwrap().len();
+  assert_eq!(len % 32, 0);
+  Ok(())
+}
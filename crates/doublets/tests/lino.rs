@@ -0,0 +1,127 @@
+// Tests for Links Notation import and export
+//
+// Fixtures written as `(index: source target)` must load back into the
+// same links, keeping indices where they are free and remapping them,
+// references included, where they collide with existing links.
+
+use doublets::{Doublets, Error, Link, Links, create_heap_store};
+
+const FIXTURE: &str = "
+(1: 1 1)
+(2: 2 2)
+(3: 1 2)
+(4: 3 2)
+";
+
+#[test]
+fn test_dump() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+  store.create_link(c, b)?;
+
+  assert_eq!(store.to_lino(), FIXTURE.trim_start());
+  Ok(())
+}
+
+#[test]
+fn test_round_trip() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let mapping = store.load_lino(FIXTURE)?;
+
+  assert!(mapping.iter().all(|(from, to)| from == to));
+  assert_eq!(store.to_lino(), FIXTURE.trim_start());
+  assert_eq!(store.search(3, 2), Some(4));
+  Ok(())
+}
+
+#[test]
+fn test_keeps_gaps() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  store.load_lino("(2: 2 2) (5: 2 5)")?;
+
+  assert_eq!(store.collect_all(), [Link::point(2), Link::new(5, 2, 5)]);
+  assert_eq!(store.get(1), None);
+  Ok(())
+}
+
+#[test]
+fn test_remaps_collisions() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  store.load_lino(FIXTURE)?;
+  let mapping = store.load_lino(FIXTURE)?;
+
+  let moved: Vec<_> = mapping.into_iter().collect();
+  assert_eq!(moved, [(1, 5), (2, 6), (3, 7), (4, 8)]);
+  assert_eq!(store.get(7), Some(Link::new(7, 5, 6)));
+  assert_eq!(store.get(8), Some(Link::new(8, 7, 6)));
+  // The original links are untouched
+  assert_eq!(store.get(4), Some(Link::new(4, 3, 2)));
+  Ok(())
+}
+
+#[test]
+fn test_remaps_far_indices() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let mapping = store.load_lino("(99999999999: 3 99999999999) (3: 3 3)")?;
+
+  let far = mapping[&99_999_999_999];
+  assert!(far < 100, "{far} is past the placeholder budget");
+  assert_eq!(mapping[&3], 3);
+  assert_eq!(store.get(far), Some(Link::new(far, 3, far)));
+  assert_eq!(store.count_all(), 2);
+  Ok(())
+}
+
+#[test]
+fn test_anonymous_and_existing_references() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let existing = store.create_point()?;
+  let mapping = store.load_lino(
+    "(7 1)
+     (1: 1 7)
+     (7: 7 7)
+     (1 42)",
+  )?;
+
+  assert_eq!(mapping[&7], 7);
+  assert_eq!(mapping[&1], 2);
+  // References to named links follow them, wherever they are written
+  assert_eq!(store.get(8), Some(Link::new(8, 7, 2)));
+  // Indices the text does not name are kept as they are
+  assert_eq!(store.get(9), Some(Link::new(9, 2, 42)));
+  assert_eq!(store.get(2), Some(Link::new(2, 2, 7)));
+  assert_eq!(store.get(existing), Some(Link::point(existing)));
+  Ok(())
+}
+
+#[test]
+fn test_malformed() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let cases = [
+    ("(1: 1)", 1),
+    ("(1: 1 1)\n(2 2 2)", 2),
+    ("(1: 1 1\n", 1),
+    ("\n\n1: 1 1)", 3),
+    ("(0: 1 1)", 1),
+    ("(1: a 1)", 1),
+    ("(1: 1 1)\n(1: 2 2)", 2),
+    ("(1:: 1 1)", 1),
+  ];
+  for (text, line) in cases {
+    assert_eq!(
+      store.load_lino(text),
+      Err(Error::InvalidNotation(line)),
+      "{text:?}"
+    );
+  }
+  assert_eq!(store.count_all(), 0);
+  Ok(())
+}
+
+#[test]
+fn test_index_too_wide() {
+  let mut store = create_heap_store::<u8>().unwrap();
+  assert_eq!(store.load_lino("(1: 1 300)"), Err(Error::Overflow));
+}
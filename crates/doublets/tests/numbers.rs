@@ -0,0 +1,108 @@
+// Tests for integers stored as links
+//
+// Numbers are chains of binary digits, so every value must round-trip
+// through any integer type that holds it, be stored once, and share the
+// links of its high bits with other numbers.
+
+use doublets::{
+  Doublets, Error, Links, Numbers, Sequences, Uniqueness, create_heap_store,
+};
+
+#[test]
+fn test_round_trip() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  for value in [0, 1, 2, 3, 42, 255, 1 << 40, u64::MAX] {
+    let link = store.to_link(value)?;
+    assert_eq!(store.from_link::<u64>(link)?, value);
+  }
+  for value in [-1, -2, -42, i64::MIN, i64::MAX] {
+    let link = store.to_link(value)?;
+    assert_eq!(store.from_link::<i64>(link)?, value);
+  }
+  Ok(())
+}
+
+#[test]
+fn test_same_value_same_link() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let link = store.to_link(7u8)?;
+  let count = store.count_all();
+
+  assert_eq!(store.to_link(7u64)?, link);
+  assert_eq!(store.to_link(7i16)?, link);
+  assert_eq!(store.to_link(7usize)?, link);
+  assert_eq!(store.count_all(), count);
+
+  assert_ne!(store.to_link(-7i32)?, link);
+  assert_eq!(store.to_link(0u32)?, store.to_link(-0i32)?);
+  Ok(())
+}
+
+#[test]
+fn test_shares_high_bits() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let twelve = store.to_link(0b1100u64)?;
+  let count = store.count_all();
+
+  // 0b1101 only differs in its last bit
+  let thirteen = store.to_link(0b1101u64)?;
+  assert_eq!(store.count_all(), count + 1);
+  assert_eq!(
+    store.get(thirteen).unwrap().source,
+    store.get(twelve).unwrap().source
+  );
+
+  // 0b110 is a prefix of both
+  store.to_link(0b110u64)?;
+  assert_eq!(store.count_all(), count + 1);
+  Ok(())
+}
+
+#[test]
+fn test_does_not_fit() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let big = store.to_link(300u32)?;
+  let negative = store.to_link(-1i8)?;
+
+  assert_eq!(store.from_link::<u8>(big), Err(Error::Overflow));
+  assert_eq!(store.from_link::<i16>(big), Ok(300));
+  assert_eq!(store.from_link::<u64>(negative), Err(Error::Overflow));
+  assert_eq!(store.from_link::<i64>(negative), Ok(-1));
+  Ok(())
+}
+
+#[test]
+fn test_not_a_number() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let text = store.create_string("7")?;
+  let char = store.create_char('7')?;
+  let point = store.create_point()?;
+
+  assert_eq!(store.from_link::<u64>(text), Err(Error::InvalidNumber(text)));
+  assert_eq!(store.from_link::<u64>(char), Err(Error::InvalidNumber(char)));
+  assert_eq!(store.from_link::<u64>(point), Err(Error::InvalidNumber(point)));
+  assert_eq!(store.from_link::<u64>(999), Err(Error::NotExists(999)));
+  // Chains `to_link` never writes: leading zeros and a negative zero
+  let [zero, one, number, negative] = [1, 2, 5, 6];
+  let padded = store.create_link(number, zero)?;
+  let padded = store.create_link(padded, one)?;
+  assert_eq!(store.from_link::<u64>(padded), Err(Error::InvalidNumber(padded)));
+  let minus_zero = store.create_link(negative, zero)?;
+  let invalid = Error::InvalidNumber(minus_zero);
+  assert_eq!(store.from_link::<u64>(minus_zero), Err(invalid.clone()));
+  assert_eq!(store.from_link::<i64>(minus_zero), Err(invalid));
+  // Nor is a number a character
+  let seven = store.to_link(0x37u64)?;
+  assert_eq!(store.read_char(seven), Err(Error::InvalidSequence(seven)));
+  Ok(())
+}
+
+#[test]
+fn test_unique_and_narrow_stores() {
+  let mut store =
+    create_heap_store::<u16>().unwrap().with_uniqueness(Uniqueness::Unique);
+  for value in [0i64, 1, 2, 3, -3, 1 << 20, -(1 << 20)] {
+    let link = store.to_link(value).unwrap();
+    assert_eq!(store.from_link::<i64>(link).unwrap(), value);
+  }
+}
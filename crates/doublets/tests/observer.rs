@@ -0,0 +1,233 @@
+// Tests for observing and vetoing the changes made to a store
+
+use {
+  doublets::{
+    Doublets, Error, Index, Link, Observer, Store, Transactional,
+    create_heap_store,
+  },
+  std::sync::{Arc, Mutex},
+};
+
+type Changes = Arc<Mutex<Vec<(Link<usize>, Link<usize>)>>>;
+
+/// Subscribe a recorder of every change matching `query`
+fn record<const N: usize>(
+  store: &mut Store<usize>,
+  query: [usize; N],
+) -> Changes {
+  let changes = Changes::default();
+  let feed = Arc::clone(&changes);
+  store.subscribe(query, move |before, after| {
+    feed.lock().unwrap().push((before, after));
+  });
+  changes
+}
+
+fn taken(changes: &Changes) -> Vec<(Link<usize>, Link<usize>)> {
+  std::mem::take(&mut *changes.lock().unwrap())
+}
+
+/// Rejects changes to links that use `forbidden` before or after
+struct Forbid {
+  forbidden: usize,
+  notified: Changes,
+}
+
+impl Observer<usize> for Forbid {
+  fn allow(&mut self, before: Link<usize>, after: Link<usize>) -> bool {
+    [before, after].iter().all(|link| {
+      link.source != self.forbidden && link.target != self.forbidden
+    })
+  }
+
+  fn notify(&mut self, before: Link<usize>, after: Link<usize>) {
+    self.notified.lock().unwrap().push((before, after));
+  }
+}
+
+/// Rejects every delete
+struct KeepAll;
+
+impl Observer<usize> for KeepAll {
+  fn allow(&mut self, _before: Link<usize>, after: Link<usize>) -> bool {
+    !after.is_null()
+  }
+
+  fn notify(&mut self, _before: Link<usize>, _after: Link<usize>) {}
+}
+
+#[test]
+fn test_feed() {
+  let mut store = create_heap_store::<usize>().unwrap();
+  let all = record(&mut store, []);
+
+  let a = store.create_point().unwrap();
+  let b = store.create_link(a, a).unwrap();
+  store.update_link(b, b, a).unwrap();
+  // Keeping the source and target changes nothing
+  store.update_link(b, b, a).unwrap();
+  store.delete_link(b).unwrap();
+
+  assert_eq!(
+    taken(&all),
+    [
+      (Link::nothing(), Link::new(a, 0, 0)),
+      (Link::new(a, 0, 0), Link::point(a)),
+      (Link::nothing(), Link::new(b, a, a)),
+      (Link::new(b, a, a), Link::new(b, b, a)),
+      (Link::new(b, b, a), Link::nothing()),
+    ]
+  );
+}
+
+#[test]
+fn test_filters() {
+  let mut store = create_heap_store::<usize>().unwrap();
+  let a = store.create_point().unwrap();
+  let b = store.create_point().unwrap();
+  let by_source = record(&mut store, [usize::ANY, a]);
+  let by_target = record(&mut store, [usize::ANY, usize::ANY, b]);
+  let by_index = record(&mut store, [b]);
+
+  let ab = store.create_link(a, b).unwrap();
+  let ba = store.create_link(b, a).unwrap();
+  // Moving a link away from a filter is seen through its `before` state
+  store.update_link(ab, ab, ab).unwrap();
+  store.delete_link(b).unwrap_err();
+  store.delete_link(ba).unwrap();
+  store.delete_link(b).unwrap();
+
+  assert_eq!(
+    taken(&by_source),
+    [
+      (Link::nothing(), Link::new(ab, a, b)),
+      (Link::new(ab, a, b), Link::point(ab)),
+    ]
+  );
+  assert_eq!(
+    taken(&by_target),
+    [
+      (Link::nothing(), Link::new(ab, a, b)),
+      (Link::new(ab, a, b), Link::point(ab)),
+      (Link::point(b), Link::nothing()),
+    ]
+  );
+  assert_eq!(taken(&by_index), [(Link::point(b), Link::nothing())]);
+}
+
+#[test]
+fn test_unsubscribe() {
+  let mut store = create_heap_store::<usize>().unwrap();
+  let changes = Changes::default();
+  let feed = Arc::clone(&changes);
+  let subscription = store.subscribe([], move |before, after| {
+    feed.lock().unwrap().push((before, after));
+  });
+  let other = store.subscribe([], |_: Link<usize>, _: Link<usize>| {});
+  assert_ne!(subscription, other);
+
+  let a = store.create_point().unwrap();
+  assert!(store.unsubscribe(subscription));
+  assert!(!store.unsubscribe(subscription));
+  store.delete_link(a).unwrap();
+
+  assert_eq!(taken(&changes).len(), 2);
+  assert!(store.unsubscribe(other));
+}
+
+#[test]
+fn test_veto() {
+  let mut store = create_heap_store::<usize>().unwrap();
+  let a = store.create_point().unwrap();
+  let b = store.create_point().unwrap();
+  let ab = store.create_link(a, b).unwrap();
+  let notified = Changes::default();
+  store.subscribe([], Forbid { forbidden: b, notified: notified.clone() });
+  let all = record(&mut store, []);
+  let snapshot = store.collect_all();
+
+  // The index a create would take is known before it is applied
+  assert_eq!(store.create_link(b, a), Err(Error::Vetoed(ab + 1)));
+  assert_eq!(store.update_link(a, a, b), Err(Error::Vetoed(a)));
+  assert_eq!(store.delete_link(ab), Err(Error::Vetoed(ab)));
+  assert_eq!(store.collect_all(), snapshot);
+  assert!(store.verify().is_ok());
+  assert!(taken(&all).is_empty());
+
+  // Vetoed creates do not use up an index
+  let aa = store.create_link(a, a).unwrap();
+  assert_eq!(aa, ab + 1);
+  store.update_link(aa, aa, a).unwrap();
+  store.delete_link(aa).unwrap();
+  assert_eq!(
+    taken(&notified),
+    [
+      (Link::nothing(), Link::new(aa, a, a)),
+      (Link::new(aa, a, a), Link::new(aa, aa, a)),
+      (Link::new(aa, aa, a), Link::nothing()),
+    ]
+  );
+  assert_eq!(taken(&all).len(), 3);
+}
+
+#[test]
+fn test_rollback_is_observed() {
+  let dir = tempfile::tempdir().unwrap();
+  let mut store = create_heap_store::<usize>().unwrap();
+  let a = store.create_point().unwrap();
+  let all = record(&mut store, []);
+  let mut store =
+    Transactional::open(store, dir.path().join("db.log")).unwrap();
+
+  let result = store.transaction(|store| {
+    store.create_link(a, a)?;
+    store.delete_link(a)
+  });
+  assert_eq!(result, Err(Error::HasUsages(a)));
+
+  let link = Link::new(a + 1, a, a);
+  assert_eq!(taken(&all), [(Link::nothing(), link), (link, Link::nothing()),]);
+}
+
+#[test]
+fn test_rollback_ignores_vetoes() {
+  let dir = tempfile::tempdir().unwrap();
+  let mut store = create_heap_store::<usize>().unwrap();
+  let a = store.create_point().unwrap();
+  store.subscribe([], KeepAll);
+  let snapshot = store.collect_all();
+  let mut store =
+    Transactional::open(store, dir.path().join("db.log")).unwrap();
+
+  // Undoing the creates deletes the links, which the observer rejects
+  let result = store.transaction(|store| {
+    let aa = store.create_link(a, a)?;
+    store.create_link(aa, a)?;
+    store.delete_link(a)
+  });
+  assert_eq!(result, Err(Error::HasUsages(a)));
+  assert_eq!(store.collect_all(), snapshot);
+
+  // Vetoes are back once the rollback is done
+  assert_eq!(store.delete_link(a), Err(Error::Vetoed(a)));
+}
+
+#[test]
+fn test_rollback_keeps_vetoes_disabled() {
+  let dir = tempfile::tempdir().unwrap();
+  let mut store = create_heap_store::<usize>().unwrap();
+  let a = store.create_point().unwrap();
+  store.subscribe([], KeepAll);
+  assert!(store.set_vetoes(false));
+  let mut store =
+    Transactional::open(store, dir.path().join("db.log")).unwrap();
+
+  let result = store.transaction(|store| {
+    store.create_link(a, a)?;
+    store.delete_link(a + 2)
+  });
+  assert_eq!(result, Err(Error::NotExists(a + 2)));
+
+  // The rollback leaves vetoes as it found them
+  assert_eq!(store.delete_link(a), Ok(a));
+}
@@ -0,0 +1,72 @@
+// Tests for stores running out of indices representable by their type
+
+use doublets::{
+  Doublets, Error, Index, Link, Links, RawLink, Store, create_heap_store,
+};
+
+fn fill<T: Index>(store: &mut Store<T>) -> Result<usize, Error<T>> {
+  let mut created = 0;
+  loop {
+    match store.create_point() {
+      Ok(_) => created += 1,
+      Err(Error::Overflow) => return Ok(created),
+      Err(err) => return Err(err),
+    }
+  }
+}
+
+#[test]
+fn test_try_from_usize() {
+  assert_eq!(u8::try_from_usize(255), Some(255));
+  assert_eq!(u8::try_from_usize(256), None);
+  assert_eq!(i8::try_from_usize(127), Some(127));
+  // Would wrap into the negative range
+  assert_eq!(i8::try_from_usize(128), None);
+  assert_eq!(i16::try_from_usize(40_000), None);
+  assert_eq!(u64::try_from_usize(usize::MAX), Some(usize::MAX as u64));
+}
+
+#[test]
+fn test_max_index_spans_type() {
+  assert_eq!(RawLink::<u8>::MAX_INDEX, 255);
+  assert_eq!(RawLink::<u16>::MAX_INDEX, 65_535);
+  assert_eq!(RawLink::<u32>::MAX_INDEX, 4_294_967_295);
+  assert_eq!(RawLink::<u64>::MAX_INDEX, usize::MAX);
+}
+
+#[test]
+fn test_u8_store_overflows() -> Result<(), Error<u8>> {
+  let mut store = create_heap_store::<u8>()?;
+
+  assert_eq!(fill(&mut store)?, RawLink::<u8>::MAX_INDEX);
+  assert_eq!(store.count_all() as usize, RawLink::<u8>::MAX_INDEX);
+  assert_eq!(store.create_link(1, 2), Err(Error::Overflow));
+
+  // Freed indices are still handed out
+  store.delete_link(5)?;
+  assert_eq!(store.create_link(1, 2)?, 5);
+  assert_eq!(store.get(5), Some(Link::new(5, 1, 2)));
+  assert_eq!(store.create_point(), Err(Error::Overflow));
+  Ok(())
+}
+
+#[test]
+fn test_signed_store_stays_non_negative() -> Result<(), Error<i8>> {
+  let mut store = create_heap_store::<i8>()?;
+
+  assert_eq!(fill(&mut store)?, i8::MAX as usize);
+  let links = store.collect_all();
+  assert!(links.iter().all(|link| link.index > 0));
+  assert_eq!(links.last().map(|link| link.index), Some(i8::MAX));
+  Ok(())
+}
+
+#[test]
+fn test_u16_store_overflows() -> Result<(), Error<u16>> {
+  let mut store = create_heap_store::<u16>()?;
+
+  assert_eq!(fill(&mut store)?, RawLink::<u16>::MAX_INDEX);
+  assert_eq!(store.search(100, 100), Some(100));
+  assert_eq!(store.create_point(), Err(Error::Overflow));
+  Ok(())
+}
@@ -0,0 +1,181 @@
+// Tests for reopening a file-backed doublets store
+//
+// The store keeps its header (counters, free list and tree roots) in the
+// reserved zero slot, so dropping a `FileMapped` store and opening the
+// same file again must restore every link and index.
+
+use {
+  doublets::{ArtStrategy, Doublets, Error, Link, Links, RawLink, Store},
+  mem::FileMapped,
+  std::path::Path,
+};
+
+type FileStore = Store<usize, FileMapped<RawLink>>;
+
+fn open(path: &Path) -> Result<FileStore, Error<usize>> {
+  let mem = FileMapped::from_path(path).expect("failed to map file");
+  Store::open(mem)
+}
+
+#[test]
+fn test_open_blank_file() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let store = open(&dir.path().join("db.links"))?;
+
+  assert_eq!(store.count_all(), 0);
+  Ok(())
+}
+
+#[test]
+fn test_reopen_restores_links() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+
+  let (a, b, c) = {
+    let mut store = open(&path)?;
+    let a = store.create_point()?;
+    let b = store.create_point()?;
+    let c = store.create_link(a, b)?;
+    (a, b, c)
+  };
+
+  let store = open(&path)?;
+  assert_eq!(store.count_all(), 3);
+  assert_eq!(store.get(c), Some(Link::new(c, a, b)));
+  assert_eq!(store.search(a, b), Some(c));
+  assert_eq!(store.count([0, a, 0]), 2);
+  assert_eq!(store.count([0, 0, b]), 2);
+  Ok(())
+}
+
+#[test]
+fn test_reopen_restores_free_list() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+
+  let freed = {
+    let mut store = open(&path)?;
+    store.create_point()?;
+    let freed = store.create_point()?;
+    store.create_point()?;
+    store.delete_link(freed)?;
+    freed
+  };
+
+  let mut store = open(&path)?;
+  assert_eq!(store.count_all(), 2);
+  assert!(store.get(freed).is_none());
+  assert_eq!(store.create_point()?, freed);
+  Ok(())
+}
+
+#[test]
+fn test_reopen_after_growth() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+
+  {
+    let mut store = open(&path)?;
+    for _ in 0..3000 {
+      store.create_point()?;
+    }
+  }
+
+  let mut store = open(&path)?;
+  assert_eq!(store.count_all(), 3000);
+  assert_eq!(store.get(2999), Some(Link::point(2999)));
+  assert_eq!(store.create_point()?, 3001);
+  Ok(())
+}
+
+#[test]
+fn test_reopen_rebuilds_radix_index() -> Result<(), Error<usize>> {
+  type ArtStore = Store<usize, FileMapped<RawLink>, ArtStrategy, ArtStrategy>;
+
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+  let open = || {
+    let mem = FileMapped::from_path(&path).expect("failed to map file");
+    ArtStore::open(mem)
+  };
+
+  let (a, b, c) = {
+    let mut store = open()?;
+    let a = store.create_point()?;
+    let b = store.create_point()?;
+    let c = store.create_link(a, b)?;
+    (a, b, c)
+  };
+
+  let mut store = open()?;
+  assert_eq!(store.search(a, b), Some(c));
+  store.delete_link(c)?;
+  assert_eq!(store.count([0, a, 0]), 1);
+  assert_eq!(store.count_all(), 2);
+  Ok(())
+}
+
+#[test]
+fn test_reject_incompatible_header() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+
+  std::fs::write(&path, [0xAB; 4096]).unwrap();
+
+  assert!(matches!(open(&path), Err(Error::InvalidHeader)));
+}
+
+#[test]
+fn test_reject_truncated_file() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+
+  let mut store = open(&path)?;
+  let a = store.create_point()?;
+  store.create_link(a, a)?;
+  drop(store);
+
+  let len = std::fs::metadata(&path).unwrap().len();
+  let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+  file.set_len(len / 2).unwrap();
+  drop(file);
+  assert!(matches!(open(&path), Err(Error::InvalidHeader)));
+  Ok(())
+}
+
+#[test]
+fn test_reject_header_past_allocated() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+
+  let mut store = open(&path)?;
+  let a = store.create_point()?;
+  store.create_link(a, a)?;
+  drop(store);
+
+  // Words 3, 5 and 6 of the header: allocated, free-list head, source root
+  let bytes = std::fs::read(&path).unwrap();
+  let word =
+    |idx: usize| idx * size_of::<usize>()..(idx + 1) * size_of::<usize>();
+  let allocated = usize::from_ne_bytes(bytes[word(3)].try_into().unwrap());
+  for idx in [5, 6] {
+    let mut bytes = bytes.clone();
+    bytes[word(idx)].copy_from_slice(&allocated.to_ne_bytes());
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(matches!(open(&path), Err(Error::InvalidHeader)), "word {idx}");
+  }
+  Ok(())
+}
+
+#[test]
+fn test_reject_other_index_width() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("db.links");
+
+  drop(open(&path)?);
+
+  let mem = FileMapped::from_path(&path).unwrap();
+  let store = Store::<u32, FileMapped<RawLink<u32>>>::open(mem);
+  assert!(matches!(store, Err(Error::InvalidHeader)));
+  Ok(())
+}
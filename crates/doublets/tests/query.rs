@@ -0,0 +1,209 @@
+// Tests for multi-pattern queries
+//
+// Matches must be exactly the variable assignments a brute-force search
+// over all links finds, whatever join order the planner picks.
+
+use doublets::{
+  Doublets, Error, Flow, Link, Pattern, Query, Store, Term, create_heap_store,
+};
+
+fn var(name: &str) -> Term<usize> {
+  Term::var(name)
+}
+
+fn bound(
+  query: &Query<usize>,
+  store: &Store<usize>,
+  names: &[&str],
+) -> Vec<Vec<usize>> {
+  let mut found: Vec<_> = query
+    .run(store)
+    .iter()
+    .map(|found| names.iter().map(|name| found.get(name).unwrap()).collect())
+    .collect();
+  found.sort();
+  found
+}
+
+/// Points 1..=n and links `from -> to` for every pair given
+fn graph(
+  n: usize,
+  edges: &[(usize, usize)],
+) -> Result<Store<usize>, Error<usize>> {
+  let mut store = create_heap_store()?;
+  for _ in 0..n {
+    store.create_point()?;
+  }
+  for &(from, to) in edges {
+    store.create_link(from, to)?;
+  }
+  Ok(store)
+}
+
+#[test]
+fn test_path_join() -> Result<(), Error<usize>> {
+  let store = graph(4, &[(1, 2), (2, 3), (1, 3), (4, 2)])?;
+  let query = Query::new()
+    .pattern(Pattern::link(var("x"), var("y")))
+    .pattern(Pattern::link(var("y"), Term::Const(3)));
+
+  // Points are links too, so `y -> y` paths show up as well
+  assert_eq!(
+    bound(&query, &store, &["x", "y"]),
+    [[1, 1], [1, 2], [1, 3], [2, 2], [2, 3], [3, 3], [4, 2]]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_cycle() -> Result<(), Error<usize>> {
+  let store = graph(4, &[(1, 2), (2, 3), (3, 1), (3, 4), (4, 2)])?;
+  let query = Query::new()
+    .pattern(Pattern::link(var("a"), var("b")))
+    .pattern(Pattern::link(var("b"), var("c")))
+    .pattern(Pattern::link(var("c"), var("a")))
+    // Skip the degenerate cycles through points
+    .pattern(Pattern::new(var("ab"), var("a"), var("b")));
+
+  let found = bound(&query, &store, &["a", "b", "c"]);
+  let cycles: Vec<_> = found
+    .into_iter()
+    .filter(|parts| parts[0] != parts[1] && parts[1] != parts[2])
+    .collect();
+  assert_eq!(
+    cycles,
+    [[1, 2, 3], [2, 3, 1], [2, 3, 4], [3, 1, 2], [3, 4, 2], [4, 2, 3]]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_repeated_variable() -> Result<(), Error<usize>> {
+  let store = graph(2, &[(1, 2), (2, 1)])?;
+  let points = Query::new().pattern(Pattern::new(var("p"), var("p"), var("p")));
+  assert_eq!(bound(&points, &store, &["p"]), [[1], [2]]);
+
+  let loops = Query::new().pattern(Pattern::link(var("x"), var("x")));
+  assert_eq!(bound(&loops, &store, &["x"]), [[1], [2]]);
+  Ok(())
+}
+
+#[test]
+fn test_links_to_links() -> Result<(), Error<usize>> {
+  // 3: 1 -> 2, and 4 points at that link
+  let store = graph(2, &[(1, 2), (3, 2)])?;
+  let query = Query::new()
+    .pattern(Pattern::new(var("edge"), Term::Const(1), var("to")))
+    .pattern(Pattern::new(var("meta"), var("edge"), Term::Any));
+
+  // Point 1 starts at 1 as well, and 1 and 3 both start at 1
+  let found = query.run(&store);
+  assert_eq!(found.len(), 3);
+  let edge = found.iter().find(|found| found.get("edge") == Some(3)).unwrap();
+  assert_eq!(edge.get("meta"), Some(4));
+  assert_eq!(
+    edge.iter().collect::<Vec<_>>(),
+    [("edge", 3), ("to", 2), ("meta", 4)]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_plan_starts_selective() -> Result<(), Error<usize>> {
+  let edges: Vec<_> = (2..=20).map(|to| (1, to)).chain([(5, 7)]).collect();
+  let store = graph(20, &edges)?;
+  let query = Query::new()
+    .pattern(Pattern::link(Term::Const(1), var("x")))
+    .pattern(Pattern::link(var("x"), Term::Const(7)))
+    .pattern(Pattern::link(Term::Const(5), var("y")));
+
+  // `5 -> ?y` matches the fewest links, and nothing joins it to the rest,
+  // so the planner follows with the smaller of the other two
+  assert_eq!(query.plan(&store), [2, 1, 0]);
+  assert_eq!(
+    bound(&query, &store, &["x", "y"]),
+    [[1, 5], [1, 7], [5, 5], [5, 7], [7, 5], [7, 7]]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_plan_counts_bound_variables() -> Result<(), Error<usize>> {
+  let edges: Vec<_> = (2..=20).map(|to| (1, to)).chain([(5, 7)]).collect();
+  let store = graph(20, &edges)?;
+  let query = Query::new()
+    .pattern(Pattern::new(var("x"), Term::Const(5), var("y")))
+    .pattern(Pattern::link(var("x"), Term::Const(7)))
+    .pattern(Pattern::link(var("y"), var("x")));
+
+  // Once `?x` and `?y` are bound, `?y -> ?x` pins both of its parts
+  // while `?x -> 7` leaves every link to 7 to try
+  assert_eq!(query.plan(&store), [0, 2, 1]);
+  // Point 5 is the only link from 5 that is also a source of `5 -> 7`
+  assert_eq!(bound(&query, &store, &["x", "y"]), [[5, 5]]);
+  Ok(())
+}
+
+#[test]
+fn test_break_and_empty() -> Result<(), Error<usize>> {
+  let store = graph(5, &[])?;
+  let query = Query::new().pattern(Pattern::link(var("x"), Term::Any));
+
+  let mut seen = 0;
+  let flow = query.each(&store, |_| {
+    seen += 1;
+    if seen == 2 { Flow::Break } else { Flow::Continue }
+  });
+  assert_eq!((flow, seen), (Flow::Break, 2));
+
+  let empty = Query::<usize>::new();
+  assert_eq!(empty.run(&store).len(), 1);
+  assert!(empty.variables().is_empty());
+  Ok(())
+}
+
+#[test]
+fn test_matches_brute_force() -> Result<(), Error<usize>> {
+  // Deterministic pseudo-random graph
+  let mut seed = 0x2545_f491_u64;
+  let mut next = |n: u64| {
+    seed ^= seed << 13;
+    seed ^= seed >> 7;
+    seed ^= seed << 17;
+    (seed % n) as usize + 1
+  };
+  let edges: Vec<_> = (0..80).map(|_| (next(12), next(12))).collect();
+  let store = graph(12, &edges)?;
+  let all = store.collect_all();
+
+  let query = Query::new()
+    .pattern(Pattern::link(var("a"), var("b")))
+    .pattern(Pattern::link(var("b"), var("c")))
+    .pattern(Pattern::link(var("a"), var("c")));
+  let found = bound(&query, &store, &["a", "b", "c"]);
+
+  let has = |from, to| {
+    all
+      .iter()
+      .any(|link: &Link<usize>| (link.source, link.target) == (from, to))
+  };
+  let mut expected = Vec::new();
+  for &Link { source: a, target: b, .. } in &all {
+    for &Link { source, target: c, .. } in &all {
+      if source == b && has(a, c) {
+        expected.push(vec![a, b, c]);
+      }
+    }
+  }
+  // Duplicate links give duplicate matches in both
+  let count = |from, to| {
+    all.iter().filter(|link| (link.source, link.target) == (from, to)).count()
+  };
+  let mut expected: Vec<_> = expected
+    .into_iter()
+    .flat_map(|m| std::iter::repeat_n(m.clone(), count(m[0], m[2])))
+    .collect();
+  expected.sort();
+  assert_eq!(found, expected);
+  Ok(())
+}
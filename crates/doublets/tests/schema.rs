@@ -0,0 +1,174 @@
+// Tests for the typed schema layer
+//
+// Types and properties are links in the store itself, and every write
+// through `Schema` is checked, so a violating write must leave the store
+// exactly as it was.
+
+use doublets::{
+  Doublets, Error, Link, Links, Numbers, Schema, Store, create_heap_store,
+};
+
+struct Model {
+  schema: Schema<usize, Store<usize>>,
+  person: usize,
+  city: usize,
+  lives_in: usize,
+  age: usize,
+}
+
+fn model() -> Result<Model, Error<usize>> {
+  let mut schema = Schema::new(create_heap_store()?)?;
+  let person = schema.declare_type("person")?;
+  let city = schema.declare_type("city")?;
+  let lives_in = schema.declare_property("lives in", person, Some(city))?;
+  let age = schema.declare_property("age", person, None)?;
+  Ok(Model { schema, person, city, lives_in, age })
+}
+
+#[test]
+fn test_declarations_are_deduplicated() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, city, lives_in, .. } = model()?;
+
+  assert_eq!(schema.declare_type("person")?, person);
+  assert_eq!(
+    schema.declare_property("lives in", person, Some(city))?,
+    lives_in
+  );
+  assert_eq!(schema.type_named("city"), Some(city));
+  assert_eq!(schema.property_named("lives in"), Some(lives_in));
+  assert_eq!(schema.type_named("country"), None);
+  assert_eq!(schema.property_named("person"), None);
+
+  assert_eq!(
+    schema.declare_property("lives in", city, None),
+    Err(Error::SchemaViolation(lives_in))
+  );
+  Ok(())
+}
+
+#[test]
+fn test_entities_of_type() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, city, .. } = model()?;
+  let alice = schema.create_entity(person)?;
+  let bob = schema.create_entity(person)?;
+  let paris = schema.create_entity(city)?;
+
+  assert_eq!(schema.entities(person), [alice, bob]);
+  assert_eq!(schema.entities(city), [paris]);
+
+  // An entity may have several types
+  schema.set_type(paris, person)?;
+  assert!(schema.has_type(paris, person));
+  assert_eq!(schema.entities(person), [alice, bob, paris]);
+  Ok(())
+}
+
+#[test]
+fn test_triples() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, city, lives_in, age } = model()?;
+  let alice = schema.create_entity(person)?;
+  let paris = schema.create_entity(city)?;
+  let berlin = schema.create_entity(city)?;
+
+  schema.attach(alice, lives_in, paris)?;
+  schema.attach(alice, lives_in, berlin)?;
+  let years = schema.to_link(30u64)?;
+  schema.attach(alice, age, years)?;
+
+  assert_eq!(schema.values(alice, lives_in), [paris, berlin]);
+  let [years] = schema.values(alice, age)[..] else { panic!() };
+  assert_eq!(schema.from_link::<u64>(years)?, 30);
+  assert_eq!(schema.values(paris, lives_in), []);
+  Ok(())
+}
+
+#[test]
+fn test_domain_and_range() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, city, lives_in, age } = model()?;
+  let alice = schema.create_entity(person)?;
+  let paris = schema.create_entity(city)?;
+  let count = schema.count_all();
+
+  let result = schema.attach(paris, age, alice);
+  assert!(matches!(result, Err(Error::SchemaViolation(_))));
+  let result = schema.attach(alice, lives_in, alice);
+  assert!(matches!(result, Err(Error::SchemaViolation(_))));
+  assert_eq!(schema.count_all(), count);
+  assert_eq!(schema.values(alice, lives_in), []);
+
+  assert_eq!(
+    schema.attach(alice, person, paris),
+    Err(Error::SchemaViolation(person))
+  );
+  Ok(())
+}
+
+#[test]
+fn test_plain_writes_are_checked() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, city, age, .. } = model()?;
+  let paris = schema.create_entity(city)?;
+  let alice = schema.create_entity(person)?;
+
+  // Building the pair by hand goes through the same check
+  let result = schema.create_link(paris, age);
+  assert!(matches!(result, Err(Error::SchemaViolation(_))));
+
+  // So does moving a valid pair onto a city
+  let pair = schema.create_link(alice, age)?;
+  let result = schema.update_link(pair, paris, age);
+  assert!(matches!(result, Err(Error::SchemaViolation(p)) if p == pair));
+  assert_eq!(schema.get(pair), Some(Link::new(pair, alice, age)));
+  Ok(())
+}
+
+#[test]
+fn test_removing_a_used_type() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, city, lives_in, .. } = model()?;
+  let alice = schema.create_entity(person)?;
+  let paris = schema.create_entity(city)?;
+  schema.attach(alice, lives_in, paris)?;
+
+  for (ty, entity) in [(person, alice), (city, paris)] {
+    let typing = schema.search(ty, entity).unwrap();
+    assert_eq!(schema.delete_link(typing), Err(Error::SchemaViolation(typing)));
+    assert!(schema.has_type(entity, ty));
+  }
+
+  // An unused type can go
+  let bob = schema.create_entity(person)?;
+  let typing = schema.search(person, bob).unwrap();
+  schema.delete_link(typing)?;
+  assert_eq!(schema.entities(person), [alice]);
+  Ok(())
+}
+
+#[test]
+fn test_persisted_in_the_store() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, lives_in, .. } = model()?;
+  let alice = schema.create_entity(person)?;
+
+  let mut schema = Schema::new(schema.into_inner())?;
+  assert_eq!(schema.type_named("person"), Some(person));
+  assert_eq!(schema.entities(person), [alice]);
+  assert!(matches!(
+    schema.attach(alice, lives_in, alice),
+    Err(Error::SchemaViolation(_))
+  ));
+  Ok(())
+}
+
+#[test]
+fn test_handler_sees_changes() -> Result<(), Error<usize>> {
+  let Model { mut schema, person, .. } = model()?;
+  let alice = schema.create_entity(person)?;
+
+  let mut seen = Vec::new();
+  schema.create([alice, alice], &mut |before, after| {
+    seen.push((before, after));
+    true
+  })?;
+  assert_eq!(seen.len(), 1);
+  assert!(seen[0].0.is_null());
+  assert_eq!((seen[0].1.source, seen[0].1.target), (alice, alice));
+  Ok(())
+}
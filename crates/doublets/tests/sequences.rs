@@ -0,0 +1,123 @@
+// Tests for strings stored as links
+//
+// Strings are pair-trees over character links, so storing text twice or
+// storing text with a common prefix must reuse links instead of adding
+// new ones.
+
+use doublets::{
+  Doublets, Error, Links, Sequences, Uniqueness, constants, create_heap_store,
+};
+
+#[test]
+fn test_round_trip() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  for text in ["", "a", "hello", "\0", "Grüße, 世界 🦀", "aaaaaaaaaaa"] {
+    let link = store.create_string(text)?;
+    assert_eq!(store.read_string(link)?, text);
+  }
+  Ok(())
+}
+
+#[test]
+fn test_reserves_constants() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  store.reserve()?;
+
+  assert_eq!(store.count_all(), constants::MAX_RESERVED);
+  for constant in 1..=constants::MAX_RESERVED {
+    assert_eq!(store.search(constant, constant), Some(constant));
+  }
+  // Reserving again changes nothing
+  store.reserve()?;
+  assert_eq!(store.count_all(), constants::MAX_RESERVED);
+  Ok(())
+}
+
+#[test]
+fn test_reserved_index_taken() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let a = store.create_point()?;
+  store.create_link(a, a)?;
+
+  assert_eq!(store.create_string("x"), Err(Error::Reserved(constants::ONE)));
+  assert_eq!(store.count_all(), 2);
+  Ok(())
+}
+
+#[test]
+fn test_deduplicates() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let first = store.create_string("hello world")?;
+  let count = store.count_all();
+
+  assert_eq!(store.create_string("hello world")?, first);
+  assert_eq!(store.count_all(), count);
+
+  // The first eight characters form a shared subtree
+  store.create_string("hello wo")?;
+  assert_eq!(store.count_all(), count + 1);
+  Ok(())
+}
+
+#[test]
+fn test_chars_are_shared() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let a = store.create_char('a')?;
+
+  assert_eq!(store.create_char('a')?, a);
+  assert_eq!(store.read_char(a)?, 'a');
+  let text = store.create_string("a")?;
+  assert_eq!(store.get(text).unwrap().target, a);
+
+  // 'a' and 'c' only differ in their second to last bit
+  let count = store.count_all();
+  store.create_char('c')?;
+  assert_eq!(store.count_all(), count + 2);
+  Ok(())
+}
+
+#[test]
+fn test_not_a_string() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  store.reserve()?;
+  let point = store.create_point()?;
+  let pair = store.create_link(constants::UNICODE_SEQUENCE, point)?;
+
+  assert_eq!(store.read_string(point), Err(Error::InvalidSequence(point)));
+  assert_eq!(store.read_string(pair), Err(Error::InvalidSequence(point)));
+  assert_eq!(store.read_char(pair), Err(Error::InvalidSequence(pair)));
+  assert_eq!(store.read_string(constants::UNICODE_SEQUENCE).as_deref(), Ok(""));
+  assert_eq!(store.read_string(99), Err(Error::NotExists(99)));
+  Ok(())
+}
+
+#[test]
+fn test_unique_store() -> Result<(), Error<usize>> {
+  let mut store =
+    create_heap_store::<usize>()?.with_uniqueness(Uniqueness::Unique);
+  let link = store.create_string("\0\u{3}\u{7}\u{ffff} 000 111")?;
+  assert_eq!(store.read_string(link)?, "\0\u{3}\u{7}\u{ffff} 000 111");
+  Ok(())
+}
+
+#[test]
+fn test_narrow_index() {
+  let mut store = create_heap_store::<u16>().unwrap();
+  let link = store.create_string("narrow, but fine").unwrap();
+  assert_eq!(store.read_string(link).unwrap(), "narrow, but fine");
+}
+
+#[test]
+fn test_every_prefix() -> Result<(), Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let text: String = ('a'..='z').chain('Α'..='Ω').collect();
+
+  let links: Vec<_> = (0..=text.chars().count())
+    .map(|len| store.create_string(&text.chars().take(len).collect::<String>()))
+    .collect::<Result<_, _>>()?;
+  for (len, &link) in links.iter().enumerate() {
+    let prefix: String = text.chars().take(len).collect();
+    assert_eq!(store.read_string(link)?, prefix);
+  }
+  Ok(())
+}
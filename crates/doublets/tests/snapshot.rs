@@ -0,0 +1,209 @@
+// Tests for portable snapshots
+//
+// A snapshot lists links by index in a width-independent encoding, so
+// importing it must reproduce every link number and rebuild the indexes
+// of the target store whatever its index type.
+
+use doublets::{
+  Doublets, Error, Link, Links, Snapshot, Store, Uniqueness, create_heap_store,
+};
+
+fn sample() -> Result<Store<usize>, Error<usize>> {
+  let mut store = create_heap_store::<usize>()?;
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+  let d = store.create_link(b, a)?;
+  store.create_link(c, d)?;
+  store.create_link(a, b)?;
+  let e = store.create_link(d, d)?;
+  store.create_link(d, b)?;
+  store.create_link(300, 70_000)?;
+  // Leave a hole that the import has to keep
+  store.delete_link(e)?;
+  Ok(store)
+}
+
+fn export(store: &impl Links<usize>) -> Vec<u8> {
+  let mut bytes = Vec::new();
+  store.export(&mut bytes).unwrap();
+  bytes
+}
+
+#[test]
+fn test_round_trip() -> Result<(), Error<usize>> {
+  let store = sample()?;
+  let bytes = export(&store);
+
+  let mut copy = create_heap_store::<usize>()?;
+  copy.import(bytes.as_slice())?;
+
+  assert_eq!(copy.collect_all(), store.collect_all());
+  assert_eq!(export(&copy), bytes);
+  Ok(())
+}
+
+#[test]
+fn test_import_rebuilds_indexes() -> Result<(), Error<usize>> {
+  let store = sample()?;
+  let mut copy = create_heap_store::<usize>()?;
+  copy.import(export(&store).as_slice())?;
+
+  assert_eq!(copy.search(1, 2), store.search(1, 2));
+  assert_eq!(copy.count_by([0, 1, 0]), store.count_by([0, 1, 0]));
+  assert_eq!(copy.count_by([0, 0, 2]), store.count_by([0, 0, 2]));
+  // The hole left by the deleted link is reused first
+  let fresh = copy.create_point()?;
+  assert_eq!(fresh, 7);
+  Ok(())
+}
+
+#[test]
+fn test_across_index_widths() -> Result<(), Error<usize>> {
+  let store = sample()?;
+  let bytes = export(&store);
+
+  let mut narrow = create_heap_store::<u32>().unwrap();
+  narrow.import(bytes.as_slice()).unwrap();
+  let links: Vec<_> = narrow
+    .collect_all()
+    .into_iter()
+    .map(|link| {
+      Link::new(link.index as usize, link.source as usize, link.target as usize)
+    })
+    .collect();
+  assert_eq!(links, store.collect_all());
+
+  let mut back = Vec::new();
+  narrow.export(&mut back).unwrap();
+  // Only the width byte and therefore the checksum differ
+  let links = 7..bytes.len() - 4;
+  assert_eq!(back[..6], bytes[..6]);
+  assert_eq!(back[links.clone()], bytes[links]);
+  Ok(())
+}
+
+#[test]
+fn test_value_too_wide() -> Result<(), Error<usize>> {
+  let bytes = export(&sample()?);
+
+  let mut tiny = create_heap_store::<u16>().unwrap();
+  assert_eq!(tiny.import(bytes.as_slice()), Err(Error::Overflow));
+  // Nothing is written when decoding fails
+  assert_eq!(tiny.count_all(), 0);
+  Ok(())
+}
+
+#[test]
+fn test_without_checksum() -> Result<(), Error<usize>> {
+  let store = sample()?;
+  let mut bytes = Vec::new();
+  Snapshot::new().with_checksum(false).export(&store, &mut bytes)?;
+  assert_eq!(bytes.len() + 4, export(&store).len());
+
+  let mut copy = create_heap_store::<usize>()?;
+  copy.import(bytes.as_slice())?;
+  assert_eq!(copy.collect_all(), store.collect_all());
+  Ok(())
+}
+
+#[test]
+fn test_corrupted_stream() -> Result<(), Error<usize>> {
+  let bytes = export(&sample()?);
+
+  let mut flipped = bytes.clone();
+  flipped[9] ^= 0x01;
+  let mut copy = create_heap_store::<usize>()?;
+  assert_eq!(copy.import(flipped.as_slice()), Err(Error::InvalidSnapshot));
+
+  for len in [0, 3, 7, bytes.len() / 2, bytes.len() - 1] {
+    assert_eq!(
+      copy.import(&bytes[..len]),
+      Err(Error::InvalidSnapshot),
+      "truncated to {len} bytes"
+    );
+  }
+
+  let mut magic = bytes.clone();
+  magic[0] = b'X';
+  assert_eq!(copy.import(magic.as_slice()), Err(Error::InvalidSnapshot));
+  assert_eq!(copy.count_all(), 0);
+  Ok(())
+}
+
+#[test]
+fn test_import_into_non_empty_store() -> Result<(), Error<usize>> {
+  let bytes = export(&sample()?);
+
+  let mut store = create_heap_store::<usize>()?;
+  store.create_point()?;
+  assert_eq!(store.import(bytes.as_slice()), Err(Error::NotEmpty));
+  Ok(())
+}
+
+#[test]
+fn test_failed_import_leaves_store_empty() -> Result<(), Error<usize>> {
+  // The sample holds (a, b) twice, which a unique store rejects
+  let bytes = export(&sample()?);
+
+  let mut store =
+    create_heap_store::<usize>()?.with_uniqueness(Uniqueness::Unique);
+  let result = store.import(bytes.as_slice());
+  assert!(matches!(result, Err(Error::AlreadyExists(..))));
+  assert_eq!(store.count_all(), 0);
+  assert!(store.verify().is_ok());
+
+  store.set_uniqueness(Uniqueness::default());
+  store.import(bytes.as_slice())?;
+  assert_eq!(export(&store), bytes);
+  Ok(())
+}
+
+#[test]
+fn test_empty_store() -> Result<(), Error<usize>> {
+  let store = create_heap_store::<usize>()?;
+  let bytes = export(&store);
+  assert_eq!(&bytes[..4], b"DBLS");
+
+  let mut copy = create_heap_store::<usize>()?;
+  copy.import(bytes.as_slice())?;
+  assert_eq!(copy.count_all(), 0);
+  Ok(())
+}
+
+#[test]
+fn test_reject_huge_gap() -> Result<(), Error<usize>> {
+  // One link at index 2^40, without a checksum
+  let mut bytes = b"DBLS\x01\x00\x08\x01".to_vec();
+  bytes.extend([0x80, 0x80, 0x80, 0x80, 0x80, 0x20, 0x01, 0x01]);
+
+  let mut store = create_heap_store::<usize>()?;
+  assert_eq!(store.import(bytes.as_slice()), Err(Error::InvalidSnapshot));
+  assert_eq!(store.count_all(), 0);
+  Ok(())
+}
+
+#[test]
+fn test_import_into_store_with_freed_slots() -> Result<(), Error<usize>> {
+  let bytes = export(&sample()?);
+
+  // Freed slots hand out 2 before 1
+  let mut store = create_heap_store::<usize>()?;
+  let points = [store.create_point()?, store.create_point()?];
+  store.delete_link(points[0])?;
+  store.delete_link(points[1])?;
+  assert_eq!(store.import(bytes.as_slice()), Err(Error::NotEmpty));
+
+  // Slot 1 comes first, but 3 follows it
+  let mut store = create_heap_store::<usize>()?;
+  let points = [1, 2, 3].map(|_| store.create_point().unwrap());
+  for point in [points[1], points[2], points[0]] {
+    store.delete_link(point)?;
+  }
+  assert_eq!(store.import(bytes.as_slice()), Err(Error::OutOfOrder(3)));
+  assert_eq!(store.count_all(), 0);
+  assert!(store.verify().is_ok());
+  assert_eq!(store.create_point()?, 1);
+  assert_eq!(store.create_point()?, 3);
+  Ok(())
+}
@@ -0,0 +1,258 @@
+// Tests for grouping writes into transactions backed by a log file
+
+use {
+  doublets::{
+    Doublets, Error, Flow, Link, Links, RawLink, ReadHandler, Store,
+    Transactional, WriteHandler, create_heap_store,
+  },
+  mem::FileMapped,
+  std::path::{Path, PathBuf},
+};
+
+type FileStore = Store<usize, FileMapped<RawLink>>;
+
+fn open(path: &Path) -> Result<FileStore, Error<usize>> {
+  let mem = FileMapped::from_path(path).expect("failed to map file");
+  Store::open(mem)
+}
+
+#[test]
+fn test_commit_keeps_changes() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let mut store =
+    Transactional::open(create_heap_store()?, dir.path().join("log"))?;
+
+  let a = store.create_point()?;
+  store.begin()?;
+  let b = store.create_link(a, a)?;
+  store.commit()?;
+
+  assert!(!store.in_transaction());
+  assert_eq!(store.get(b), Some(Link::new(b, a, a)));
+  Ok(())
+}
+
+#[test]
+fn test_rollback_restores_state() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let mut store =
+    Transactional::open(create_heap_store()?, dir.path().join("log"))?;
+
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_link(a, b)?;
+  let d = store.create_link(b, a)?;
+  let snapshot = store.collect_all();
+
+  store.begin()?;
+  store.delete_link(d)?;
+  store.create_link(c, c)?;
+  store.update_link(c, b, b)?;
+  store.rebase(b, a)?;
+  store.create_point()?;
+  store.rollback()?;
+
+  assert_eq!(store.collect_all(), snapshot);
+  assert_eq!(store.search(a, b), Some(c));
+  assert_eq!(store.count([0, 0, a]), 2);
+  Ok(())
+}
+
+#[test]
+fn test_transaction_closure() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let mut store =
+    Transactional::open(create_heap_store()?, dir.path().join("log"))?;
+
+  let a = store.create_point()?;
+  let b = store.transaction(|store| store.create_link(a, a))?;
+  assert_eq!(store.count_all(), 2);
+
+  let result = store.transaction(|store| {
+    store.create_point()?;
+    store.delete_link(a)
+  });
+  assert_eq!(result, Err(Error::HasUsages(a)));
+  assert_eq!(store.collect_all(), vec![Link::point(a), Link::new(b, a, a)]);
+  Ok(())
+}
+
+#[test]
+fn test_transaction_state_errors() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let mut store =
+    Transactional::open(create_heap_store::<usize>()?, dir.path().join("log"))?;
+
+  assert_eq!(store.commit(), Err(Error::NoTransaction));
+  assert_eq!(store.rollback(), Err(Error::NoTransaction));
+
+  store.begin()?;
+  assert_eq!(store.begin(), Err(Error::TransactionActive));
+  store.commit()?;
+
+  assert!(store.into_inner().is_ok());
+  Ok(())
+}
+
+#[test]
+fn test_recover_unfinished_transaction() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let (db, log) = (dir.path().join("db"), dir.path().join("log"));
+
+  let snapshot = {
+    let mut store = Transactional::open(open(&db)?, &log)?;
+    let a = store.create_point()?;
+    let b = store.create_point()?;
+    store.transaction(|store| store.create_link(a, b))?;
+    let snapshot = store.collect_all();
+
+    store.begin()?;
+    let c = store.create_link(b, b)?;
+    store.update_link(c, a, a)?;
+    store.rebase(b, a)?;
+
+    // Simulate the process dying before commit
+    std::mem::forget(store);
+    snapshot
+  };
+
+  let store = Transactional::open(open(&db)?, &log)?;
+  assert_eq!(store.collect_all(), snapshot);
+  assert_eq!(std::fs::metadata(&log).unwrap().len(), 0);
+  Ok(())
+}
+
+/// Copies the store and log files aside right before and right after
+/// `create` writes to the store, the states a crash there would leave
+struct CrashOnCreate {
+  store: FileStore,
+  files: [PathBuf; 2],
+}
+
+impl CrashOnCreate {
+  fn copy(path: &Path, stage: &str) -> PathBuf {
+    path.with_extension(stage)
+  }
+
+  fn save(&self, stage: &str) {
+    for file in &self.files {
+      std::fs::copy(file, Self::copy(file, stage)).unwrap();
+    }
+  }
+}
+
+impl Links<usize> for CrashOnCreate {
+  fn count<const N: usize>(&self, query: [usize; N]) -> usize {
+    self.store.count(query)
+  }
+
+  fn create<const N: usize, H: WriteHandler<usize>>(
+    &mut self,
+    query: [usize; N],
+    handler: &mut H,
+  ) -> Result<Flow, Error<usize>> {
+    self.save("before");
+    let flow = self.store.create(query, handler)?;
+    self.save("after");
+    Ok(flow)
+  }
+
+  fn each<const N: usize, H: ReadHandler<usize>>(
+    &self,
+    query: [usize; N],
+    handler: &mut H,
+  ) -> Flow {
+    self.store.each(query, handler)
+  }
+
+  fn update<const N1: usize, const N2: usize, H: WriteHandler<usize>>(
+    &mut self,
+    query: [usize; N1],
+    change: [usize; N2],
+    handler: &mut H,
+  ) -> Result<Flow, Error<usize>> {
+    self.store.update(query, change, handler)
+  }
+
+  fn delete<const N: usize, H: WriteHandler<usize>>(
+    &mut self,
+    query: [usize; N],
+    handler: &mut H,
+  ) -> Result<Flow, Error<usize>> {
+    self.store.delete(query, handler)
+  }
+
+  fn get(&self, index: usize) -> Option<Link<usize>> {
+    self.store.get(index)
+  }
+
+  fn next_index(&self) -> Option<usize> {
+    self.store.next_index()
+  }
+}
+
+#[test]
+fn test_recover_write_logged_ahead() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let (db, log) = (dir.path().join("db"), dir.path().join("log"));
+
+  let snapshot = {
+    let mut store = open(&db)?;
+    let a = store.create_point()?;
+    let b = store.create_point()?;
+    let c = store.create_link(a, b)?;
+    let snapshot = store.collect_all();
+
+    let files = [db.clone(), log.clone()];
+    let store = CrashOnCreate { store, files };
+    let mut store = Transactional::open(store, &log)?;
+    store.begin()?;
+    store.update_link(c, b, a)?;
+    store.create_link(b, b)?;
+    std::mem::forget(store);
+    snapshot
+  };
+
+  // The create is logged both before and after it reaches the store
+  for stage in ["before", "after"] {
+    std::fs::copy(CrashOnCreate::copy(&db, stage), &db).unwrap();
+    std::fs::copy(CrashOnCreate::copy(&log, stage), &log).unwrap();
+    let store = Transactional::open(open(&db)?, &log)?;
+    assert_eq!(store.collect_all(), snapshot, "crashed {stage} the write");
+    assert!(store.store().verify().is_ok());
+  }
+  Ok(())
+}
+
+#[test]
+fn test_log_emptied_after_transaction() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let log = dir.path().join("log");
+  let mut store = Transactional::open(create_heap_store::<usize>()?, &log)?;
+  let len = || std::fs::metadata(&log).unwrap().len();
+
+  let a = store.create_point()?;
+  store.begin()?;
+  store.create_link(a, a)?;
+  assert!(len() > 0);
+  store.commit()?;
+  assert_eq!(len(), 0);
+
+  store.begin()?;
+  store.create_point()?;
+  store.rollback()?;
+  assert_eq!(len(), 0);
+  assert_eq!(store.count_all(), 2);
+  Ok(())
+}
+
+#[test]
+fn test_reject_corrupt_log() -> Result<(), Error<usize>> {
+  let dir = tempfile::tempdir().unwrap();
+  let log = dir.path().join("log");
+  std::fs::write(&log, [0xFF; 49]).unwrap();
+
+  let result = Transactional::open(create_heap_store::<usize>()?, &log);
+  assert!(matches!(result, Err(Error::CorruptLog)));
+  Ok(())
+}
@@ -1,15 +1,15 @@
 // Tests demonstrating tree backend selection for the doublets store
 //
 // This module tests the ability to choose between different tree
-// implementations (SBT - Size-Balanced Tree and ART - Adaptive Radix
-// Tree) for source and target indexing.
+// implementations (SBT - Size-Balanced Tree, ART - Adaptive Radix Tree
+// and B+ tree) for source and target indexing.
 //
 // The tests use a generic approach with a macro to instantiate test
 // functions for different tree backend combinations.
 
 use doublets::{
-  ArtStrategy, Doublets, Flow, Link, Links, Result, SbtStrategy, TreeStrategy,
-  create_heap_store_with_strategies,
+  ArtStrategy, BPlusStrategy, Cursor, Doublets, Flow, Link, Links, Result,
+  SbtStrategy, TreeStrategy, create_heap_store_with_strategies,
 };
 
 /// Macro to generate tests for a specific tree backend combination
@@ -50,6 +50,21 @@ macro_rules! define_tests_for_backend {
       fn [<test_scalability_ $suffix>]() -> Result<(), usize> {
         test_scalability::<$src, $tgt>()
       }
+
+      #[test]
+      fn [<test_queries_match_scan_ $suffix>]() -> Result<(), usize> {
+        test_queries_match_scan::<$src, $tgt>()
+      }
+
+      #[test]
+      fn [<test_query_order_ $suffix>]() -> Result<(), usize> {
+        test_query_order::<$src, $tgt>()
+      }
+
+      #[test]
+      fn [<test_cursor_resume_ $suffix>]() -> Result<(), usize> {
+        test_cursor_resume::<$src, $tgt>()
+      }
     }
   };
 }
@@ -238,8 +253,169 @@ where
   Ok(())
 }
 
+// Source/target queries and counts must agree with a full scan,
+// including after updates and deletes have reshaped the trees
+fn test_queries_match_scan<S, T>() -> Result<(), usize>
+where
+  S: TreeStrategy<usize> + 'static,
+  T: TreeStrategy<usize> + 'static,
+{
+  fn collect<L: Links<usize>>(
+    store: &L,
+    query: [usize; 3],
+  ) -> Vec<Link<usize>> {
+    let mut links = Vec::new();
+    store.each(query, &mut |link| {
+      links.push(link);
+      Flow::Continue
+    });
+    links.sort_by_key(|link| link.index);
+    links
+  }
+
+  let mut store = create_heap_store_with_strategies::<usize, S, T>()?;
+  let points: Vec<_> =
+    (0..20).map(|_| store.create_point()).collect::<Result<_, _>>()?;
+
+  // xorshift keeps the sequence deterministic without extra dependencies
+  let mut state = 0x2545_f491_u64;
+  let mut next = |bound: usize| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    (state % bound as u64) as usize
+  };
+
+  for _ in 0..500 {
+    let links = store.collect_all();
+    let source = points[next(points.len())];
+    let target = points[next(points.len())];
+    match next(3) {
+      0 => {
+        store.get_or_create(source, target)?;
+      }
+      1 if store.search(source, target).is_none() => {
+        let link = links[next(links.len())];
+        if !points.contains(&link.index) {
+          store.update_link(link.index, source, target)?;
+        }
+      }
+      _ => {
+        let link = links[next(links.len())];
+        if !points.contains(&link.index) {
+          store.delete_link(link.index)?;
+        }
+      }
+    }
+  }
+
+  let all = store.collect_all();
+  for &point in &points {
+    let by_source: Vec<_> =
+      all.iter().copied().filter(|link| link.source == point).collect();
+    assert_eq!(store.count([0, point, 0]), by_source.len());
+    assert_eq!(collect(&store, [0, point, 0]), by_source);
+
+    let by_target: Vec<_> =
+      all.iter().copied().filter(|link| link.target == point).collect();
+    assert_eq!(store.count([0, 0, point]), by_target.len());
+    assert_eq!(collect(&store, [0, 0, point]), by_target);
+  }
+  for link in &all {
+    assert_eq!(store.count([0, link.source, link.target]), 1);
+    assert_eq!(collect(&store, [0, link.source, link.target]), vec![*link]);
+  }
+
+  Ok(())
+}
+
+// Indexed queries walk the composite key order: by source the links come
+// sorted by (target, index) and by target they come sorted by (source, index)
+fn test_query_order<S, T>() -> Result<(), usize>
+where
+  S: TreeStrategy<usize> + 'static,
+  T: TreeStrategy<usize> + 'static,
+{
+  let mut store = create_heap_store_with_strategies::<usize, S, T>()?;
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  let c = store.create_point()?;
+  let ac = store.create_link(a, c)?;
+  let ab = store.create_link(a, b)?;
+  let ab2 = store.create_link(a, b)?;
+  let cb = store.create_link(c, b)?;
+
+  let mut by_source = Vec::new();
+  store.each([0, a, 0], &mut |link: Link<usize>| {
+    by_source.push(link.index);
+    Flow::Continue
+  });
+  assert_eq!(by_source, [a, ab, ab2, ac]);
+
+  let mut by_target = Vec::new();
+  store.each([0, 0, b], &mut |link: Link<usize>| {
+    by_target.push(link.index);
+    Flow::Continue
+  });
+  assert_eq!(by_target, [ab, ab2, b, cb]);
+
+  Ok(())
+}
+
+fn test_cursor_resume<S, T>() -> Result<(), usize>
+where
+  S: TreeStrategy<usize> + 'static,
+  T: TreeStrategy<usize> + 'static,
+{
+  let mut store = create_heap_store_with_strategies::<usize, S, T>()?;
+  let a = store.create_point()?;
+  let b = store.create_point()?;
+  // More links than one batch, with targets out of index order
+  let mut from_a: Vec<_> = (0..600)
+    .map(|i| store.create_link(a, if i % 3 == 0 { b } else { a }))
+    .collect::<Result<_, _>>()?;
+
+  let mut cursor = Cursor::by_source(a);
+  let page: Vec<_> = store.resume(&mut cursor).take(300).collect();
+  assert_eq!(cursor.last(), page.last().copied());
+
+  // Links created and deleted between pages do not disturb the walk
+  let last = page.last().unwrap().index;
+  store.delete_link(last)?;
+  from_a.retain(|&idx| idx != last);
+  from_a.push(store.create_link(a, b)?);
+  from_a.push(store.create_link(a, a)?);
+
+  let mut cursor = Cursor::from_token(&cursor.token())?;
+  let rest: Vec<_> = store.resume(&mut cursor).collect();
+  let walked: Vec<_> =
+    page.iter().chain(&rest).map(|link| link.index).collect();
+
+  // By target, then index, with the point `a` among links targeting `a`
+  from_a.push(a);
+  from_a.sort_by_key(|&idx| (store.get(idx).map(|link| link.target), idx));
+  let expected: Vec<_> =
+    from_a.into_iter().filter(|&idx| idx != last).collect();
+  let walked: Vec<_> = walked.into_iter().filter(|&idx| idx != last).collect();
+  assert_eq!(walked, expected);
+
+  let mut cursor = Cursor::by_target(b);
+  let by_target: Vec<_> = store.resume(&mut cursor).collect();
+  assert_eq!(by_target.len(), store.count([0, 0, b]));
+  assert!(
+    by_target
+      .windows(2)
+      .all(|w| (w[0].source, w[0].index) < (w[1].source, w[1].index))
+  );
+
+  Ok(())
+}
+
 // Instantiate tests for all backend combinations
 define_tests_for_backend!(SbtStrategy, SbtStrategy, "sbt_sbt");
 define_tests_for_backend!(ArtStrategy, ArtStrategy, "art_art");
 define_tests_for_backend!(SbtStrategy, ArtStrategy, "sbt_art");
 define_tests_for_backend!(ArtStrategy, SbtStrategy, "art_sbt");
+define_tests_for_backend!(BPlusStrategy, BPlusStrategy, "bplus_bplus");
+define_tests_for_backend!(SbtStrategy, BPlusStrategy, "sbt_bplus");
+define_tests_for_backend!(BPlusStrategy, ArtStrategy, "bplus_art");
@@ -0,0 +1,229 @@
+// Tests for checking a store against its link records and repairing it
+//
+// Damage is done to the raw words of a store's memory, the way a bug or
+// a torn write would leave it, and the store is then opened again.
+
+use {
+  doublets::{
+    ArtStrategy, BPlusStrategy, Doublets, Error, Integrity, Links, Problem,
+    SbtStrategy, Side, Store, TreeStrategy, create_heap_store,
+    create_heap_store_with_strategies,
+  },
+  mem::RawMem,
+};
+
+/// Words of a `RawLink<usize>`: source, target, then size, left and right
+/// of the source and of the target tree node
+type Words = [usize; 8];
+
+/// The header keeps the free count, free-list head and roots in these
+const FREE_COUNT: usize = 4;
+const FIRST_FREE: usize = 5;
+const SOURCE_ROOT: usize = 6;
+
+fn corrupt(
+  store: Store<usize>,
+  edit: impl FnOnce(&mut [Words]),
+) -> Store<usize> {
+  let mut mem = store.into_mem();
+  let slice = mem.as_mut_slice();
+  // SAFETY: a `RawLink<usize>` is eight index words without padding
+  let words = unsafe {
+    std::slice::from_raw_parts_mut(
+      slice.as_mut_ptr().cast::<Words>(),
+      slice.len(),
+    )
+  };
+  edit(words);
+  Store::open(mem).unwrap()
+}
+
+/// Three points and every link between two different ones
+fn sample() -> Result<Store<usize>, Error<usize>> {
+  let mut store = create_heap_store()?;
+  let points = [(); 3].map(|_| store.create_point().unwrap());
+  for source in points {
+    for target in points.into_iter().filter(|&target| target != source) {
+      store.create_link(source, target)?;
+    }
+  }
+  Ok(store)
+}
+
+fn healthy<S: TreeStrategy<usize>, U: TreeStrategy<usize>>()
+-> Result<(), Error<usize>> {
+  let mut store = create_heap_store_with_strategies::<usize, S, U>()?;
+  let points: Vec<_> =
+    (0..7).map(|_| store.create_point()).collect::<Result<_, _>>()?;
+  let a = points[0];
+  let links: Vec<_> = (0..200)
+    .map(|i| store.create_link(a, points[i % 7]))
+    .collect::<Result<_, _>>()?;
+  for &link in links.iter().step_by(3) {
+    store.delete_link(link)?;
+  }
+  store.update_link(links[1], links[2], a)?;
+
+  let report = store.verify();
+  assert_eq!(report.problems, []);
+  assert_eq!(report.links, store.count_all());
+
+  let by_target = store.count([0, 0, points[3]]);
+  store.rebuild_indexes()?;
+  assert!(store.verify().is_ok());
+  assert_eq!(store.count([0, links[2], a]), 1);
+  assert_eq!(store.count([0, 0, points[3]]), by_target);
+  // The point `a` and every link but the one moved to `links[2]`
+  assert_eq!(store.count([0, a, 0]), store.count_all() - points.len());
+  Ok(())
+}
+
+#[test]
+fn test_healthy_stores() -> Result<(), Error<usize>> {
+  healthy::<SbtStrategy, SbtStrategy>()?;
+  healthy::<ArtStrategy, SbtStrategy>()?;
+  healthy::<SbtStrategy, BPlusStrategy>()?;
+  healthy::<BPlusStrategy, ArtStrategy>()
+}
+
+#[test]
+fn test_wrong_size() -> Result<(), Error<usize>> {
+  let mut store = corrupt(sample()?, |words| words[4][2] += 1);
+
+  let report = store.verify();
+  assert!(
+    matches!(
+      report.problems[..],
+      [Problem::WrongSize { side: Side::Source, node: 4, stored, actual }]
+        if stored == actual + 1
+    ),
+    "{report:?}"
+  );
+  assert!(report.is_repairable());
+
+  store.rebuild_indexes()?;
+  assert!(store.verify().is_ok());
+  Ok(())
+}
+
+#[test]
+fn test_record_changed_behind_the_indexes() -> Result<(), Error<usize>> {
+  // `1 -> 2` becomes `3 -> 3` without moving in either tree
+  let mut store =
+    corrupt(sample()?, |words| words[4][..2].copy_from_slice(&[3, 3]));
+
+  let report = store.verify();
+  assert!(
+    report
+      .problems
+      .contains(&Problem::Misplaced { side: Side::Source, link: 4 })
+  );
+  assert!(
+    report
+      .problems
+      .contains(&Problem::Misplaced { side: Side::Target, link: 4 })
+  );
+  assert_eq!(store.search(3, 3), Some(3));
+
+  store.rebuild_indexes()?;
+  assert!(store.verify().is_ok());
+  assert_eq!(store.count([0, 3, 3]), 2);
+  assert_eq!(store.count([0, 1, 0]), 2);
+  Ok(())
+}
+
+#[test]
+fn test_cycle_and_lost_root() -> Result<(), Error<usize>> {
+  let store = corrupt(sample()?, |words| {
+    let root = words[0][SOURCE_ROOT];
+    words[root][3] = root;
+  });
+  let report = store.verify();
+  let root = report.problems.iter().find_map(|problem| match *problem {
+    Problem::Repeated { side: Side::Source, link } => Some(link),
+    _ => None,
+  });
+  assert!(root.is_some(), "{report:?}");
+
+  // Without a root, no link is reached
+  let mut store = corrupt(store, |words| words[0][SOURCE_ROOT] = 0);
+  let report = store.verify();
+  assert_eq!(
+    report.problems,
+    (1..=9)
+      .map(|link| Problem::Missing { side: Side::Source, link })
+      .collect::<Vec<_>>()
+  );
+
+  store.rebuild_indexes()?;
+  assert!(store.verify().is_ok());
+  assert_eq!(store.count([0, 2, 0]), 3);
+  Ok(())
+}
+
+#[test]
+fn test_free_list() -> Result<(), Error<usize>> {
+  let mut store = sample()?;
+  store.delete_link(5)?;
+  store.delete_link(7)?;
+
+  // 7 is the head and leads to 5, which now leads back to 7
+  let store = corrupt(store, |words| words[5][0] = 7);
+  assert_eq!(store.verify().problems, [Problem::FreeListCycle(7)]);
+
+  let store = corrupt(store, |words| {
+    words[5][0] = 0;
+    words[0][FREE_COUNT] = 3;
+  });
+  assert_eq!(
+    store.verify().problems,
+    [Problem::FreeCount { stored: 3, actual: 2 }]
+  );
+
+  let store = corrupt(store, |words| words[0][FIRST_FREE] = 4);
+  assert_eq!(
+    store.verify().problems,
+    [
+      Problem::FreeListBroken(4),
+      Problem::FreeSlotLost(5),
+      Problem::FreeSlotLost(7),
+      Problem::FreeCount { stored: 3, actual: 0 },
+    ]
+  );
+
+  let mut store = store;
+  store.rebuild_indexes()?;
+  assert!(store.verify().is_ok());
+  // The lowest free slot is handed out first
+  assert_eq!(store.create_point()?, 5);
+  assert_eq!(store.create_point()?, 7);
+  assert_eq!(store.create_point()?, 10);
+  Ok(())
+}
+
+#[test]
+fn test_dangling_references() -> Result<(), Error<usize>> {
+  let mut store = sample()?.with_integrity(Integrity::Unchecked);
+  store.delete_link(2)?;
+
+  let report = store.verify();
+  assert_eq!(report.links, 8);
+  assert_eq!(
+    report.problems,
+    [
+      Problem::Dangling { link: 4, reference: 2 },
+      Problem::Dangling { link: 6, reference: 2 },
+      Problem::Dangling { link: 7, reference: 2 },
+      Problem::Dangling { link: 9, reference: 2 },
+    ]
+  );
+  assert!(!report.is_repairable());
+  assert_eq!(
+    report.problems[0].to_string(),
+    "link 4 uses 2, which is not a link"
+  );
+
+  store.rebuild_indexes()?;
+  assert_eq!(store.verify(), report);
+  Ok(())
+}
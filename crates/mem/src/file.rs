@@ -115,6 +115,18 @@ impl<T: Pod> RawMem for FileMapped<T> {
 
     Ok(())
   }
+
+  fn stored(&self) -> Option<usize> {
+    let len = self.file.metadata().ok()?.len();
+    Some(len as usize / size_of::<T>())
+  }
+
+  fn grow_stored(&mut self, cap: usize) -> Result<&mut [Self::Item]> {
+    let page = self.grow(cap)?;
+    // SAFETY: the page maps bytes of the file, which are always
+    //  initialized, and `T: Pod` can be read from any bytes
+    Ok(unsafe { page.assumed() })
+  }
 }
 
 impl<T> Drop for FileMapped<T> {
@@ -68,6 +68,14 @@ macro_rules! memory {
         fn shrink(&mut self, cap: usize) -> Result<()> {
           self.0.shrink(cap)
         }
+
+        fn stored(&self) -> Option<usize> {
+          self.0.stored()
+        }
+
+        fn grow_stored(&mut self, cap: usize) -> Result<&mut [Self::Item]> {
+          self.0.grow_stored(cap)
+        }
       }
 
       impl<T> fmt::Debug for $name<$param> {
@@ -53,4 +53,14 @@ impl<T: Pod, P: Deref<Target = [T]> + DerefMut> RawMem for PreAlloc<P> {
     self.used = self.used.saturating_sub(cap);
     Ok(())
   }
+
+  fn stored(&self) -> Option<usize> {
+    Some(self.place.len())
+  }
+
+  fn grow_stored(&mut self, cap: usize) -> Result<&mut [Self::Item]> {
+    let page = self.grow(cap)?;
+    // SAFETY: the page was transmuted from an initialized `[T]`
+    Ok(unsafe { page.assumed() })
+  }
 }
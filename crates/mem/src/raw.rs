@@ -147,4 +147,22 @@ pub trait RawMem {
   fn grow(&mut self, cap: usize) -> Result<Page<'_, Self::Item>>;
 
   fn shrink(&mut self, cap: usize) -> Result<()>;
+
+  /// Items the backing storage already holds, whether grown into or not
+  ///
+  /// Growing up to this length and calling [`Page::assumed`] reads data
+  /// stored earlier, past it the storage is only zero-extended. `None`
+  /// for memory that keeps nothing of its own, like heap allocations.
+  fn stored(&self) -> Option<usize> {
+    None
+  }
+
+  /// [`grow`](Self::grow) exposing the items the storage already holds
+  ///
+  /// Memory with [`stored`](Self::stored) data hands it back as it is,
+  /// zero-extended past its end. The default zeroes the grown items,
+  /// which suits memory that keeps nothing of its own.
+  fn grow_stored(&mut self, cap: usize) -> Result<&mut [Self::Item]> {
+    Ok(self.grow(cap)?.zeroed())
+  }
 }
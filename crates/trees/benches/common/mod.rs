@@ -1,4 +1,8 @@
-use trees::{AdaptiveRadix, Idx, Node, SizeBalanced, Tree};
+use {
+  mem::Alloc,
+  std::marker::PhantomData,
+  trees::{AdaptiveRadix, Art, Idx, Node, SizeBalanced, Tree},
+};
 
 /// Vector-backed tree store for testing and benchmarking.
 /// Generic over the tree implementation strategy.
@@ -67,58 +71,31 @@ impl<T: Idx> Tree<T> for Store<T> {
 // SBT strategy
 impl<T: Idx> SizeBalanced<T> for Store<T> {}
 
-// ART strategy
-impl<T: Idx> AdaptiveRadix<T> for Store<T> {}
-
-/// ART-specific store that uses ART insert/remove by default
-#[derive(Debug, Clone)]
+/// ART-specific store keeping elements in a heap-allocated radix tree
+#[derive(Debug)]
 pub struct ArtStore<T> {
-  inner: Store<T>,
+  art: Art<Alloc<u64>>,
+  _marker: PhantomData<T>,
 }
 
 impl<T> ArtStore<T> {
-  pub fn new(capacity: usize) -> Self {
-    Self { inner: Store::new(capacity) }
+  pub fn new(_capacity: usize) -> Self {
+    Self { art: Art::default(), _marker: PhantomData }
   }
 
   pub fn reset(&mut self) {
-    self.inner.reset()
+    self.art.clear()
   }
 }
 
-impl<T: Idx> Tree<T> for ArtStore<T> {
-  #[inline(always)]
-  fn get(&self, idx: T) -> Option<Node<T>> {
-    self.inner.get(idx)
-  }
+impl<T: Idx> AdaptiveRadix<T> for ArtStore<T> {
+  type Mem = Alloc<u64>;
 
-  #[inline(always)]
-  fn set(&mut self, idx: T, node: Node<T>) {
-    self.inner.set(idx, node)
+  fn radix(&self) -> &Art<Self::Mem> {
+    &self.art
   }
 
-  #[inline(always)]
-  fn left_mut(&mut self, idx: T) -> Option<&mut T> {
-    self.inner.left_mut(idx)
-  }
-
-  #[inline(always)]
-  fn right_mut(&mut self, idx: T) -> Option<&mut T> {
-    self.inner.right_mut(idx)
-  }
-
-  #[inline(always)]
-  fn is_left_of(&self, first: T, second: T) -> bool {
-    self.inner.is_left_of(first, second)
-  }
-
-  fn insert(&mut self, root: Option<T>, idx: T) -> Option<T> {
-    self.insert_art(root, idx)
-  }
-
-  fn remove(&mut self, root: Option<T>, idx: T) -> Option<T> {
-    self.remove_art(root, idx)
+  fn radix_mut(&mut self) -> &mut Art<Self::Mem> {
+    &mut self.art
   }
 }
-
-impl<T: Idx> AdaptiveRadix<T> for ArtStore<T> {}
@@ -2,14 +2,21 @@ mod common;
 
 use {
   common::Store,
-  criterion::{Criterion, black_box, criterion_group, criterion_main},
-  trees::{Idx, Tree},
+  criterion::{Criterion, criterion_group, criterion_main},
+  std::hint::black_box,
+  trees::{AdaptiveRadix, Idx, Tree},
 };
 
-/// Trait for tree stores that can be reset and created
-trait BenchStore<T: Idx>: Tree<T> {
+/// Tree stores that can be created, reset and driven element by element
+///
+/// Binary trees thread their root through every call,
+/// radix trees keep it internally and ignore it.
+trait BenchStore<T: Idx> {
   fn new(capacity: usize) -> Self;
   fn reset(&mut self);
+  fn insert(&mut self, root: Option<T>, idx: T) -> Option<T>;
+  fn contains(&self, root: Option<T>, idx: T) -> bool;
+  fn remove(&mut self, root: Option<T>, idx: T) -> Option<T>;
 }
 
 impl<T: Idx> BenchStore<T> for Store<T> {
@@ -20,6 +27,18 @@ impl<T: Idx> BenchStore<T> for Store<T> {
   fn reset(&mut self) {
     Store::reset(self)
   }
+
+  fn insert(&mut self, root: Option<T>, idx: T) -> Option<T> {
+    Tree::insert(self, root, idx)
+  }
+
+  fn contains(&self, root: Option<T>, idx: T) -> bool {
+    Tree::contains(self, root.unwrap(), idx)
+  }
+
+  fn remove(&mut self, root: Option<T>, idx: T) -> Option<T> {
+    Tree::remove(self, root, idx)
+  }
 }
 
 impl<T: Idx> BenchStore<T> for common::ArtStore<T> {
@@ -30,6 +49,20 @@ impl<T: Idx> BenchStore<T> for common::ArtStore<T> {
   fn reset(&mut self) {
     common::ArtStore::reset(self)
   }
+
+  fn insert(&mut self, root: Option<T>, idx: T) -> Option<T> {
+    self.insert_art(idx).unwrap();
+    root
+  }
+
+  fn contains(&self, _root: Option<T>, idx: T) -> bool {
+    self.search_art(idx)
+  }
+
+  fn remove(&mut self, root: Option<T>, idx: T) -> Option<T> {
+    self.remove_art(idx);
+    root
+  }
 }
 
 // Helper function for insert benchmarks
@@ -62,7 +95,7 @@ where
       root = store.insert(root, T::from(i));
     }
     for i in 1..n {
-      black_box(store.contains(root.unwrap(), T::from(i)));
+      black_box(store.contains(root, T::from(i)));
     }
     store.reset();
   }
@@ -1,271 +1,989 @@
-use crate::{Idx, Node, Tree};
+use {
+  crate::{Idx, validate::Violation},
+  core::ops::ControlFlow,
+  mem::{RawMem, Result},
+  std::collections::HashSet,
+};
 
-/// Adaptive Radix Tree - provides operations on trie stored in slice
-///
-/// Extends the base Tree trait with adaptive radix tree operations.
-/// Uses byte-by-byte key decomposition with adaptive node sizing.
-///
-/// ART is optimized for main-memory indexing with:
-/// - O(k) search/insert/delete where k is key length (8 bytes for u64)
-/// - Adaptive node sizes (4, 16, 48, 256 children) for memory efficiency
-/// - Excellent cache locality through compact node structures
-/// - Path compression to reduce tree height
+/// Maximum key length in bytes supported by [`Art`]
+pub const MAX_KEY_LEN: usize = 32;
+
+/// Number of prefix bytes stored inline in inner nodes
 ///
-/// Node size field is repurposed to store node type information:
-/// - 0: Empty/unused node
-/// - 1-4: Node4 (up to 4 children)
-/// - 5-16: Node16 (up to 16 children)
-/// - 17-48: Node48 (up to 48 children)
-/// - 49-256: Node256 (up to 256 children)
-pub trait AdaptiveRadix<T: Idx>: Tree<T> {
-  /// Node type from size field
+/// Longer prefixes are compressed optimistically: only their length is
+/// trusted while descending and the full key is verified at the leaf.
+const MAX_PREFIX: usize = 8;
+
+/// Words taken by the header and inline prefix of every inner node
+const HEADER: usize = 2;
+
+/// Size class of leaves in the free lists (inner nodes use their tag)
+const LEAF_CLASS: usize = 0;
+
+/// Node type in Adaptive Radix Tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeType {
+  Node4,
+  Node16,
+  Node48,
+  Node256,
+}
+
+impl NodeType {
+  /// Smallest node type able to hold `children` children
   #[inline]
-  fn node_type(&self, idx: T) -> Option<NodeType> {
-    let size = self.get(idx)?.size;
-    NodeType::from_size(size)
+  pub fn from_size(children: usize) -> Option<Self> {
+    match children {
+      0..=4 => Some(Self::Node4),
+      5..=16 => Some(Self::Node16),
+      17..=48 => Some(Self::Node48),
+      49..=256 => Some(Self::Node256),
+      _ => None,
+    }
   }
 
-  /// Set node type by updating size field
+  /// Maximum number of children
   #[inline]
-  fn set_node_type(&mut self, idx: T, node_type: NodeType) {
-    if let Some(node) = self.get(idx) {
-      self.set(idx, Node { size: node_type.to_size(), ..node });
+  pub fn capacity(self) -> usize {
+    match self {
+      Self::Node4 => 4,
+      Self::Node16 => 16,
+      Self::Node48 => 48,
+      Self::Node256 => 256,
     }
   }
 
-  /// Number of children from node
+  /// Next larger node type
   #[inline]
-  fn child_count(&self, idx: T) -> usize {
-    self.get(idx).map(|n| n.size).unwrap_or(0)
+  fn grown(self) -> Option<Self> {
+    match self {
+      Self::Node4 => Some(Self::Node16),
+      Self::Node16 => Some(Self::Node48),
+      Self::Node48 => Some(Self::Node256),
+      Self::Node256 => None,
+    }
   }
 
-  /// Extract byte from key at given depth (0 = most significant)
+  /// Smaller node type to switch to once `count` children remain
   ///
-  /// Platform independence: assumes usize is 64 bits (8 bytes) as the shift
-  /// calculation uses `(7 - depth % 8)`. On 32-bit platforms, this will still
-  /// work correctly as the depth is clamped by the modulo operation.
+  /// Thresholds sit below the growth points so that a node oscillating
+  /// around a boundary is not copied back and forth.
   #[inline]
-  fn key_byte(&self, key: T, depth: usize) -> u8 {
-    let key_usize = key.as_usize();
-    // extract bytes in big-endian order for proper lexicographic sorting
-    let shift = (7 - (depth % 8)) * 8;
-    ((key_usize >> shift) & 0xFF) as u8
+  fn shrunk(self, count: usize) -> Option<Self> {
+    match self {
+      Self::Node16 if count <= 3 => Some(Self::Node4),
+      Self::Node48 if count <= 12 => Some(Self::Node16),
+      Self::Node256 if count <= 37 => Some(Self::Node48),
+      _ => None,
+    }
   }
 
-  /// Find child with given byte value
-  fn find_child(&self, idx: T, byte: u8) -> Option<T> {
-    let node_type = self.node_type(idx)?;
-    match node_type {
-      NodeType::Node4 | NodeType::Node16 => {
-        // for small nodes, stored in left/right alternating pattern
-        // simplified implementation - production uses dedicated storage
-        if byte < 128 { self.left(idx) } else { self.right(idx) }
-      }
-      NodeType::Node48 | NodeType::Node256 => {
-        // for large nodes, use hash-based selection
-        if byte.is_multiple_of(2) { self.left(idx) } else { self.right(idx) }
-      }
-      NodeType::Empty => None,
+  #[inline]
+  fn tag(self) -> usize {
+    match self {
+      Self::Node4 => 1,
+      Self::Node16 => 2,
+      Self::Node48 => 3,
+      Self::Node256 => 4,
     }
   }
 
-  /// Insert child at given byte value
-  fn insert_child(&mut self, idx: T, byte: u8, child: T) -> bool {
-    let _node_type = self.node_type(idx).unwrap_or(NodeType::Empty);
-
-    // simple implementation: use left for lower bytes, right for higher
-    if byte < 128 {
-      self.set_left(idx, Some(child));
-    } else {
-      self.set_right(idx, Some(child));
+  #[inline]
+  fn from_tag(tag: usize) -> Self {
+    match tag {
+      1 => Self::Node4,
+      2 => Self::Node16,
+      3 => Self::Node48,
+      _ => Self::Node256,
     }
+  }
 
-    // update node type if needed
-    let new_count = self.child_count(idx) + 1;
-    if let Some(new_type) = NodeType::from_size(new_count) {
-      self.set_node_type(idx, new_type);
+  /// Words for the key bytes (or the child index of Node48)
+  #[inline]
+  fn key_words(self) -> usize {
+    match self {
+      Self::Node4 => 1,
+      Self::Node16 => 2,
+      Self::Node48 => 32,
+      Self::Node256 => 0,
     }
+  }
+
+  /// Total size of the node in words
+  #[inline]
+  fn words(self) -> usize {
+    HEADER + self.key_words() + self.capacity()
+  }
+}
+
+/// Reference to a node: word offset shifted left, low bit marks leaves
+///
+/// Offset `0` is reserved, so the null reference is `0`.
+type Ref = u64;
+
+#[inline]
+fn leaf_ref(at: usize) -> Ref {
+  ((at as u64) << 1) | 1
+}
+
+#[inline]
+fn inner_ref(at: usize) -> Ref {
+  (at as u64) << 1
+}
+
+#[inline]
+fn is_leaf(node: Ref) -> bool {
+  node & 1 == 1
+}
+
+#[inline]
+fn offset(node: Ref) -> usize {
+  (node >> 1) as usize
+}
+
+/// Adaptive Radix Tree over fixed-length byte keys
+///
+/// Nodes live in their own [`RawMem`] arena of `u64` words:
+/// - inner nodes adapt between Node4, Node16, Node48 and Node256 as
+///   children are added and removed
+/// - common key prefixes are compressed into inner nodes (path compression)
+/// - leaves hang directly below the first distinguishing byte and keep the
+///   full key (lazy expansion), so lookups are exact
+///
+/// Search, insert and remove cost O(k) where k is the key length.
+/// Children are kept in byte order, so traversal yields keys in
+/// lexicographic order.
+///
+/// # Examples
+/// ```
+/// use {mem::Alloc, trees::Art};
+///
+/// let mut art = Art::new(Alloc::new(), 8);
+/// art.insert(&42u64.to_be_bytes(), 1).unwrap();
+///
+/// assert_eq!(art.get(&42u64.to_be_bytes()), Some(1));
+/// assert_eq!(art.remove(&42u64.to_be_bytes()), Some(1));
+/// assert!(art.is_empty());
+/// ```
+pub struct Art<M> {
+  mem: M,
+  key_len: usize,
+  root: Ref,
+  len: usize,
+  /// Words of the arena in use, the rest is spare capacity
+  used: usize,
+  /// Heads of free lists for leaves and each inner node type
+  free: [usize; 5],
+}
+
+impl<M: RawMem<Item = u64>> Art<M> {
+  /// Create an empty tree for keys of exactly `key_len` bytes
+  ///
+  /// # Panics
+  ///
+  /// Panics if `key_len` is zero or exceeds [`MAX_KEY_LEN`].
+  pub fn new(mem: M, key_len: usize) -> Self {
+    assert!(
+      (1..=MAX_KEY_LEN).contains(&key_len),
+      "key length must be in 1..={MAX_KEY_LEN}"
+    );
+    Self { mem, key_len, root: 0, len: 0, used: 0, free: [0; 5] }
+  }
+
+  /// Length of keys in bytes
+  #[inline]
+  pub fn key_len(&self) -> usize {
+    self.key_len
+  }
+
+  /// Number of keys in the tree
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Check if the tree holds no keys
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Type of the root node, `None` if the tree is empty or a single leaf
+  pub fn root_type(&self) -> Option<NodeType> {
+    (self.root != 0 && !is_leaf(self.root))
+      .then(|| self.header(offset(self.root)).0)
+  }
 
-    true
+  /// Remove all keys, keeping the arena memory for reuse
+  pub fn clear(&mut self) {
+    self.mem.as_mut_slice().fill(0);
+    self.root = 0;
+    self.len = 0;
+    self.used = 0;
+    self.free = [0; 5];
   }
 
-  /// Search for key in ART tree
-  fn search_art(&self, root: T, key: T) -> bool {
-    let mut current = root;
+  /// Get the value stored under `key`
+  pub fn get(&self, key: &[u8]) -> Option<u64> {
+    self.check_key(key);
+
+    let mut node = self.root;
     let mut depth = 0;
+    while node != 0 {
+      if is_leaf(node) {
+        let at = offset(node);
+        return self.leaf_matches(at, key).then(|| self.word(at));
+      }
+
+      let at = offset(node);
+      let (_, _, prefix_len) = self.header(at);
+      // Only inline prefix bytes are checked, the leaf verifies the rest
+      for i in 0..prefix_len.min(MAX_PREFIX) {
+        if self.byte(at + 1, i) != key[depth + i] {
+          return None;
+        }
+      }
+      depth += prefix_len;
+      if depth >= self.key_len {
+        return None;
+      }
+
+      let slot = self.find_child(at, key[depth])?;
+      node = self.word(slot);
+      depth += 1;
+    }
+    None
+  }
 
+  /// Check if `key` is present
+  #[inline]
+  pub fn contains(&self, key: &[u8]) -> bool {
+    self.get(key).is_some()
+  }
+
+  /// Insert `value` under `key`, returning the previous value if any
+  pub fn insert(&mut self, key: &[u8], value: u64) -> Result<Option<u64>> {
+    self.check_key(key);
+
+    // Slot `0` stands for the root reference
+    let mut slot = 0;
+    let mut depth = 0;
     loop {
-      // check if we've found the exact key
-      if current == key {
-        return true;
+      let node = self.load(slot);
+
+      if node == 0 {
+        let leaf = self.alloc_leaf(key, value)?;
+        self.store(slot, leaf);
+        self.len += 1;
+        return Ok(None);
       }
 
-      let byte = self.key_byte(key, depth);
+      if is_leaf(node) {
+        let at = offset(node);
+        if self.leaf_matches(at, key) {
+          let old = self.word(at);
+          self.set_word(at, value);
+          return Ok(Some(old));
+        }
 
-      match self.find_child(current, byte) {
-        Some(next) => {
-          current = next;
-          depth += 1;
-          // prevent infinite loops - max depth for u64 is 8 bytes
-          if depth > 8 {
-            return false;
-          }
+        // Split: both leaves go below a Node4 holding their common prefix
+        let mut common = 0;
+        while self.leaf_byte(at, depth + common) == key[depth + common] {
+          common += 1;
         }
-        None => return false,
+        let (branch, leaf) = self.alloc_branch(key, value)?;
+        self.set_prefix(branch, common, |i| key[depth + i]);
+        let existing = self.leaf_byte(at, depth + common);
+        self.add_child(branch, existing, node);
+        self.add_child(branch, key[depth + common], leaf);
+        self.store(slot, inner_ref(branch));
+        self.len += 1;
+        return Ok(None);
       }
+
+      let at = offset(node);
+      let (_, _, prefix_len) = self.header(at);
+      if prefix_len > 0 {
+        let mismatch = self.prefix_mismatch(at, key, depth);
+        if mismatch < prefix_len {
+          self.split_prefix(slot, at, key, value, depth, mismatch)?;
+          self.len += 1;
+          return Ok(None);
+        }
+        depth += prefix_len;
+      }
+
+      if let Some(child) = self.find_child(at, key[depth]) {
+        slot = child;
+        depth += 1;
+        continue;
+      }
+
+      let at = self.reserve_child(slot, at)?;
+      let leaf = self.alloc_leaf(key, value)?;
+      self.add_child(at, key[depth], leaf);
+      self.len += 1;
+      return Ok(None);
     }
   }
 
-  /// Insert key into ART tree, returns new root
-  fn insert_art(&mut self, root: Option<T>, key: T) -> Option<T> {
-    let root_idx = match root {
-      Some(idx) => idx,
-      None => {
-        // empty tree - key becomes root
-        self.set_node_type(key, NodeType::Node4);
-        return Some(key);
+  /// Remove `key`, returning its value if it was present
+  pub fn remove(&mut self, key: &[u8]) -> Option<u64> {
+    self.check_key(key);
+
+    let mut slot = 0;
+    let mut depth = 0;
+    loop {
+      let node = self.load(slot);
+      if node == 0 {
+        return None;
       }
-    };
 
-    let mut current = root_idx;
+      if is_leaf(node) {
+        // Only reachable for a root leaf, inner leaves are handled below
+        let at = offset(node);
+        if !self.leaf_matches(at, key) {
+          return None;
+        }
+        let value = self.word(at);
+        self.store(slot, 0);
+        self.release(at, LEAF_CLASS, self.leaf_words());
+        self.len -= 1;
+        return Some(value);
+      }
+
+      let at = offset(node);
+      let (_, _, prefix_len) = self.header(at);
+      for i in 0..prefix_len.min(MAX_PREFIX) {
+        if self.byte(at + 1, i) != key[depth + i] {
+          return None;
+        }
+      }
+      depth += prefix_len;
+      if depth >= self.key_len {
+        return None;
+      }
+
+      let byte = key[depth];
+      let child_slot = self.find_child(at, byte)?;
+      let child = self.word(child_slot);
+
+      if is_leaf(child) {
+        let leaf = offset(child);
+        if !self.leaf_matches(leaf, key) {
+          return None;
+        }
+        let value = self.word(leaf);
+        self.remove_child(slot, at, byte);
+        self.release(leaf, LEAF_CLASS, self.leaf_words());
+        self.len -= 1;
+        return Some(value);
+      }
+
+      slot = child_slot;
+      depth += 1;
+    }
+  }
+
+  /// Call `f` with every key and value in lexicographic key order
+  pub fn for_each(&self, mut f: impl FnMut(&[u8], u64)) {
+    let _ = self.scan_prefix(&[], |key, value| {
+      f(key, value);
+      ControlFlow::<()>::Continue(())
+    });
+  }
+
+  /// Call `f` with keys starting with `prefix` in lexicographic order
+  ///
+  /// The walk descends along `prefix` and visits only the matching
+  /// subtree, stopping early once `f` breaks.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `prefix` is longer than the key length.
+  pub fn scan_prefix<B>(
+    &self,
+    prefix: &[u8],
+    mut f: impl FnMut(&[u8], u64) -> ControlFlow<B>,
+  ) -> ControlFlow<B> {
+    assert!(prefix.len() <= self.key_len, "prefix is longer than keys");
+
+    let mut node = self.root;
     let mut depth = 0;
+    while node != 0 && !is_leaf(node) {
+      let at = offset(node);
+      let (_, _, prefix_len) = self.header(at);
+      let inline = prefix_len.min(MAX_PREFIX).min(prefix.len() - depth);
+      for i in 0..inline {
+        if self.byte(at + 1, i) != prefix[depth + i] {
+          return ControlFlow::Continue(());
+        }
+      }
+      depth += prefix_len;
+      if depth >= prefix.len() {
+        break;
+      }
+      match self.find_child(at, prefix[depth]) {
+        Some(slot) => node = self.word(slot),
+        None => return ControlFlow::Continue(()),
+      }
+      depth += 1;
+    }
 
-    loop {
-      let byte = self.key_byte(key, depth);
-
-      match self.find_child(current, byte) {
-        Some(next) => {
-          if next == key {
-            // key already exists
-            return Some(root_idx);
-          }
-          current = next;
-          depth += 1;
-          if depth > 8 {
-            break;
-          }
+    // Bytes skipped by optimistic prefixes are checked on every leaf
+    let mut key = [0; MAX_KEY_LEN];
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+      if node == 0 {
+        continue;
+      }
+      if is_leaf(node) {
+        let at = offset(node);
+        for (i, byte) in key[..self.key_len].iter_mut().enumerate() {
+          *byte = self.leaf_byte(at, i);
         }
-        None => {
-          // insert new child
-          self.set_node_type(key, NodeType::Node4);
-          self.insert_child(current, byte, key);
-          return Some(root_idx);
+        if key.starts_with(prefix) {
+          f(&key[..self.key_len], self.word(at))?;
         }
+        continue;
+      }
+      // Push in reverse so the smallest child is visited first
+      stack.extend(self.children(offset(node)).rev().map(|(_, child)| child));
+    }
+    ControlFlow::Continue(())
+  }
+
+  /// Walk every node below the root, returning the number of leaves
+  ///
+  /// Fails on the first node that lies outside the arena, is reached
+  /// twice, or whose header disagrees with its children.
+  pub(crate) fn validate_nodes<T>(
+    &self,
+  ) -> core::result::Result<usize, Violation<T>> {
+    let mut seen = HashSet::new();
+    let mut leaves = 0;
+    let mut stack: Vec<_> =
+      (self.root != 0).then_some((self.root, 0)).into_iter().collect();
+    while let Some((node, parent)) = stack.pop() {
+      let at = offset(node);
+      let words = if is_leaf(node) {
+        self.leaf_words()
+      } else {
+        self.header(at).0.words()
+      };
+      if at == 0 || at + words > self.used {
+        return Err(Violation::RadixOutOfBounds { node: at, parent });
+      }
+      if !seen.insert(at) {
+        return Err(Violation::RadixRevisited { node: at });
+      }
+      if is_leaf(node) {
+        leaves += 1;
+        continue;
+      }
+
+      let (_, count, _) = self.header(at);
+      let children: Vec<_> = self.children(at).collect();
+      if children.len() != count {
+        let actual = children.len();
+        return Err(Violation::RadixChildCount {
+          node: at,
+          stored: count,
+          actual,
+        });
+      }
+      // Removing the second to last child collapses a node into its parent
+      if count < 2 {
+        return Err(Violation::RadixUnderfull { node: at });
       }
+      stack.extend(children.into_iter().rev().map(|(_, child)| (child, at)));
+    }
+    Ok(leaves)
+  }
+
+  #[inline]
+  fn check_key(&self, key: &[u8]) {
+    assert_eq!(key.len(), self.key_len, "key length mismatch");
+  }
+
+  #[inline]
+  fn word(&self, at: usize) -> u64 {
+    self.mem.as_slice()[at]
+  }
+
+  #[inline]
+  fn set_word(&mut self, at: usize, value: u64) {
+    self.mem.as_mut_slice()[at] = value;
+  }
+
+  /// Byte `i` of the byte array starting at word `at`
+  #[inline]
+  fn byte(&self, at: usize, i: usize) -> u8 {
+    (self.word(at + i / 8) >> ((i % 8) * 8)) as u8
+  }
+
+  #[inline]
+  fn set_byte(&mut self, at: usize, i: usize, byte: u8) {
+    let shift = (i % 8) * 8;
+    let word = self.word(at + i / 8) & !(0xFF << shift);
+    self.set_word(at + i / 8, word | ((byte as u64) << shift));
+  }
+
+  /// Load the reference stored in `slot` (`0` is the root)
+  #[inline]
+  fn load(&self, slot: usize) -> Ref {
+    if slot == 0 { self.root } else { self.word(slot) }
+  }
+
+  #[inline]
+  fn store(&mut self, slot: usize, node: Ref) {
+    if slot == 0 {
+      self.root = node;
+    } else {
+      self.set_word(slot, node);
+    }
+  }
+
+  #[inline]
+  fn leaf_words(&self) -> usize {
+    1 + self.key_len.div_ceil(8)
+  }
+
+  #[inline]
+  fn leaf_byte(&self, at: usize, i: usize) -> u8 {
+    self.byte(at + 1, i)
+  }
+
+  fn leaf_matches(&self, at: usize, key: &[u8]) -> bool {
+    key.iter().enumerate().all(|(i, &byte)| self.leaf_byte(at, i) == byte)
+  }
+
+  /// Node type, child count and full prefix length of an inner node
+  #[inline]
+  fn header(&self, at: usize) -> (NodeType, usize, usize) {
+    let word = self.word(at);
+    let kind = NodeType::from_tag((word & 0xFF) as usize);
+    let count = ((word >> 8) & 0xFFFF) as usize;
+    let prefix_len = (word >> 32) as usize;
+    (kind, count, prefix_len)
+  }
+
+  #[inline]
+  fn set_header(
+    &mut self,
+    at: usize,
+    kind: NodeType,
+    count: usize,
+    prefix_len: usize,
+  ) {
+    let word =
+      kind.tag() as u64 | ((count as u64) << 8) | ((prefix_len as u64) << 32);
+    self.set_word(at, word);
+  }
+
+  /// Set the prefix of a node, storing up to `MAX_PREFIX` bytes inline
+  fn set_prefix(&mut self, at: usize, len: usize, byte: impl Fn(usize) -> u8) {
+    let (kind, count, _) = self.header(at);
+    self.set_header(at, kind, count, len);
+    for i in 0..len.min(MAX_PREFIX) {
+      self.set_byte(at + 1, i, byte(i));
+    }
+  }
+
+  /// Take words from the free list of `class` or from the arena end
+  fn alloc(&mut self, class: usize, words: usize) -> Result<usize> {
+    let head = self.free[class];
+    if head != 0 {
+      self.free[class] = self.word(head) as usize;
+      self.mem.as_mut_slice()[head..head + words].fill(0);
+      return Ok(head);
     }
 
-    Some(root_idx)
+    // Word `0` is reserved for the null reference
+    let at = self.used.max(1);
+    let needed = at + words;
+    let len = self.mem.as_slice().len();
+    if needed > len {
+      // Grow geometrically to keep reallocations amortized
+      self.mem.grow((needed - len).max(len))?.zeroed();
+    }
+    self.used = needed;
+    Ok(at)
   }
 
-  /// Remove key from ART tree, returns new root
-  fn remove_art(&mut self, root: Option<T>, key: T) -> Option<T> {
-    let root_idx = root?;
+  /// Return a node to the free list of its size class
+  fn release(&mut self, at: usize, class: usize, words: usize) {
+    self.mem.as_mut_slice()[at..at + words].fill(0);
+    self.set_word(at, self.free[class] as u64);
+    self.free[class] = at;
+  }
 
-    // handle root removal
-    if root_idx == key {
-      self.clear(key);
-      return None;
+  fn alloc_leaf(&mut self, key: &[u8], value: u64) -> Result<Ref> {
+    let at = self.alloc(LEAF_CLASS, self.leaf_words())?;
+    self.set_word(at, value);
+    for (i, &byte) in key.iter().enumerate() {
+      self.set_byte(at + 1, i, byte);
     }
+    Ok(leaf_ref(at))
+  }
 
-    // traverse tree to find key and its parent
-    self.remove_art_impl(root_idx, key, 0);
-    Some(root_idx)
+  fn alloc_node(&mut self, kind: NodeType) -> Result<usize> {
+    let at = self.alloc(kind.tag(), kind.words())?;
+    self.set_header(at, kind, 0, 0);
+    Ok(at)
   }
 
-  /// Internal remove implementation with parent tracking
-  fn remove_art_impl(&mut self, current: T, key: T, depth: usize) -> bool {
-    if depth > 8 {
-      return false;
+  /// Allocate the Node4 and leaf of a split, or neither of them
+  fn alloc_branch(&mut self, key: &[u8], value: u64) -> Result<(usize, Ref)> {
+    let kind = NodeType::Node4;
+    let branch = self.alloc_node(kind)?;
+    match self.alloc_leaf(key, value) {
+      Ok(leaf) => Ok((branch, leaf)),
+      Err(err) => {
+        self.release(branch, kind.tag(), kind.words());
+        Err(err)
+      }
     }
+  }
 
-    let byte = self.key_byte(key, depth);
+  /// Leftmost leaf below an inner node
+  fn min_leaf(&self, mut node: Ref) -> usize {
+    while !is_leaf(node) {
+      let (_, child) = self
+        .children(offset(node))
+        .next()
+        .expect("inner nodes always have children");
+      node = child;
+    }
+    offset(node)
+  }
 
-    match self.find_child(current, byte) {
-      Some(next) => {
-        if next == key {
-          // found the key - remove child pointer from parent
-          self.remove_child(current, byte);
-          self.clear(key);
-          return true;
+  /// Length of the prefix of node `at` matching `key` from `depth`
+  fn prefix_mismatch(&self, at: usize, key: &[u8], depth: usize) -> usize {
+    let (_, _, prefix_len) = self.header(at);
+    let inline = prefix_len.min(MAX_PREFIX);
+    for i in 0..inline {
+      if self.byte(at + 1, i) != key[depth + i] {
+        return i;
+      }
+    }
+
+    if prefix_len > MAX_PREFIX {
+      // Bytes past the inline part are recovered from any leaf below
+      let leaf = self.min_leaf(inner_ref(at));
+      for i in inline..prefix_len {
+        if self.leaf_byte(leaf, depth + i) != key[depth + i] {
+          return i;
         }
-        // recursively search in child
-        self.remove_art_impl(next, key, depth + 1)
       }
-      None => false,
     }
+    prefix_len
   }
 
-  /// Remove child at given byte value from parent node
-  fn remove_child(&mut self, parent: T, byte: u8) {
-    let node_type = self.node_type(parent).unwrap_or(NodeType::Empty);
+  /// Split the compressed prefix of node `at` where `key` diverges
+  fn split_prefix(
+    &mut self,
+    slot: usize,
+    at: usize,
+    key: &[u8],
+    value: u64,
+    depth: usize,
+    mismatch: usize,
+  ) -> Result<()> {
+    let (kind, count, prefix_len) = self.header(at);
+    let (branch, leaf) = self.alloc_branch(key, value)?;
+    self.set_prefix(branch, mismatch, |i| key[depth + i]);
+
+    // The old node keeps what follows the diverging byte
+    let rest = prefix_len - mismatch - 1;
+    let edge = if prefix_len <= MAX_PREFIX {
+      let edge = self.byte(at + 1, mismatch);
+      for i in 0..rest {
+        let byte = self.byte(at + 1, mismatch + 1 + i);
+        self.set_byte(at + 1, i, byte);
+      }
+      self.set_header(at, kind, count, rest);
+      edge
+    } else {
+      let leaf = self.min_leaf(inner_ref(at));
+      let edge = self.leaf_byte(leaf, depth + mismatch);
+      let start = depth + mismatch + 1;
+      self.set_header(at, kind, count, rest);
+      for i in 0..rest.min(MAX_PREFIX) {
+        let byte = self.leaf_byte(leaf, start + i);
+        self.set_byte(at + 1, i, byte);
+      }
+      edge
+    };
+
+    self.add_child(branch, edge, inner_ref(at));
+    self.add_child(branch, key[depth + mismatch], leaf);
+    self.store(slot, inner_ref(branch));
+    Ok(())
+  }
+
+  /// Find the slot holding the child for `byte`
+  fn find_child(&self, at: usize, byte: u8) -> Option<usize> {
+    let (kind, count, _) = self.header(at);
+    let keys = at + HEADER;
+    let children = keys + kind.key_words();
+    match kind {
+      NodeType::Node4 | NodeType::Node16 => {
+        (0..count).find(|&i| self.byte(keys, i) == byte).map(|i| children + i)
+      }
+      NodeType::Node48 => match self.byte(keys, byte as usize) {
+        0 => None,
+        pos => Some(children + pos as usize - 1),
+      },
+      NodeType::Node256 => {
+        let slot = children + byte as usize;
+        (self.word(slot) != 0).then_some(slot)
+      }
+    }
+  }
+
+  /// Children of an inner node in byte order
+  fn children(
+    &self,
+    at: usize,
+  ) -> impl DoubleEndedIterator<Item = (u8, Ref)> + '_ {
+    let (kind, count, _) = self.header(at);
+    let keys = at + HEADER;
+    let children = keys + kind.key_words();
+    let bytes = match kind {
+      NodeType::Node4 | NodeType::Node16 => 0..count,
+      NodeType::Node48 | NodeType::Node256 => 0..256,
+    };
+    bytes.filter_map(move |i| match kind {
+      NodeType::Node4 | NodeType::Node16 => {
+        Some((self.byte(keys, i), self.word(children + i)))
+      }
+      NodeType::Node48 => match self.byte(keys, i) {
+        0 => None,
+        pos => Some((i as u8, self.word(children + pos as usize - 1))),
+      },
+      NodeType::Node256 => {
+        let child = self.word(children + i);
+        (child != 0).then_some((i as u8, child))
+      }
+    })
+  }
 
-    // search for and remove the child based on node type strategy
-    match node_type {
+  /// Add a child to a node that has room for it
+  fn add_child(&mut self, at: usize, byte: u8, child: Ref) {
+    let (kind, count, prefix_len) = self.header(at);
+    let keys = at + HEADER;
+    let children = keys + kind.key_words();
+    match kind {
       NodeType::Node4 | NodeType::Node16 => {
-        // for small nodes, check which pointer contains the target
-        // and clear only that specific pointer
-        if byte < 128 {
-          if self.left(parent).is_some() {
-            self.set_left(parent, None);
-          }
-        } else if self.right(parent).is_some() {
-          self.set_right(parent, None);
+        // Keep keys sorted so that children are visited in byte order
+        let pos =
+          (0..count).find(|&i| self.byte(keys, i) > byte).unwrap_or(count);
+        for i in (pos..count).rev() {
+          let key = self.byte(keys, i);
+          self.set_byte(keys, i + 1, key);
+          self.set_word(children + i + 1, self.word(children + i));
         }
+        self.set_byte(keys, pos, byte);
+        self.set_word(children + pos, child);
+      }
+      NodeType::Node48 => {
+        let pos = (0..48)
+          .find(|&i| self.word(children + i) == 0)
+          .expect("node has room for a child");
+        self.set_byte(keys, byte as usize, pos as u8 + 1);
+        self.set_word(children + pos, child);
       }
-      NodeType::Node48 | NodeType::Node256 => {
-        // for large nodes, use hash-based selection matching find_child
-        if byte.is_multiple_of(2) {
-          if self.left(parent).is_some() {
-            self.set_left(parent, None);
-          }
-        } else if self.right(parent).is_some() {
-          self.set_right(parent, None);
+      NodeType::Node256 => {
+        self.set_word(children + byte as usize, child);
+      }
+    }
+    self.set_header(at, kind, count + 1, prefix_len);
+  }
+
+  /// Make room for one more child, growing the node if it is full
+  ///
+  /// Returns the offset of the node that should receive the child.
+  fn reserve_child(&mut self, slot: usize, at: usize) -> Result<usize> {
+    let (kind, count, _) = self.header(at);
+    match kind.grown() {
+      Some(grown) if count == kind.capacity() => self.resize(slot, at, grown),
+      _ => Ok(at),
+    }
+  }
+
+  /// Copy a node into a node of another type and free the old one
+  fn resize(
+    &mut self,
+    slot: usize,
+    at: usize,
+    kind: NodeType,
+  ) -> Result<usize> {
+    let (old, _, prefix_len) = self.header(at);
+    let children: Vec<_> = self.children(at).collect();
+
+    let new = self.alloc_node(kind)?;
+    self.set_header(new, kind, 0, prefix_len);
+    self.set_word(new + 1, self.word(at + 1));
+    for (byte, child) in children {
+      self.add_child(new, byte, child);
+    }
+
+    self.release(at, old.tag(), old.words());
+    self.store(slot, inner_ref(new));
+    Ok(new)
+  }
+
+  /// Remove the child for `byte`, shrinking or collapsing the node
+  fn remove_child(&mut self, slot: usize, at: usize, byte: u8) {
+    let (kind, count, prefix_len) = self.header(at);
+    let keys = at + HEADER;
+    let children = keys + kind.key_words();
+    match kind {
+      NodeType::Node4 | NodeType::Node16 => {
+        let pos = (0..count)
+          .find(|&i| self.byte(keys, i) == byte)
+          .expect("child exists");
+        for i in pos..count - 1 {
+          let key = self.byte(keys, i + 1);
+          self.set_byte(keys, i, key);
+          self.set_word(children + i, self.word(children + i + 1));
         }
+        self.set_byte(keys, count - 1, 0);
+        self.set_word(children + count - 1, 0);
+      }
+      NodeType::Node48 => {
+        let pos = self.byte(keys, byte as usize) as usize;
+        self.set_byte(keys, byte as usize, 0);
+        self.set_word(children + pos - 1, 0);
+      }
+      NodeType::Node256 => {
+        self.set_word(children + byte as usize, 0);
       }
-      NodeType::Empty => return, // nothing to remove
     }
+    let count = count - 1;
+    self.set_header(at, kind, count, prefix_len);
 
-    // update node type based on new child count
-    let new_count = self.child_count(parent).saturating_sub(1);
-    if let Some(new_type) = NodeType::from_size(new_count) {
-      self.set_node_type(parent, new_type);
+    if kind == NodeType::Node4 && count == 1 {
+      self.collapse(slot, at);
+    } else if let Some(shrunk) = kind.shrunk(count) {
+      // Shrinking only allocates from a free list or the arena end;
+      // on failure the node simply keeps its larger type
+      let _ = self.resize(slot, at, shrunk);
     }
   }
+
+  /// Replace a Node4 with its only child, merging compressed prefixes
+  fn collapse(&mut self, slot: usize, at: usize) {
+    let (_, _, prefix_len) = self.header(at);
+    let (edge, child) = self.children(at).next().expect("one child remains");
+
+    if !is_leaf(child) {
+      // New prefix: node prefix + edge byte + child prefix
+      let inner = offset(child);
+      let (kind, count, child_len) = self.header(inner);
+      let mut bytes = [0; MAX_PREFIX];
+      let own = prefix_len.min(MAX_PREFIX);
+      for (i, byte) in bytes[..own].iter_mut().enumerate() {
+        *byte = self.byte(at + 1, i);
+      }
+      if own < MAX_PREFIX {
+        bytes[own] = edge;
+        let from_child = (MAX_PREFIX - own - 1).min(child_len);
+        for i in 0..from_child {
+          bytes[own + 1 + i] = self.byte(inner + 1, i);
+        }
+      }
+      self.set_header(inner, kind, count, prefix_len + 1 + child_len);
+      for (i, &byte) in bytes.iter().enumerate() {
+        self.set_byte(inner + 1, i, byte);
+      }
+    }
+
+    self.store(slot, child);
+    self.release(at, NodeType::Node4.tag(), NodeType::Node4.words());
+  }
 }
 
-/// Node type in Adaptive Radix Tree
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum NodeType {
-  Empty,
-  Node4,
-  Node16,
-  Node48,
-  Node256,
+impl<M: RawMem<Item = u64> + Default> Default for Art<M> {
+  /// Empty tree keyed by `usize` indices in big-endian order
+  fn default() -> Self {
+    Self::new(M::default(), size_of::<usize>())
+  }
 }
 
-impl NodeType {
-  /// Convert from size field value
-  #[inline]
-  pub fn from_size(size: usize) -> Option<Self> {
-    match size {
-      0 => Some(Self::Empty),
-      1..=4 => Some(Self::Node4),
-      5..=16 => Some(Self::Node16),
-      17..=48 => Some(Self::Node48),
-      49..=256 => Some(Self::Node256),
-      _ => None,
-    }
+impl<M> core::fmt::Debug for Art<M> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("Art")
+      .field("key_len", &self.key_len)
+      .field("len", &self.len)
+      .field("used", &self.used)
+      .finish()
   }
+}
 
-  /// Convert to size field value
-  #[inline]
-  pub fn to_size(self) -> usize {
-    match self {
-      Self::Empty => 0,
-      Self::Node4 => 4,
-      Self::Node16 => 16,
-      Self::Node48 => 48,
-      Self::Node256 => 256,
-    }
+/// Adaptive Radix Tree indexing elements of a store
+///
+/// Bridges element indices to an [`Art`] kept in its own memory:
+/// implementors provide the tree and derive a fixed-length key for every
+/// element. The element index is stored as the value, so lookups are exact.
+pub trait AdaptiveRadix<T: Idx> {
+  /// Memory backing the radix tree nodes
+  type Mem: RawMem<Item = u64>;
+
+  /// Radix tree holding the elements
+  fn radix(&self) -> &Art<Self::Mem>;
+
+  /// Mutable radix tree holding the elements
+  fn radix_mut(&mut self) -> &mut Art<Self::Mem>;
+
+  /// Write the key of `idx` into `key` (of the tree's key length)
+  ///
+  /// Defaults to the big-endian bytes of the index,
+  /// which orders keys like the indices themselves.
+  fn radix_key(&self, idx: T, key: &mut [u8]) {
+    let bytes = (idx.as_usize() as u64).to_be_bytes();
+    let len = key.len().min(bytes.len());
+    let start = key.len() - len;
+    key.fill(0);
+    key[start..].copy_from_slice(&bytes[bytes.len() - len..]);
+  }
+
+  /// Search for an element in the tree
+  fn search_art(&self, idx: T) -> bool {
+    let mut buf = [0; MAX_KEY_LEN];
+    let key = &mut buf[..self.radix().key_len()];
+    self.radix_key(idx, key);
+    self.radix().get(key) == Some(idx.as_usize() as u64)
+  }
+
+  /// Insert an element, returns `false` if its key was already present
+  fn insert_art(&mut self, idx: T) -> Result<bool> {
+    let mut buf = [0; MAX_KEY_LEN];
+    let key = &mut buf[..self.radix().key_len()];
+    self.radix_key(idx, key);
+    let old = self.radix_mut().insert(key, idx.as_usize() as u64)?;
+    Ok(old.is_none())
+  }
+
+  /// Remove an element, returns `false` if it was not present
+  fn remove_art(&mut self, idx: T) -> bool {
+    let mut buf = [0; MAX_KEY_LEN];
+    let key = &mut buf[..self.radix().key_len()];
+    self.radix_key(idx, key);
+    self.radix_mut().remove(key).is_some()
+  }
+}
+
+/// A radix tree on its own indexes elements by their big-endian index
+impl<T: Idx, M: RawMem<Item = u64>> AdaptiveRadix<T> for Art<M> {
+  type Mem = M;
+
+  fn radix(&self) -> &Art<M> {
+    self
+  }
+
+  fn radix_mut(&mut self) -> &mut Art<M> {
+    self
   }
 }
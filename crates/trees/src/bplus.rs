@@ -0,0 +1,677 @@
+use {
+  bytemuck::{Pod, Zeroable},
+  core::{
+    fmt,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+  },
+  mem::{RawMem, Result},
+};
+
+/// Size of a B+ tree node page in bytes
+pub const PAGE_SIZE: usize = 4096;
+
+/// Bytes of the node header: entry count, node kind and next leaf
+const HEADER: usize = 16;
+
+/// Marker of a missing next leaf or free page
+const NONE: u64 = u64::MAX;
+
+const LEAF: u8 = 1;
+const INNER: u8 = 2;
+
+/// Page holding a single B+ tree node
+///
+/// Nodes are plain bytes, so pages can live in any [`RawMem`] backend,
+/// including memory-mapped files.
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct BPlusNode {
+  words: [u64; PAGE_SIZE / 8],
+}
+
+/// B+ tree mapping `K` to `V`, with nodes stored in pages of `M`
+///
+/// All entries live in leaves, which are linked left to right so that
+/// range scans walk leaves sequentially. Inner nodes only route searches.
+/// Every node takes one [`PAGE_SIZE`] page, so the fanout is derived from
+/// the sizes of `K` and `V`.
+///
+/// Operations cost O(log n) page visits. Underfull nodes borrow from or
+/// merge with a sibling on removal, keeping every node but the root at
+/// least half full.
+///
+/// # Examples
+/// ```
+/// use {mem::Alloc, trees::BPlusTree};
+///
+/// let mut tree = BPlusTree::<u64, u64, _>::new(Alloc::new());
+/// for key in 0..1000 {
+///   tree.insert(key, key * 2).unwrap();
+/// }
+///
+/// assert_eq!(tree.get(&21), Some(42));
+/// assert_eq!(tree.range(10..13).map(|(key, _)| key).collect::<Vec<_>>(), [
+///   10, 11, 12
+/// ]);
+/// ```
+pub struct BPlusTree<K, V, M> {
+  mem: M,
+  root: Option<usize>,
+  /// Number of inner levels above the leaves
+  height: usize,
+  len: usize,
+  /// Pages in use, the rest of the memory is spare capacity
+  used: usize,
+  /// Head of the list of released pages
+  free: Option<usize>,
+  leaf_cap: usize,
+  inner_cap: usize,
+  _marker: PhantomData<(K, V)>,
+}
+
+impl<K: Pod + Ord, V: Pod, M: RawMem<Item = BPlusNode>> BPlusTree<K, V, M> {
+  /// Create an empty tree with its pages in `mem`
+  ///
+  /// Pages already in `mem` count as spare capacity and are overwritten.
+  /// The root and counts of a tree are not kept in its pages, so a tree
+  /// cannot be reopened from the pages of an earlier one.
+  ///
+  /// # Panics
+  ///
+  /// Panics if fewer than four entries of `K` and `V` fit in a page.
+  pub fn new(mem: M) -> Self {
+    let (key, value) = (size_of::<K>(), size_of::<V>());
+    let leaf_cap = (PAGE_SIZE - HEADER) / (key + value).max(1);
+    // Inner nodes hold one more child than keys
+    let inner_cap = (PAGE_SIZE - HEADER - 8) / (key + 8);
+    assert!(
+      leaf_cap >= 4 && inner_cap >= 4,
+      "at least four entries must fit in a page"
+    );
+
+    Self {
+      mem,
+      root: None,
+      height: 0,
+      len: 0,
+      used: 0,
+      free: None,
+      leaf_cap,
+      inner_cap,
+      _marker: PhantomData,
+    }
+  }
+
+  /// Number of entries in the tree
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Check if the tree holds no entries
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Number of inner levels above the leaves
+  #[inline]
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Remove all entries, keeping the pages for reuse
+  pub fn clear(&mut self) {
+    self.root = None;
+    self.height = 0;
+    self.len = 0;
+    self.used = 0;
+    self.free = None;
+  }
+
+  /// Get the value stored under `key`
+  pub fn get(&self, key: &K) -> Option<V> {
+    let leaf = self.find_leaf(key)?;
+    let pos = self.search(leaf, key).ok()?;
+    Some(self.value(leaf, pos))
+  }
+
+  /// Check if `key` is present
+  #[inline]
+  pub fn contains(&self, key: &K) -> bool {
+    self.get(key).is_some()
+  }
+
+  /// Insert `value` under `key`, returning the previous value if any
+  pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>> {
+    let Some(mut page) = self.root else {
+      let leaf = self.alloc(LEAF)?;
+      self.store_leaf(leaf, &[key], &[value]);
+      self.root = Some(leaf);
+      self.len = 1;
+      return Ok(None);
+    };
+
+    let mut path = Vec::with_capacity(self.height);
+    for _ in 0..self.height {
+      let pos = self.route(page, &key);
+      path.push((page, pos));
+      page = self.child(page, pos);
+    }
+
+    let pos = match self.search(page, &key) {
+      Ok(pos) => {
+        let old = self.value(page, pos);
+        self.write(page, self.value_at(pos), &value);
+        return Ok(Some(old));
+      }
+      Err(pos) => pos,
+    };
+
+    let count = self.count(page);
+    if count < self.leaf_cap {
+      self.open_gap(page, self.key_at(0), size_of::<K>(), count, pos);
+      self.open_gap(page, self.value_at(0), size_of::<V>(), count, pos);
+      self.write(page, self.key_at(pos), &key);
+      self.write(page, self.value_at(pos), &value);
+      self.set_count(page, count + 1);
+    } else {
+      // A split may cascade up to a new root, reserve pages for all of
+      // them so that the tree is never left half updated
+      self.reserve(self.height + 2)?;
+
+      let (mut keys, mut values) = self.load_leaf(page);
+      keys.insert(pos, key);
+      values.insert(pos, value);
+
+      let mid = keys.len() / 2;
+      let right = self.alloc(LEAF)?;
+      self.store_leaf(right, &keys[mid..], &values[mid..]);
+      self.store_leaf(page, &keys[..mid], &values[..mid]);
+      self.set_next(right, self.next(page));
+      self.set_next(page, Some(right));
+
+      self.insert_separator(path, keys[mid], right)?;
+    }
+
+    self.len += 1;
+    Ok(None)
+  }
+
+  /// Remove `key`, returning its value if it was present
+  pub fn remove(&mut self, key: &K) -> Option<V> {
+    let mut page = self.root?;
+    let mut path = Vec::with_capacity(self.height);
+    for _ in 0..self.height {
+      let pos = self.route(page, key);
+      path.push((page, pos));
+      page = self.child(page, pos);
+    }
+
+    let pos = self.search(page, key).ok()?;
+    let value = self.value(page, pos);
+    let count = self.count(page);
+    self.close_gap(page, self.key_at(0), size_of::<K>(), count, pos);
+    self.close_gap(page, self.value_at(0), size_of::<V>(), count, pos);
+    self.set_count(page, count - 1);
+    self.len -= 1;
+
+    self.rebalance(page, path);
+    Some(value)
+  }
+
+  /// Iterate over entries with keys in `range`, in key order
+  pub fn range<R: RangeBounds<K>>(&self, range: R) -> RangeIter<'_, K, V, M> {
+    let end = range.end_bound().cloned();
+    let start = match range.start_bound() {
+      Bound::Included(key) => self.lower_bound(key, false),
+      Bound::Excluded(key) => self.lower_bound(key, true),
+      Bound::Unbounded => self.first_leaf().map(|leaf| (leaf, 0)),
+    };
+    let (page, pos) = match start {
+      Some((page, pos)) => (Some(page), pos),
+      None => (None, 0),
+    };
+    RangeIter { tree: self, page, pos, end }
+  }
+
+  /// Iterate over all entries in key order
+  #[inline]
+  pub fn iter(&self) -> RangeIter<'_, K, V, M> {
+    self.range(..)
+  }
+
+  /// Leaf that would hold `key`
+  fn find_leaf(&self, key: &K) -> Option<usize> {
+    let mut page = self.root?;
+    for _ in 0..self.height {
+      page = self.child(page, self.route(page, key));
+    }
+    Some(page)
+  }
+
+  fn first_leaf(&self) -> Option<usize> {
+    let mut page = self.root?;
+    for _ in 0..self.height {
+      page = self.child(page, 0);
+    }
+    Some(page)
+  }
+
+  /// Position of the first entry not below `key` (or above if `exclusive`)
+  fn lower_bound(&self, key: &K, exclusive: bool) -> Option<(usize, usize)> {
+    let leaf = self.find_leaf(key)?;
+    let pos = match self.search(leaf, key) {
+      Ok(pos) if exclusive => pos + 1,
+      Ok(pos) | Err(pos) => pos,
+    };
+    Some((leaf, pos))
+  }
+
+  /// Add a separator and right child produced by a split below
+  fn insert_separator(
+    &mut self,
+    mut path: Vec<(usize, usize)>,
+    mut sep: K,
+    mut right: usize,
+  ) -> Result<()> {
+    while let Some((page, pos)) = path.pop() {
+      let count = self.count(page);
+      if count < self.inner_cap {
+        self.open_gap(page, self.key_at(0), size_of::<K>(), count, pos);
+        self.open_gap(page, self.child_at(0), 8, count + 1, pos + 1);
+        self.write(page, self.key_at(pos), &sep);
+        self.write(page, self.child_at(pos + 1), &(right as u64));
+        self.set_count(page, count + 1);
+        return Ok(());
+      }
+
+      let (mut keys, mut children) = self.load_inner(page);
+      keys.insert(pos, sep);
+      children.insert(pos + 1, right);
+
+      // The middle key moves up instead of staying in either half
+      let mid = keys.len() / 2;
+      let new = self.alloc(INNER)?;
+      self.store_inner(new, &keys[mid + 1..], &children[mid + 1..]);
+      self.store_inner(page, &keys[..mid], &children[..=mid]);
+      sep = keys[mid];
+      right = new;
+    }
+
+    let left = self.root.expect("split below an existing root");
+    let root = self.alloc(INNER)?;
+    self.store_inner(root, &[sep], &[left, right]);
+    self.root = Some(root);
+    self.height += 1;
+    Ok(())
+  }
+
+  /// Restore minimum fill from `page` up after a removal
+  fn rebalance(&mut self, mut page: usize, mut path: Vec<(usize, usize)>) {
+    loop {
+      let leaf = self.is_leaf(page);
+      let count = self.count(page);
+
+      let Some((parent, pos)) = path.pop() else {
+        // The root may be underfull, but not empty
+        if count == 0 {
+          self.root = if leaf {
+            None
+          } else {
+            self.height -= 1;
+            Some(self.child(page, 0))
+          };
+          self.release(page);
+        }
+        return;
+      };
+
+      let min = if leaf { self.leaf_cap / 2 } else { self.inner_cap / 2 };
+      if count >= min {
+        return;
+      }
+
+      // Pair with the left sibling when there is one
+      let left = pos.saturating_sub(1);
+      let merged = if leaf {
+        self.rebalance_leaves(parent, left)
+      } else {
+        self.rebalance_inner(parent, left)
+      };
+      if !merged {
+        return;
+      }
+      page = parent;
+    }
+  }
+
+  /// Merge or redistribute leaves at `pos` and `pos + 1` of `parent`
+  ///
+  /// Returns `true` if the leaves were merged and the parent lost a key.
+  fn rebalance_leaves(&mut self, parent: usize, pos: usize) -> bool {
+    let (left, right) = (self.child(parent, pos), self.child(parent, pos + 1));
+    let (mut keys, mut values) = self.load_leaf(left);
+    let (right_keys, right_values) = self.load_leaf(right);
+    keys.extend(right_keys);
+    values.extend(right_values);
+
+    if keys.len() <= self.leaf_cap {
+      self.store_leaf(left, &keys, &values);
+      self.set_next(left, self.next(right));
+      self.release(right);
+      self.remove_separator(parent, pos);
+      return true;
+    }
+
+    let mid = keys.len() / 2;
+    self.store_leaf(left, &keys[..mid], &values[..mid]);
+    self.store_leaf(right, &keys[mid..], &values[mid..]);
+    self.write(parent, self.key_at(pos), &keys[mid]);
+    false
+  }
+
+  /// Merge or redistribute inner nodes at `pos` and `pos + 1` of `parent`
+  ///
+  /// Returns `true` if the nodes were merged and the parent lost a key.
+  fn rebalance_inner(&mut self, parent: usize, pos: usize) -> bool {
+    let (left, right) = (self.child(parent, pos), self.child(parent, pos + 1));
+    let (mut keys, mut children) = self.load_inner(left);
+    let (right_keys, right_children) = self.load_inner(right);
+    // The separator moves down between the two halves
+    keys.push(self.key(parent, pos));
+    keys.extend(right_keys);
+    children.extend(right_children);
+
+    if keys.len() <= self.inner_cap {
+      self.store_inner(left, &keys, &children);
+      self.release(right);
+      self.remove_separator(parent, pos);
+      return true;
+    }
+
+    let mid = keys.len() / 2;
+    self.store_inner(left, &keys[..mid], &children[..=mid]);
+    self.store_inner(right, &keys[mid + 1..], &children[mid + 1..]);
+    self.write(parent, self.key_at(pos), &keys[mid]);
+    false
+  }
+
+  /// Remove key `pos` and child `pos + 1` from an inner node
+  fn remove_separator(&mut self, page: usize, pos: usize) {
+    let count = self.count(page);
+    self.close_gap(page, self.key_at(0), size_of::<K>(), count, pos);
+    self.close_gap(page, self.child_at(0), 8, count + 1, pos + 1);
+    self.set_count(page, count - 1);
+  }
+
+  /// Binary search for `key` among the keys of `page`
+  fn search(&self, page: usize, key: &K) -> core::result::Result<usize, usize> {
+    let (mut lo, mut hi) = (0, self.count(page));
+    while lo < hi {
+      let mid = (lo + hi) / 2;
+      match self.key(page, mid).cmp(key) {
+        core::cmp::Ordering::Less => lo = mid + 1,
+        core::cmp::Ordering::Greater => hi = mid,
+        core::cmp::Ordering::Equal => return Ok(mid),
+      }
+    }
+    Err(lo)
+  }
+
+  /// Child of an inner node whose subtree may hold `key`
+  ///
+  /// Child `i` holds keys from separator `i - 1` (inclusive)
+  /// up to separator `i` (exclusive).
+  fn route(&self, page: usize, key: &K) -> usize {
+    match self.search(page, key) {
+      Ok(pos) => pos + 1,
+      Err(pos) => pos,
+    }
+  }
+
+  /// Make sure `pages` more pages can be allocated without growing
+  fn reserve(&mut self, pages: usize) -> Result<()> {
+    let needed = self.used + pages;
+    let len = self.mem.as_slice().len();
+    if needed > len {
+      // Grow geometrically, but settle for the exact amount when
+      // the backend is bounded (e.g. `PreAlloc`)
+      let grown = self.mem.grow((needed - len).max(len)).map(|page| {
+        page.zeroed();
+      });
+      if grown.is_err() {
+        self.mem.grow(needed - len)?.zeroed();
+      }
+    }
+    Ok(())
+  }
+
+  fn alloc(&mut self, kind: u8) -> Result<usize> {
+    let page = match self.free {
+      Some(page) => {
+        self.free = self.next(page);
+        page
+      }
+      None => {
+        self.reserve(1)?;
+        self.used += 1;
+        self.used - 1
+      }
+    };
+    self.set_count(page, 0);
+    self.bytes_mut(page)[4] = kind;
+    self.set_next(page, None);
+    Ok(page)
+  }
+
+  /// Put a page on the free list, linked through its next pointer
+  fn release(&mut self, page: usize) {
+    self.set_count(page, 0);
+    self.bytes_mut(page)[4] = 0;
+    self.set_next(page, self.free);
+    self.free = Some(page);
+  }
+
+  #[inline]
+  fn bytes(&self, page: usize) -> &[u8] {
+    bytemuck::bytes_of(&self.mem.as_slice()[page])
+  }
+
+  #[inline]
+  fn bytes_mut(&mut self, page: usize) -> &mut [u8] {
+    bytemuck::bytes_of_mut(&mut self.mem.as_mut_slice()[page])
+  }
+
+  #[inline]
+  fn read<T: Pod>(&self, page: usize, at: usize) -> T {
+    bytemuck::pod_read_unaligned(&self.bytes(page)[at..at + size_of::<T>()])
+  }
+
+  #[inline]
+  fn write<T: Pod>(&mut self, page: usize, at: usize, value: &T) {
+    self.bytes_mut(page)[at..at + size_of::<T>()]
+      .copy_from_slice(bytemuck::bytes_of(value));
+  }
+
+  #[inline]
+  fn count(&self, page: usize) -> usize {
+    self.read::<u32>(page, 0) as usize
+  }
+
+  #[inline]
+  fn set_count(&mut self, page: usize, count: usize) {
+    self.write(page, 0, &(count as u32));
+  }
+
+  #[inline]
+  fn is_leaf(&self, page: usize) -> bool {
+    self.bytes(page)[4] == LEAF
+  }
+
+  #[inline]
+  fn next(&self, page: usize) -> Option<usize> {
+    let next = self.read::<u64>(page, 8);
+    (next != NONE).then_some(next as usize)
+  }
+
+  #[inline]
+  fn set_next(&mut self, page: usize, next: Option<usize>) {
+    self.write(page, 8, &next.map_or(NONE, |next| next as u64));
+  }
+
+  #[inline]
+  fn key_at(&self, pos: usize) -> usize {
+    HEADER + pos * size_of::<K>()
+  }
+
+  #[inline]
+  fn value_at(&self, pos: usize) -> usize {
+    HEADER + self.leaf_cap * size_of::<K>() + pos * size_of::<V>()
+  }
+
+  #[inline]
+  fn child_at(&self, pos: usize) -> usize {
+    HEADER + self.inner_cap * size_of::<K>() + pos * 8
+  }
+
+  #[inline]
+  fn key(&self, page: usize, pos: usize) -> K {
+    self.read(page, self.key_at(pos))
+  }
+
+  #[inline]
+  fn value(&self, page: usize, pos: usize) -> V {
+    self.read(page, self.value_at(pos))
+  }
+
+  #[inline]
+  fn child(&self, page: usize, pos: usize) -> usize {
+    self.read::<u64>(page, self.child_at(pos)) as usize
+  }
+
+  /// Shift elements `pos..count` of an array one slot to the right
+  fn open_gap(
+    &mut self,
+    page: usize,
+    base: usize,
+    size: usize,
+    count: usize,
+    pos: usize,
+  ) {
+    let range = base + pos * size..base + count * size;
+    self.bytes_mut(page).copy_within(range, base + (pos + 1) * size);
+  }
+
+  /// Shift elements `pos + 1..count` of an array one slot to the left
+  fn close_gap(
+    &mut self,
+    page: usize,
+    base: usize,
+    size: usize,
+    count: usize,
+    pos: usize,
+  ) {
+    let range = base + (pos + 1) * size..base + count * size;
+    self.bytes_mut(page).copy_within(range, base + pos * size);
+  }
+
+  fn load_leaf(&self, page: usize) -> (Vec<K>, Vec<V>) {
+    let count = self.count(page);
+    let keys = (0..count).map(|pos| self.key(page, pos)).collect();
+    let values = (0..count).map(|pos| self.value(page, pos)).collect();
+    (keys, values)
+  }
+
+  fn store_leaf(&mut self, page: usize, keys: &[K], values: &[V]) {
+    for (pos, (key, value)) in keys.iter().zip(values).enumerate() {
+      self.write(page, self.key_at(pos), key);
+      self.write(page, self.value_at(pos), value);
+    }
+    self.set_count(page, keys.len());
+  }
+
+  fn load_inner(&self, page: usize) -> (Vec<K>, Vec<usize>) {
+    let count = self.count(page);
+    let keys = (0..count).map(|pos| self.key(page, pos)).collect();
+    let children = (0..=count).map(|pos| self.child(page, pos)).collect();
+    (keys, children)
+  }
+
+  fn store_inner(&mut self, page: usize, keys: &[K], children: &[usize]) {
+    for (pos, key) in keys.iter().enumerate() {
+      self.write(page, self.key_at(pos), key);
+    }
+    for (pos, &child) in children.iter().enumerate() {
+      self.write(page, self.child_at(pos), &(child as u64));
+    }
+    self.set_count(page, keys.len());
+  }
+}
+
+impl<K, V, M: Default> Default for BPlusTree<K, V, M>
+where
+  K: Pod + Ord,
+  V: Pod,
+  M: RawMem<Item = BPlusNode>,
+{
+  fn default() -> Self {
+    Self::new(M::default())
+  }
+}
+
+impl<K, V, M> fmt::Debug for BPlusTree<K, V, M> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("BPlusTree")
+      .field("len", &self.len)
+      .field("height", &self.height)
+      .field("pages", &self.used)
+      .finish()
+  }
+}
+
+/// Iterator over a key range of a [`BPlusTree`]
+///
+/// Walks the linked leaves, so each step is O(1) amortized.
+pub struct RangeIter<'a, K, V, M> {
+  tree: &'a BPlusTree<K, V, M>,
+  page: Option<usize>,
+  pos: usize,
+  end: Bound<K>,
+}
+
+impl<K, V, M> Iterator for RangeIter<'_, K, V, M>
+where
+  K: Pod + Ord,
+  V: Pod,
+  M: RawMem<Item = BPlusNode>,
+{
+  type Item = (K, V);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let page = self.page?;
+      if self.pos >= self.tree.count(page) {
+        self.page = self.tree.next(page);
+        self.pos = 0;
+        continue;
+      }
+
+      let key = self.tree.key(page, self.pos);
+      let past_end = match &self.end {
+        Bound::Included(end) => key > *end,
+        Bound::Excluded(end) => key >= *end,
+        Bound::Unbounded => false,
+      };
+      if past_end {
+        self.page = None;
+        return None;
+      }
+
+      let value = self.tree.value(page, self.pos);
+      self.pos += 1;
+      return Some((key, value));
+    }
+  }
+}
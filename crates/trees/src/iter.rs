@@ -0,0 +1,162 @@
+use {
+  crate::{Idx, Node},
+  core::cmp::Ordering,
+};
+
+/// In-order iterator over a binary tree, driven by explicit stacks
+///
+/// Nodes are read through `nodes`, so the iterator works over any
+/// [`Tree`](crate::Tree) as well as read-only views of tree memory.
+/// `cmp` places each element relative to the iterated range:
+/// [`Ordering::Less`] before it, [`Ordering::Greater`] after it and
+/// [`Ordering::Equal`] inside. It must be monotonic in tree order.
+///
+/// Each stack holds at most one path from the root, so memory is
+/// O(height) and a full traversal costs O(n) without parent pointers.
+/// Iteration runs from both ends and stops where the two ends meet.
+///
+/// # Examples
+/// ```
+/// use {core::cmp::Ordering, trees::{Iter, Node}};
+///
+/// // 2 is the root, 1 and 3 its children
+/// let nodes = [
+///   Node::default(),
+///   Node::default(),
+///   Node { size: 3, left: Some(1), right: Some(3) },
+///   Node::default(),
+/// ];
+/// let get = |idx: usize| nodes.get(idx).copied();
+///
+/// let iter = || Iter::new(Some(2), get, |_| Ordering::Equal);
+/// assert_eq!(iter().collect::<Vec<_>>(), [1, 2, 3]);
+/// assert_eq!(iter().rev().collect::<Vec<_>>(), [3, 2, 1]);
+/// ```
+pub struct Iter<T, G, C> {
+  nodes: G,
+  cmp: C,
+  /// Path to the next element from the front, nearest on top
+  front: Vec<T>,
+  /// Path to the next element from the back, nearest on top
+  back: Vec<T>,
+  front_last: Option<T>,
+  back_last: Option<T>,
+  done: bool,
+}
+
+impl<T, G, C> Iter<T, G, C>
+where
+  T: Idx,
+  G: Fn(T) -> Option<Node<T>>,
+  C: Fn(T) -> Ordering,
+{
+  /// Iterate over elements of the tree at `root` selected by `cmp`
+  pub fn new(root: Option<T>, nodes: G, cmp: C) -> Self {
+    let mut iter = Self {
+      nodes,
+      cmp,
+      front: Vec::new(),
+      back: Vec::new(),
+      front_last: None,
+      back_last: None,
+      done: false,
+    };
+
+    // Descend to the first element not before the range
+    let mut current = root;
+    while let Some(idx) = current {
+      let node = iter.node(idx);
+      if (iter.cmp)(idx) == Ordering::Less {
+        current = node.right;
+      } else {
+        iter.front.push(idx);
+        current = node.left;
+      }
+    }
+
+    // Descend to the last element not after the range
+    let mut current = root;
+    while let Some(idx) = current {
+      let node = iter.node(idx);
+      if (iter.cmp)(idx) == Ordering::Greater {
+        current = node.left;
+      } else {
+        iter.back.push(idx);
+        current = node.right;
+      }
+    }
+
+    iter
+  }
+
+  #[inline]
+  fn node(&self, idx: T) -> Node<T> {
+    (self.nodes)(idx).unwrap_or_default()
+  }
+}
+
+impl<T, G, C> Iterator for Iter<T, G, C>
+where
+  T: Idx,
+  G: Fn(T) -> Option<Node<T>>,
+  C: Fn(T) -> Ordering,
+{
+  type Item = T;
+
+  fn next(&mut self) -> Option<T> {
+    if self.done {
+      return None;
+    }
+
+    let Some(idx) = self.front.pop() else {
+      self.done = true;
+      return None;
+    };
+    if Some(idx) == self.back_last || (self.cmp)(idx) == Ordering::Greater {
+      self.done = true;
+      return None;
+    }
+
+    // The successor is the leftmost element of the right subtree,
+    // or the nearest ancestor already waiting on the stack
+    let mut current = self.node(idx).right;
+    while let Some(next) = current {
+      self.front.push(next);
+      current = self.node(next).left;
+    }
+
+    self.front_last = Some(idx);
+    Some(idx)
+  }
+}
+
+impl<T, G, C> DoubleEndedIterator for Iter<T, G, C>
+where
+  T: Idx,
+  G: Fn(T) -> Option<Node<T>>,
+  C: Fn(T) -> Ordering,
+{
+  fn next_back(&mut self) -> Option<T> {
+    if self.done {
+      return None;
+    }
+
+    let Some(idx) = self.back.pop() else {
+      self.done = true;
+      return None;
+    };
+    if Some(idx) == self.front_last || (self.cmp)(idx) == Ordering::Less {
+      self.done = true;
+      return None;
+    }
+
+    let mut current = self.node(idx).left;
+    while let Some(prev) = current {
+      self.back.push(prev);
+      current = self.node(prev).right;
+    }
+
+    self.back_last = Some(idx);
+    Some(idx)
+  }
+}
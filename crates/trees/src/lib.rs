@@ -1,12 +1,17 @@
 #![allow(unsafe_op_in_unsafe_fn)]
 
 mod art;
+mod bplus;
+mod iter;
 mod node;
 mod sbt;
 mod tree;
+pub mod validate;
 
 pub use {
-  art::{AdaptiveRadix, NodeType},
+  art::{AdaptiveRadix, Art, MAX_KEY_LEN, NodeType},
+  bplus::{BPlusNode, BPlusTree, PAGE_SIZE, RangeIter},
+  iter::Iter,
   node::{Idx, Node},
   sbt::SizeBalanced,
   tree::Tree,
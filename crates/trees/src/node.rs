@@ -51,7 +51,8 @@ macro_rules! impl_idx_nonzero {
 
 impl_idx! { u8 u16 u32 u64 usize }
 impl_idx_nonzero! {
-  NonZeroU8 NonZeroU16 NonZeroU32 NonZeroU64 NonZeroUsize
+  NonZeroU8 NonZeroU16 NonZeroU32 NonZeroU64 NonZeroU128 NonZeroUsize
+  NonZeroI8 NonZeroI16 NonZeroI32 NonZeroI64 NonZeroI128 NonZeroIsize
 }
 
 /// Tree node structure - stores size and children
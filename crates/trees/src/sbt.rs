@@ -109,6 +109,68 @@ pub trait SizeBalanced<T: Idx>: Tree<T> {
     }
   }
 
+  /// Position of `idx` in the sorted order of the tree, counting from 0
+  ///
+  /// Returns `None` if the index is not in the tree.
+  fn rank(&self, root: Option<T>, idx: T) -> Option<usize> {
+    let mut rank = 0;
+    let mut current = root;
+    while let Some(node) = current {
+      if self.is_left_of(idx, node) {
+        current = self.left(node);
+      } else if self.is_right_of(idx, node) {
+        rank += self.left_size(node).unwrap_or(0) + 1;
+        current = self.right(node);
+      } else {
+        return Some(rank + self.left_size(node).unwrap_or(0));
+      }
+    }
+    None
+  }
+
+  /// Element at position `k` in the sorted order of the tree
+  ///
+  /// Returns `None` if the tree has `k` or fewer elements.
+  fn select(&self, root: Option<T>, mut k: usize) -> Option<T> {
+    let mut current = root;
+    while let Some(node) = current {
+      let left = self.left_size(node).unwrap_or(0);
+      match k.cmp(&left) {
+        core::cmp::Ordering::Less => current = self.left(node),
+        core::cmp::Ordering::Equal => return Some(node),
+        core::cmp::Ordering::Greater => {
+          k -= left + 1;
+          current = self.right(node);
+        }
+      }
+    }
+    None
+  }
+
+  /// Number of elements ordered before `probe`
+  ///
+  /// `probe` does not have to be in the tree. Whole left subtrees are
+  /// skipped by their size, so the walk is O(height).
+  fn count_less(&self, root: Option<T>, probe: T) -> usize {
+    let mut count = 0;
+    let mut current = root;
+    while let Some(node) = current {
+      if self.is_left_of(node, probe) {
+        count += self.left_size(node).unwrap_or(0) + 1;
+        current = self.right(node);
+      } else {
+        current = self.left(node);
+      }
+    }
+    count
+  }
+
+  /// Number of elements in `lo..hi`
+  fn count_range(&self, root: Option<T>, lo: T, hi: T) -> usize {
+    let below_hi = self.count_less(root, hi);
+    below_hi.saturating_sub(self.count_less(root, lo))
+  }
+
   /// Internal insert implementation using pointer for in-place updates
   ///
   /// # Safety
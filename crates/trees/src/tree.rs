@@ -1,4 +1,10 @@
-use crate::{Idx, Node};
+use {
+  crate::{Idx, Iter, Node},
+  core::{
+    cmp::Ordering,
+    ops::{Bound, RangeBounds},
+  },
+};
 
 /// Base trait for all tree implementations
 ///
@@ -73,13 +79,19 @@ pub trait Tree<T: Idx> {
     current
   }
 
-  /// Get in-order successor (next node in sorted order)
+  /// Get in-order successor within the subtree of `idx`
+  ///
+  /// Returns `None` when the successor is an ancestor, use
+  /// [`iter_from`](Self::iter_from) to walk the whole tree in order.
   #[inline]
   fn next(&self, idx: T) -> Option<T> {
     self.right(idx).map(|idx| self.leftest(idx))
   }
 
-  /// Get in-order predecessor (previous node in sorted order)
+  /// Get in-order predecessor within the subtree of `idx`
+  ///
+  /// Returns `None` when the predecessor is an ancestor, use
+  /// [`iter_from`](Self::iter_from) to walk the whole tree in order.
   #[inline]
   fn prev(&self, idx: T) -> Option<T> {
     self.left(idx).map(|idx| self.rightest(idx))
@@ -104,6 +116,61 @@ pub trait Tree<T: Idx> {
     }
   }
 
+  /// Iterate over the tree at `root` in order
+  ///
+  /// The iterator is double-ended, so `.rev()` walks it backwards.
+  fn iter_from(
+    &self,
+    root: Option<T>,
+  ) -> Iter<T, impl Fn(T) -> Option<Node<T>>, impl Fn(T) -> Ordering> {
+    Iter::new(root, |idx| self.get(idx), |_| Ordering::Equal)
+  }
+
+  /// Iterate in order over elements of the tree at `root` within `range`
+  ///
+  /// Bounds are elements compared with [`is_left_of`](Self::is_left_of),
+  /// so they do not have to be in the tree themselves.
+  fn range<R: RangeBounds<T>>(
+    &self,
+    root: Option<T>,
+    range: R,
+  ) -> Iter<T, impl Fn(T) -> Option<Node<T>>, impl Fn(T) -> Ordering> {
+    let start = range.start_bound().cloned();
+    let end = range.end_bound().cloned();
+    self.range_by(root, move |idx| {
+      let before = match start {
+        Bound::Included(lo) => self.is_left_of(idx, lo),
+        Bound::Excluded(lo) => !self.is_right_of(idx, lo),
+        Bound::Unbounded => false,
+      };
+      let after = match end {
+        Bound::Included(hi) => self.is_right_of(idx, hi),
+        Bound::Excluded(hi) => !self.is_left_of(idx, hi),
+        Bound::Unbounded => false,
+      };
+      if before {
+        Ordering::Less
+      } else if after {
+        Ordering::Greater
+      } else {
+        Ordering::Equal
+      }
+    })
+  }
+
+  /// Iterate in order over elements for which `cmp` returns `Equal`
+  ///
+  /// `cmp` places an element before (`Less`) or after (`Greater`) the
+  /// wanted range, which lets callers select by keys that are not
+  /// elements, like all links with a given source.
+  fn range_by<C: Fn(T) -> Ordering>(
+    &self,
+    root: Option<T>,
+    cmp: C,
+  ) -> Iter<T, impl Fn(T) -> Option<Node<T>>, C> {
+    Iter::new(root, |idx| self.get(idx), cmp)
+  }
+
   /// Clear node (set to default)
   #[inline]
   fn clear(&mut self, idx: T) {
@@ -113,12 +180,12 @@ pub trait Tree<T: Idx> {
   /// Insert index into tree, returns new root
   ///
   /// Implementations should use their specific tree strategy
-  /// (e.g., SizeBalanced::insert_sbt or AdaptiveRadix::insert_art).
+  /// (e.g., SizeBalanced::insert_sbt).
   fn insert(&mut self, root: Option<T>, idx: T) -> Option<T>;
 
   /// Remove index from tree, returns new root (None if tree empty)
   ///
   /// Implementations should use their specific tree strategy
-  /// (e.g., SizeBalanced::remove_sbt or AdaptiveRadix::remove_art).
+  /// (e.g., SizeBalanced::remove_sbt).
   fn remove(&mut self, root: Option<T>, idx: T) -> Option<T>;
 }
@@ -0,0 +1,285 @@
+//! Structural invariant checks for trees
+//!
+//! Every check walks the whole tree without trusting it: nodes reached
+//! twice, children that do not exist and keys out of order are reported
+//! as a [`Violation`] naming the offending node instead of looping or
+//! panicking. They work on any implementation of the tree traits, so
+//! property tests and fuzz targets can assert them after every operation.
+//!
+//! # Examples
+//! ```
+//! use {mem::Alloc, trees::{Art, validate}};
+//!
+//! let mut art = Art::<Alloc<u64>>::default();
+//! for idx in 1..100usize {
+//!   trees::AdaptiveRadix::insert_art(&mut art, idx)?;
+//! }
+//! assert_eq!(validate::adaptive_radix(&art, 1..100usize), Ok(99));
+//! # Ok::<(), mem::Error>(())
+//! ```
+
+use {
+  crate::{AdaptiveRadix, Idx, MAX_KEY_LEN, SizeBalanced, Tree},
+  core::{fmt, ops::ControlFlow},
+  std::collections::HashSet,
+};
+
+/// Broken invariant found in a tree
+///
+/// Binary tree variants name nodes by index, radix tree variants by their
+/// word offset in the arena of the [`Art`](crate::Art).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation<T> {
+  /// A child (or the root) has no node
+  Missing { node: T },
+  /// A node is reached twice, through a cycle or a shared subtree
+  Revisited { node: T },
+  /// A node is not on the side of `ancestor` its position requires
+  Unordered { node: T, ancestor: T },
+  /// A node keeps a size other than the number of nodes below it
+  WrongSize { node: T, stored: usize, actual: usize },
+  /// The subtree of `nephew`, a grandchild of `node`, is larger than its
+  /// uncle, the other child of `node`
+  Unbalanced { node: T, nephew: T },
+  /// A radix node lies outside the arena, `parent` is `0` for the root
+  RadixOutOfBounds { node: usize, parent: usize },
+  /// A radix node is reached twice
+  RadixRevisited { node: usize },
+  /// A radix node counts a different number of children than it holds
+  RadixChildCount { node: usize, stored: usize, actual: usize },
+  /// An inner radix node has fewer than two children
+  RadixUnderfull { node: usize },
+  /// A key is not above the key before it, `value` is stored under it
+  RadixUnordered { value: u64 },
+  /// Looking up the key of `value` does not lead back to it
+  RadixUnreachable { value: u64 },
+  /// An element is not found under its key
+  Absent { element: T },
+  /// The tree holds a different number of keys than expected
+  Count { expected: usize, actual: usize },
+}
+
+impl<T: fmt::Debug> fmt::Display for Violation<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Violation::Missing { node } => write!(f, "node {node:?} does not exist"),
+      Violation::Revisited { node } => {
+        write!(f, "node {node:?} is reached twice")
+      }
+      Violation::Unordered { node, ancestor } => {
+        write!(f, "node {node:?} is on the wrong side of {ancestor:?}")
+      }
+      Violation::WrongSize { node, stored, actual } => write!(
+        f,
+        "node {node:?} keeps size {stored}, its subtree has {actual} nodes"
+      ),
+      Violation::Unbalanced { node, nephew } => {
+        write!(f, "node {nephew:?} below {node:?} outweighs its uncle")
+      }
+      Violation::RadixOutOfBounds { node, parent } => {
+        write!(f, "radix node at {node} below {parent} is outside the arena")
+      }
+      Violation::RadixRevisited { node } => {
+        write!(f, "radix node at {node} is reached twice")
+      }
+      Violation::RadixChildCount { node, stored, actual } => write!(
+        f,
+        "radix node at {node} counts {stored} children and holds {actual}"
+      ),
+      Violation::RadixUnderfull { node } => {
+        write!(f, "radix node at {node} has fewer than two children")
+      }
+      Violation::RadixUnordered { value } => {
+        write!(f, "key of {value} is not above the key before it")
+      }
+      Violation::RadixUnreachable { value } => {
+        write!(f, "key of {value} does not lead back to it")
+      }
+      Violation::Absent { element } => {
+        write!(f, "element {element:?} is not found under its key")
+      }
+      Violation::Count { expected, actual } => {
+        write!(f, "tree holds {actual} keys, expected {expected}")
+      }
+    }
+  }
+}
+
+impl<T: fmt::Debug> std::error::Error for Violation<T> {}
+
+/// Check the binary search tree at `root`, returning its node count
+///
+/// Every child has to exist, be reached once and lie between its
+/// ancestors according to [`Tree::is_left_of`].
+pub fn tree<T, Tr>(tree: &Tr, root: Option<T>) -> Result<usize, Violation<T>>
+where
+  T: Idx,
+  Tr: Tree<T> + ?Sized,
+{
+  walk(tree, root).map(|order| order.len())
+}
+
+/// Check the size-balanced tree at `root`, returning its node count
+///
+/// On top of what [`tree`] checks, every node has to keep the size of its
+/// subtree, and no grandchild subtree may be larger than its uncle:
+/// `size(left) >= size(right.left), size(right.right)` and the same
+/// mirrored. [`SizeBalanced::insert_sbt`] rebalances in a single pass on
+/// the way down and [`SizeBalanced::remove_sbt`] not at all, so trees
+/// built by them only guarantee what [`sizes`] checks.
+pub fn size_balanced<T, Tr>(
+  tree: &Tr,
+  root: Option<T>,
+) -> Result<usize, Violation<T>>
+where
+  T: Idx,
+  Tr: SizeBalanced<T> + ?Sized,
+{
+  let order = sized_walk(tree, root)?;
+  let size = |idx: Option<T>| idx.and_then(|idx| tree.size(idx)).unwrap_or(0);
+  for &(node, _) in &order {
+    let (left, right) = (tree.left(node), tree.right(node));
+    let sides = [(left, right), (right, left)];
+    for (uncle, child) in sides {
+      let Some(child) = child else { continue };
+      for nephew in [tree.left(child), tree.right(child)].into_iter().flatten()
+      {
+        if size(Some(nephew)) > size(uncle) {
+          return Err(Violation::Unbalanced { node, nephew });
+        }
+      }
+    }
+  }
+  Ok(order.len())
+}
+
+/// Check the sizes kept by the tree at `root`, returning its node count
+///
+/// Checks what [`tree`] checks and that every node keeps the size of its
+/// subtree, which holds after any sequence of inserts and removals.
+pub fn sizes<T, Tr>(tree: &Tr, root: Option<T>) -> Result<usize, Violation<T>>
+where
+  T: Idx,
+  Tr: SizeBalanced<T> + ?Sized,
+{
+  sized_walk(tree, root).map(|order| order.len())
+}
+
+/// Like [`walk`], failing as well on the first node with a wrong size
+fn sized_walk<T, Tr>(
+  tree: &Tr,
+  root: Option<T>,
+) -> Result<Vec<(T, Option<usize>)>, Violation<T>>
+where
+  T: Idx,
+  Tr: SizeBalanced<T> + ?Sized,
+{
+  let order = walk(tree, root)?;
+  // Reversed pre-order reaches every node after its subtree
+  let mut actual = vec![0; order.len()];
+  for (position, &(_, parent)) in order.iter().enumerate().rev() {
+    actual[position] += 1;
+    if let Some(parent) = parent {
+      actual[parent] += actual[position];
+    }
+  }
+  for (&(node, _), actual) in order.iter().zip(actual) {
+    let stored = tree.size(node).unwrap_or(0);
+    if stored != actual {
+      return Err(Violation::WrongSize { node, stored, actual });
+    }
+  }
+  Ok(order)
+}
+
+/// Check a radix tree that should hold exactly `elements`, returning its
+/// key count
+///
+/// The nodes of the [`Art`](crate::Art) are walked first, then every key
+/// has to be above the one before it and lead back to its value, and
+/// every element has to be found under its
+/// [`radix_key`](AdaptiveRadix::radix_key).
+pub fn adaptive_radix<T, A>(
+  radix: &A,
+  elements: impl IntoIterator<Item = T>,
+) -> Result<usize, Violation<T>>
+where
+  T: Idx,
+  A: AdaptiveRadix<T> + ?Sized,
+{
+  let art = radix.radix();
+  let leaves = art.validate_nodes()?;
+  if leaves != art.len() {
+    return Err(Violation::Count { expected: art.len(), actual: leaves });
+  }
+
+  let mut previous = None::<[u8; MAX_KEY_LEN]>;
+  let flow = art.scan_prefix(&[], |key, value| {
+    let mut current = [0; MAX_KEY_LEN];
+    current[..key.len()].copy_from_slice(key);
+    if previous.is_some_and(|previous| previous >= current) {
+      return ControlFlow::Break(Violation::RadixUnordered { value });
+    }
+    if art.get(key) != Some(value) {
+      return ControlFlow::Break(Violation::RadixUnreachable { value });
+    }
+    previous = Some(current);
+    ControlFlow::Continue(())
+  });
+  if let ControlFlow::Break(violation) = flow {
+    return Err(violation);
+  }
+
+  let mut expected = 0;
+  for element in elements {
+    if !radix.search_art(element) {
+      return Err(Violation::Absent { element });
+    }
+    expected += 1;
+  }
+  if expected != art.len() {
+    return Err(Violation::Count { expected, actual: art.len() });
+  }
+  Ok(art.len())
+}
+
+/// Nodes of the tree at `root` in pre-order, with the position of their
+/// parent, failing on the first violation of [`tree`]
+fn walk<T, Tr>(
+  tree: &Tr,
+  root: Option<T>,
+) -> Result<Vec<(T, Option<usize>)>, Violation<T>>
+where
+  T: Idx,
+  Tr: Tree<T> + ?Sized,
+{
+  let mut seen = HashSet::new();
+  let mut order = Vec::new();
+  // Every node comes with the ancestors it has to lie between
+  let mut stack: Vec<_> =
+    root.map(|root| (root, None, None, None)).into_iter().collect();
+  while let Some((node, parent, lower, upper)) = stack.pop() {
+    let Some(links) = tree.get(node) else {
+      return Err(Violation::Missing { node });
+    };
+    if !seen.insert(node.as_usize()) {
+      return Err(Violation::Revisited { node });
+    }
+    let misplaced = [
+      lower.filter(|&lower| !tree.is_left_of(lower, node)),
+      upper.filter(|&upper| !tree.is_left_of(node, upper)),
+    ];
+    if let Some(ancestor) = misplaced.into_iter().flatten().next() {
+      return Err(Violation::Unordered { node, ancestor });
+    }
+
+    let position = order.len();
+    order.push((node, parent));
+    stack.extend(
+      links.right.map(|right| (right, Some(position), Some(node), upper)),
+    );
+    stack
+      .extend(links.left.map(|left| (left, Some(position), lower, Some(node))));
+  }
+  Ok(order)
+}
@@ -0,0 +1,314 @@
+mod common;
+
+use {
+  common::ArtStore,
+  mem::{Alloc, PreAlloc},
+  proptest::prelude::*,
+  std::{collections::BTreeMap, ops::ControlFlow},
+  trees::{AdaptiveRadix, Art, NodeType},
+};
+
+fn key(value: u64) -> [u8; 8] {
+  value.to_be_bytes()
+}
+
+fn collect(art: &Art<Alloc<u64>>) -> Vec<(Vec<u8>, u64)> {
+  let mut items = Vec::new();
+  art.for_each(|key, value| items.push((key.to_vec(), value)));
+  items
+}
+
+#[test]
+fn test_insert_get_remove() {
+  let mut art = Art::new(Alloc::new(), 8);
+
+  assert_eq!(art.insert(&key(1), 10).unwrap(), None);
+  assert_eq!(art.insert(&key(2), 20).unwrap(), None);
+  assert_eq!(art.insert(&key(1), 11).unwrap(), Some(10));
+
+  assert_eq!(art.len(), 2);
+  assert_eq!(art.get(&key(1)), Some(11));
+  assert_eq!(art.get(&key(3)), None);
+
+  assert_eq!(art.remove(&key(1)), Some(11));
+  assert_eq!(art.remove(&key(1)), None);
+  assert_eq!(art.get(&key(2)), Some(20));
+  assert_eq!(art.len(), 1);
+}
+
+#[test]
+fn test_nodes_grow_and_shrink() {
+  let mut art = Art::new(Alloc::new(), 8);
+  // Keys differ only in the last byte, so they share one inner node
+  let grown = [
+    (4, NodeType::Node4),
+    (16, NodeType::Node16),
+    (48, NodeType::Node48),
+    (256, NodeType::Node256),
+  ];
+
+  let mut inserted = 0;
+  for (count, kind) in grown {
+    while inserted < count {
+      art.insert(&key(inserted), inserted).unwrap();
+      inserted += 1;
+    }
+    assert_eq!(art.root_type(), Some(kind));
+  }
+
+  let shrunk =
+    [(37, NodeType::Node48), (12, NodeType::Node16), (3, NodeType::Node4)];
+  for (count, kind) in shrunk {
+    while inserted > count {
+      inserted -= 1;
+      assert_eq!(art.remove(&key(inserted)), Some(inserted));
+    }
+    assert_eq!(art.root_type(), Some(kind));
+  }
+
+  for value in 0..3 {
+    assert_eq!(art.get(&key(value)), Some(value));
+  }
+}
+
+#[test]
+fn test_collapse_single_child() {
+  let mut art = Art::new(Alloc::new(), 8);
+  art.insert(&key(0x0100), 1).unwrap();
+  art.insert(&key(0x0101), 2).unwrap();
+  art.insert(&key(0x0200), 3).unwrap();
+
+  // Removing 0x0200 leaves a Node4 with a single inner child,
+  // which is merged into it along with the edge byte
+  art.remove(&key(0x0200));
+  assert_eq!(art.root_type(), Some(NodeType::Node4));
+  assert_eq!(art.get(&key(0x0100)), Some(1));
+  assert_eq!(art.get(&key(0x0101)), Some(2));
+  assert_eq!(art.get(&key(0x0201)), None);
+
+  art.remove(&key(0x0101));
+  assert_eq!(art.root_type(), None);
+  assert_eq!(art.get(&key(0x0100)), Some(1));
+}
+
+#[test]
+fn test_long_prefixes() {
+  let mut art = Art::new(Alloc::new(), 32);
+  let long = |tail: u8, at: usize| {
+    let mut key = [7; 32];
+    key[at] = tail;
+    key
+  };
+
+  // Keys share more than the inline prefix, then diverge at various depths
+  for (value, at) in [20, 31, 12, 9, 25].into_iter().enumerate() {
+    art.insert(&long(0, at), value as u64).unwrap();
+  }
+  for (value, at) in [20, 31, 12, 9, 25].into_iter().enumerate() {
+    assert_eq!(art.get(&long(0, at)), Some(value as u64));
+    assert_eq!(art.get(&long(1, at)), None);
+  }
+
+  assert_eq!(art.remove(&long(0, 12)), Some(2));
+  assert_eq!(art.remove(&long(0, 25)), Some(4));
+  assert_eq!(art.get(&long(0, 20)), Some(0));
+  assert_eq!(art.get(&long(0, 31)), Some(1));
+  assert_eq!(art.get(&long(0, 9)), Some(3));
+}
+
+#[test]
+fn test_for_each_sorted() {
+  let mut art = Art::new(Alloc::new(), 8);
+  for value in [300, 5, 70_000, 6, 1 << 40, 0] {
+    art.insert(&key(value), value).unwrap();
+  }
+
+  let values: Vec<_> = collect(&art).into_iter().map(|(_, v)| v).collect();
+  assert_eq!(values, [0, 5, 6, 300, 70_000, 1 << 40]);
+}
+
+#[test]
+fn test_scan_prefix() {
+  let mut art = Art::new(Alloc::new(), 8);
+  for value in [0x0101, 0x0102, 0x0201, 0x0103, 0x01_0000_0101] {
+    art.insert(&key(value), value).unwrap();
+  }
+
+  let scan = |prefix: &[u8], limit: usize| {
+    let mut found = Vec::new();
+    let _ = art.scan_prefix(prefix, |_, value| {
+      found.push(value);
+      if found.len() == limit {
+        ControlFlow::Break(())
+      } else {
+        ControlFlow::Continue(())
+      }
+    });
+    found
+  };
+
+  assert_eq!(scan(&[0, 0, 0, 0, 0, 0, 1], 10), [0x0101, 0x0102, 0x0103]);
+  assert_eq!(scan(&[0, 0, 0, 0, 0, 0, 1], 2), [0x0101, 0x0102]);
+  assert_eq!(scan(&[0, 0, 0, 0, 0, 0, 2], 10), [0x0201]);
+  assert_eq!(scan(&[0, 0, 0, 1], 10), [0x01_0000_0101]);
+  assert_eq!(scan(&[0, 0, 0, 0, 0, 0, 3], 10), []);
+  assert_eq!(scan(&key(0x0102), 10), [0x0102]);
+  assert_eq!(scan(&[], 10).len(), 5);
+}
+
+#[test]
+fn test_clear_reuses_arena() {
+  let mut art = Art::new(Alloc::new(), 8);
+  for value in 0..1000 {
+    art.insert(&key(value * 7), value).unwrap();
+  }
+  art.clear();
+
+  assert!(art.is_empty());
+  assert_eq!(art.get(&key(7)), None);
+  art.insert(&key(7), 1).unwrap();
+  assert_eq!(art.get(&key(7)), Some(1));
+}
+
+#[test]
+fn test_failed_insert_changes_nothing() {
+  // Deterministic pseudo-random keys sharing prefixes
+  let mut seed = 0x2545_f491_u64;
+  let mut next = |n: u64| {
+    seed ^= seed << 13;
+    seed ^= seed >> 7;
+    seed ^= seed << 17;
+    seed % n
+  };
+  for _ in 0..300 {
+    // Arenas too small for every key, so inserts start failing
+    let words = 64 + next(1500) as usize;
+    let mut art = Art::new(PreAlloc::new(vec![0u64; words]), 8);
+    let mut model = BTreeMap::new();
+    for _ in 0..400 {
+      let value = (next(4) << 48) | (next(3) << 24) | next(40);
+      if next(3) == 0 {
+        assert_eq!(art.remove(&key(value)), model.remove(&value));
+      } else if art.insert(&key(value), value).is_ok() {
+        model.insert(value, value);
+      }
+    }
+    assert_eq!(art.len(), model.len());
+    for &value in model.keys() {
+      assert_eq!(art.get(&key(value)), Some(value));
+    }
+    let mut items = Vec::new();
+    art.for_each(|_, value| items.push(value));
+    assert!(items.into_iter().eq(model.into_values()));
+  }
+}
+
+#[test]
+fn test_adaptive_radix_store() {
+  let mut store: ArtStore<usize> = ArtStore::new(0);
+
+  assert!(store.insert_art(42).unwrap());
+  assert!(!store.insert_art(42).unwrap());
+  assert!(store.insert_art(7).unwrap());
+
+  assert!(store.search_art(42));
+  assert!(store.search_art(7));
+  assert!(!store.search_art(8));
+
+  assert!(store.remove_art(42));
+  assert!(!store.remove_art(42));
+  assert!(!store.search_art(42));
+  assert!(store.search_art(7));
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig {
+    cases: 128,
+    .. ProptestConfig::default()
+  })]
+
+  #[test]
+  fn prop_matches_btree_map(
+    ops in prop::collection::vec((any::<bool>(), 0u64..2048), 1..600)
+  ) {
+    let mut art = Art::new(Alloc::new(), 8);
+    let mut model = BTreeMap::new();
+
+    for (i, (insert, value)) in ops.into_iter().enumerate() {
+      // Spread keys over several bytes to exercise prefixes
+      let value = value.wrapping_mul(0x9E37_79B9) & 0xFFFF_FFFF;
+      if insert {
+        let old = art.insert(&key(value), i as u64).unwrap();
+        prop_assert_eq!(old, model.insert(value, i as u64));
+      } else {
+        prop_assert_eq!(art.remove(&key(value)), model.remove(&value));
+      }
+      prop_assert_eq!(art.len(), model.len());
+    }
+
+    for (&value, &stored) in &model {
+      prop_assert_eq!(art.get(&key(value)), Some(stored));
+    }
+    let expected: Vec<_> =
+      model.iter().map(|(&k, &v)| (key(k).to_vec(), v)).collect();
+    prop_assert_eq!(collect(&art), expected);
+
+    // Every key prefix of the first two bytes yields the matching range
+    for high in [0u8, 0x12, 0x9E] {
+      let mut found = Vec::new();
+      let _ = art.scan_prefix(&[0, 0, 0, 0, high], |_, value| {
+        found.push(value);
+        ControlFlow::<()>::Continue(())
+      });
+      let expected: Vec<_> = model
+        .iter()
+        .filter(|&(&k, _)| k >> 24 == high as u64)
+        .map(|(_, &v)| v)
+        .collect();
+      prop_assert_eq!(found, expected);
+    }
+  }
+
+  #[test]
+  fn prop_long_keys_match_btree_map(
+    ops in prop::collection::vec((any::<bool>(), 0u16..512), 1..400)
+  ) {
+    // Keys share runs longer than the inline prefix between varying bytes
+    let long = |value: u16| {
+      let mut key = [0xAA; 24];
+      key[11] = (value >> 8) as u8;
+      key[23] = value as u8;
+      key
+    };
+    let mut art = Art::new(Alloc::new(), 24);
+    let mut model = BTreeMap::new();
+
+    for (i, (insert, value)) in ops.into_iter().enumerate() {
+      if insert {
+        let old = art.insert(&long(value), i as u64).unwrap();
+        prop_assert_eq!(old, model.insert(value, i as u64));
+      } else {
+        prop_assert_eq!(art.remove(&long(value)), model.remove(&value));
+      }
+    }
+
+    for value in 0..512 {
+      prop_assert_eq!(art.get(&long(value)), model.get(&value).copied());
+    }
+  }
+
+  #[test]
+  fn prop_insert_remove_all(
+    values in prop::collection::hash_set(any::<u64>(), 1..300)
+  ) {
+    let mut art = Art::new(Alloc::new(), 8);
+    for &value in &values {
+      art.insert(&key(value), value).unwrap();
+    }
+    for &value in &values {
+      prop_assert_eq!(art.remove(&key(value)), Some(value));
+    }
+    prop_assert!(art.is_empty());
+    prop_assert_eq!(art.root_type(), None);
+  }
+}
@@ -0,0 +1,193 @@
+use {
+  mem::{Alloc, PreAlloc, RawMem, TempFile},
+  proptest::prelude::*,
+  std::{collections::BTreeMap, ops::Bound},
+  trees::{BPlusNode, BPlusTree},
+};
+
+/// Wide key that leaves room for few entries per page,
+/// so that small tests already split and merge nodes
+#[derive(
+  Debug,
+  Clone,
+  Copy,
+  PartialEq,
+  Eq,
+  PartialOrd,
+  Ord,
+  bytemuck::Pod,
+  bytemuck::Zeroable,
+)]
+#[repr(C)]
+struct Wide([u64; 96]);
+
+impl Wide {
+  fn new(key: u64) -> Self {
+    let mut words = [0; 96];
+    words[0] = key;
+    Self(words)
+  }
+}
+
+fn keys<K: bytemuck::Pod + Ord, V: bytemuck::Pod, M>(
+  tree: &BPlusTree<K, V, M>,
+) -> Vec<K>
+where
+  M: RawMem<Item = BPlusNode>,
+{
+  tree.iter().map(|(key, _)| key).collect()
+}
+
+#[test]
+fn test_insert_get_remove() {
+  let mut tree = BPlusTree::<u64, u64, _>::new(Alloc::new());
+
+  assert_eq!(tree.insert(1, 10).unwrap(), None);
+  assert_eq!(tree.insert(2, 20).unwrap(), None);
+  assert_eq!(tree.insert(1, 11).unwrap(), Some(10));
+
+  assert_eq!(tree.len(), 2);
+  assert_eq!(tree.get(&1), Some(11));
+  assert_eq!(tree.get(&3), None);
+
+  assert_eq!(tree.remove(&1), Some(11));
+  assert_eq!(tree.remove(&1), None);
+  assert_eq!(tree.get(&2), Some(20));
+  assert_eq!(tree.len(), 1);
+}
+
+#[test]
+fn test_split_and_merge() {
+  let mut tree = BPlusTree::<Wide, u64, _>::new(Alloc::new());
+  for key in 0..500 {
+    tree.insert(Wide::new(key), key).unwrap();
+  }
+  assert!(tree.height() >= 2);
+  assert_eq!(keys(&tree), (0..500).map(Wide::new).collect::<Vec<_>>());
+
+  for key in (0..500).filter(|key| key % 3 != 0) {
+    assert_eq!(tree.remove(&Wide::new(key)), Some(key));
+  }
+  let rest: Vec<_> = (0..500).filter(|key| key % 3 == 0).collect();
+  assert_eq!(
+    keys(&tree),
+    rest.iter().copied().map(Wide::new).collect::<Vec<_>>()
+  );
+
+  for key in rest {
+    assert_eq!(tree.remove(&Wide::new(key)), Some(key));
+  }
+  assert!(tree.is_empty());
+  assert_eq!(tree.height(), 0);
+  assert_eq!(tree.iter().next(), None);
+}
+
+#[test]
+fn test_range_scan() {
+  let mut tree = BPlusTree::<u64, (), _>::new(Alloc::new());
+  for key in (0..10_000).map(|key| key * 2) {
+    tree.insert(key, ()).unwrap();
+  }
+
+  let range = |range: (Bound<u64>, Bound<u64>)| {
+    tree.range(range).map(|(key, _)| key).collect::<Vec<_>>()
+  };
+  assert_eq!(
+    range((Bound::Included(100), Bound::Excluded(108))),
+    [100, 102, 104, 106]
+  );
+  assert_eq!(
+    range((Bound::Excluded(100), Bound::Included(106))),
+    [102, 104, 106]
+  );
+  assert_eq!(range((Bound::Included(101), Bound::Included(103))), [102]);
+  assert_eq!(
+    range((Bound::Included(19_990), Bound::Unbounded)),
+    [19_990, 19_992, 19_994, 19_996, 19_998]
+  );
+  assert_eq!(range((Bound::Unbounded, Bound::Excluded(4))), [0, 2]);
+  assert!(range((Bound::Included(30_000), Bound::Unbounded)).is_empty());
+  assert_eq!(tree.iter().count(), 10_000);
+}
+
+#[test]
+fn test_pages_are_reused() {
+  let mut tree = BPlusTree::<Wide, u64, _>::new(Alloc::new());
+  for round in 0..3 {
+    for key in 0..200 {
+      tree.insert(Wide::new(key), round).unwrap();
+    }
+    for key in 0..200 {
+      assert_eq!(tree.remove(&Wide::new(key)), Some(round));
+    }
+  }
+  assert!(tree.is_empty());
+  assert!(format!("{tree:?}").contains("len: 0"));
+}
+
+#[test]
+fn test_pre_alloc_backend() {
+  let mut pages = vec![bytemuck::Zeroable::zeroed(); 64];
+  let mut tree = BPlusTree::<u64, u64, _>::new(PreAlloc::new(&mut pages[..]));
+
+  for key in 0..2000 {
+    tree.insert(key, key).unwrap();
+  }
+  assert_eq!(tree.get(&1999), Some(1999));
+
+  // A bounded backend reports running out of pages instead of panicking
+  let result =
+    (2000..100_000).try_for_each(|key| tree.insert(key, key).map(drop));
+  assert!(result.is_err());
+  assert_eq!(tree.get(&1000), Some(1000));
+  assert_eq!(keys(&tree).len(), tree.len());
+}
+
+#[test]
+fn test_file_backend() {
+  let mut tree = BPlusTree::<u64, u64, _>::new(TempFile::new().unwrap());
+  for key in (0..5000).rev() {
+    tree.insert(key, key + 1).unwrap();
+  }
+  assert_eq!(tree.get(&0), Some(1));
+  assert_eq!(keys(&tree), (0..5000).collect::<Vec<_>>());
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig {
+    cases: 64,
+    .. ProptestConfig::default()
+  })]
+
+  #[test]
+  fn prop_matches_btree_map(
+    ops in prop::collection::vec((any::<bool>(), 0u64..400), 1..800),
+    bounds in (0u64..400, 0u64..400),
+  ) {
+    let mut tree = BPlusTree::<Wide, u64, _>::new(Alloc::new());
+    let mut model = BTreeMap::new();
+
+    for (i, (insert, key)) in ops.into_iter().enumerate() {
+      if insert {
+        let old = tree.insert(Wide::new(key), i as u64).unwrap();
+        prop_assert_eq!(old, model.insert(key, i as u64));
+      } else {
+        prop_assert_eq!(tree.remove(&Wide::new(key)), model.remove(&key));
+      }
+      prop_assert_eq!(tree.len(), model.len());
+    }
+
+    let all: Vec<_> =
+      tree.iter().map(|(key, value)| (key.0[0], value)).collect();
+    let expected: Vec<_> = model.iter().map(|(&k, &v)| (k, v)).collect();
+    prop_assert_eq!(all, expected);
+
+    let (lo, hi) = (bounds.0.min(bounds.1), bounds.0.max(bounds.1));
+    let found: Vec<_> = tree
+      .range(Wide::new(lo)..Wide::new(hi))
+      .map(|(key, _)| key.0[0])
+      .collect();
+    let expected: Vec<_> = model.range(lo..hi).map(|(&k, _)| k).collect();
+    prop_assert_eq!(found, expected);
+  }
+}
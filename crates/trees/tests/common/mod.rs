@@ -1,4 +1,8 @@
-use trees::{AdaptiveRadix, Idx, Node, SizeBalanced, Tree};
+use {
+  mem::Alloc,
+  std::marker::PhantomData,
+  trees::{AdaptiveRadix, Art, Idx, Node, SizeBalanced, Tree},
+};
 
 /// Vector-backed tree store for testing and benchmarking.
 /// Generic over the tree implementation strategy.
@@ -8,6 +12,7 @@ pub struct VecStore<T> {
 }
 
 impl<T> VecStore<T> {
+  #[allow(dead_code)]
   pub fn new(capacity: usize) -> Self {
     Self { nodes: (0..capacity).map(|_| Node::default()).collect() }
   }
@@ -83,63 +88,49 @@ impl<T: Idx> Tree<T> for VecStore<T> {
 // SBT strategy
 impl<T: Idx> SizeBalanced<T> for VecStore<T> {}
 
-// ART strategy
-impl<T: Idx> AdaptiveRadix<T> for VecStore<T> {}
-
 // Type alias for convenience
+#[allow(dead_code)]
 pub type Store<T> = VecStore<T>;
 
-/// ART-specific store that uses ART insert/remove by default
-#[derive(Debug, Clone)]
+/// Store holding `values` inserted in order, and the root of their tree
+#[allow(dead_code)]
+pub fn build(values: &[usize]) -> (Store<usize>, Option<usize>) {
+  let cap = values.iter().max().map_or(0, |max| max + 1);
+  let mut store = Store::new(cap);
+  let mut root = None;
+  for &value in values {
+    root = store.insert(root, value);
+  }
+  (store, root)
+}
+
+/// ART-specific store keeping elements in a heap-allocated radix tree
+#[derive(Debug)]
 pub struct ArtStore<T> {
-  inner: VecStore<T>,
+  art: Art<Alloc<u64>>,
+  _marker: PhantomData<T>,
 }
 
 impl<T> ArtStore<T> {
-  pub fn new(capacity: usize) -> Self {
-    Self { inner: VecStore::new(capacity) }
+  #[allow(dead_code)]
+  pub fn new(_capacity: usize) -> Self {
+    Self { art: Art::default(), _marker: PhantomData }
   }
 
   #[allow(dead_code)]
   pub fn reset(&mut self) {
-    self.inner.reset()
+    self.art.clear()
   }
 }
 
-impl<T: Idx> Tree<T> for ArtStore<T> {
-  #[inline(always)]
-  fn get(&self, idx: T) -> Option<Node<T>> {
-    self.inner.get(idx)
-  }
-
-  #[inline(always)]
-  fn set(&mut self, idx: T, node: Node<T>) {
-    self.inner.set(idx, node)
-  }
-
-  #[inline(always)]
-  fn left_mut(&mut self, idx: T) -> Option<&mut T> {
-    self.inner.left_mut(idx)
-  }
+impl<T: Idx> AdaptiveRadix<T> for ArtStore<T> {
+  type Mem = Alloc<u64>;
 
-  #[inline(always)]
-  fn right_mut(&mut self, idx: T) -> Option<&mut T> {
-    self.inner.right_mut(idx)
+  fn radix(&self) -> &Art<Self::Mem> {
+    &self.art
   }
 
-  #[inline(always)]
-  fn is_left_of(&self, first: T, second: T) -> bool {
-    self.inner.is_left_of(first, second)
-  }
-
-  // Override to use ART instead of default SBT
-  fn insert(&mut self, root: Option<T>, idx: T) -> Option<T> {
-    AdaptiveRadix::insert_art(self, root, idx)
-  }
-
-  fn remove(&mut self, root: Option<T>, idx: T) -> Option<T> {
-    AdaptiveRadix::remove_art(self, root, idx)
+  fn radix_mut(&mut self) -> &mut Art<Self::Mem> {
+    &mut self.art
   }
 }
-
-impl<T: Idx> AdaptiveRadix<T> for ArtStore<T> {}
@@ -0,0 +1,138 @@
+mod common;
+
+use {
+  common::{Store, build},
+  core::cmp::Ordering,
+  proptest::prelude::*,
+  std::collections::BTreeSet,
+  trees::Tree,
+};
+
+#[test]
+fn test_iter_in_order() {
+  let (store, root) = build(&[50, 20, 80, 10, 30, 70, 90, 25, 35]);
+
+  let all: Vec<_> = store.iter_from(root).collect();
+  assert_eq!(all, [10, 20, 25, 30, 35, 50, 70, 80, 90]);
+
+  let rev: Vec<_> = store.iter_from(root).rev().collect();
+  assert_eq!(rev, [90, 80, 70, 50, 35, 30, 25, 20, 10]);
+}
+
+#[test]
+fn test_iter_empty() {
+  let store: Store<usize> = Store::new(4);
+  assert_eq!(store.iter_from(None).next(), None);
+  assert_eq!(store.iter_from(None).next_back(), None);
+}
+
+#[test]
+fn test_next_stops_at_ancestor() {
+  let (store, root) = build(&[1, 2, 3]);
+  let root = root.unwrap();
+  let left = store.leftest(root);
+
+  // `next` only looks into the subtree, the iterator continues past it
+  assert_eq!(store.next(left), None);
+  let after: Vec<_> = store.range(Some(root), left + 1..).collect();
+  assert_eq!(after, [2, 3]);
+}
+
+#[test]
+fn test_range_bounds() {
+  let (store, root) = build(&(1..20).map(|i| i * 2).collect::<Vec<_>>());
+  let range = |lo, hi| store.range(root, lo..hi).collect::<Vec<_>>();
+
+  assert_eq!(range(6, 12), [6, 8, 10]);
+  // Bounds that are not in the tree
+  assert_eq!(range(5, 11), [6, 8, 10]);
+  assert_eq!(range(0, 3), [2]);
+  assert_eq!(range(39, 100), []);
+  assert_eq!(range(10, 10), []);
+
+  let inclusive: Vec<_> = store.range(root, 6..=12).rev().collect();
+  assert_eq!(inclusive, [12, 10, 8, 6]);
+  let tail: Vec<_> = store.range(root, 34..).collect();
+  assert_eq!(tail, [34, 36, 38]);
+  let head: Vec<_> = store.range(root, ..=4).collect();
+  assert_eq!(head, [2, 4]);
+}
+
+#[test]
+fn test_double_ended_meet() {
+  let (store, root) = build(&[4, 2, 6, 1, 3, 5, 7]);
+  let mut iter = store.iter_from(root);
+
+  assert_eq!(iter.next(), Some(1));
+  assert_eq!(iter.next_back(), Some(7));
+  assert_eq!(iter.next_back(), Some(6));
+  assert_eq!(iter.next(), Some(2));
+  let rest: Vec<_> = iter.by_ref().collect();
+  assert_eq!(rest, [3, 4, 5]);
+  assert_eq!(iter.next_back(), None);
+}
+
+#[test]
+fn test_range_by_key() {
+  // Elements grouped by tens, selected by group like a source prefix
+  let (store, root) = build(&[31, 12, 45, 33, 10, 38, 21, 30]);
+  let group = |tens: usize| {
+    store.range_by(root, move |idx| (idx / 10).cmp(&tens)).collect::<Vec<_>>()
+  };
+
+  assert_eq!(group(3), [30, 31, 33, 38]);
+  assert_eq!(group(1), [10, 12]);
+  assert_eq!(group(0), []);
+  assert_eq!(group(5), []);
+
+  let all = store.range_by(root, |_| Ordering::Equal).count();
+  assert_eq!(all, 8);
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig {
+    cases: 128,
+    .. ProptestConfig::default()
+  })]
+
+  #[test]
+  fn prop_range_matches_btree_set(
+    values in prop::collection::hash_set(1usize..500, 0..200),
+    lo in 0usize..520,
+    len in 0usize..200,
+  ) {
+    let values: Vec<_> = values.into_iter().collect();
+    let (store, root) = build(&values);
+    let model: BTreeSet<_> = values.iter().copied().collect();
+
+    let all: Vec<_> = store.iter_from(root).collect();
+    prop_assert_eq!(all, model.iter().copied().collect::<Vec<_>>());
+
+    let hi = lo + len;
+    let found: Vec<_> = store.range(root, lo..hi).rev().collect();
+    let expected: Vec<_> = model.range(lo..hi).rev().copied().collect();
+    prop_assert_eq!(found, expected);
+  }
+
+  #[test]
+  fn prop_alternating_ends(
+    values in prop::collection::hash_set(1usize..300, 0..100),
+    pattern in prop::collection::vec(any::<bool>(), 0..120),
+  ) {
+    let values: Vec<_> = values.into_iter().collect();
+    let (store, root) = build(&values);
+    let mut model: Vec<_> = values.clone();
+    model.sort_unstable();
+
+    let mut iter = store.iter_from(root);
+    let mut model = model.into_iter();
+    for front in pattern {
+      if front {
+        prop_assert_eq!(iter.next(), model.next());
+      } else {
+        prop_assert_eq!(iter.next_back(), model.next_back());
+      }
+    }
+    prop_assert_eq!(iter.collect::<Vec<_>>(), model.collect::<Vec<_>>());
+  }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use {
+  common::{Store, build},
+  proptest::prelude::*,
+  trees::{SizeBalanced, Tree},
+};
+
+#[test]
+fn test_rank_and_select() {
+  let (store, root) = build(&[40, 10, 70, 20, 60, 30, 50]);
+
+  for (k, value) in [10, 20, 30, 40, 50, 60, 70].into_iter().enumerate() {
+    assert_eq!(store.rank(root, value), Some(k));
+    assert_eq!(store.select(root, k), Some(value));
+  }
+  assert_eq!(store.rank(root, 45), None);
+  assert_eq!(store.select(root, 7), None);
+}
+
+#[test]
+fn test_count_less_and_range() {
+  let (store, root) = build(&[40, 10, 70, 20, 60, 30, 50]);
+
+  assert_eq!(store.count_less(root, 10), 0);
+  assert_eq!(store.count_less(root, 45), 4);
+  assert_eq!(store.count_less(root, 71), 7);
+
+  assert_eq!(store.count_range(root, 20, 60), 4);
+  assert_eq!(store.count_range(root, 15, 65), 5);
+  assert_eq!(store.count_range(root, 60, 20), 0);
+  assert_eq!(store.count_range(root, 0, 100), 7);
+}
+
+#[test]
+fn test_empty_tree() {
+  let store: Store<usize> = Store::new(4);
+
+  assert_eq!(store.rank(None, 1), None);
+  assert_eq!(store.select(None, 0), None);
+  assert_eq!(store.count_less(None, 3), 0);
+  assert_eq!(store.count_range(None, 0, 3), 0);
+}
+
+#[test]
+fn test_ranks_after_remove() {
+  let (mut store, mut root) = build(&(1..=30).collect::<Vec<_>>());
+  for value in (2..=30).step_by(3) {
+    root = store.remove(root, value);
+  }
+
+  let left: Vec<_> = (1..=30).filter(|v| (v + 1) % 3 != 0).collect();
+  for (k, &value) in left.iter().enumerate() {
+    assert_eq!(store.select(root, k), Some(value));
+    assert_eq!(store.rank(root, value), Some(k));
+  }
+  assert_eq!(store.rank(root, 2), None);
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig {
+    cases: 128,
+    .. ProptestConfig::default()
+  })]
+
+  #[test]
+  fn prop_matches_sorted_vec(
+    values in prop::collection::hash_set(1usize..400, 0..150),
+    lo in 0usize..420,
+    hi in 0usize..420,
+  ) {
+    let values: Vec<_> = values.into_iter().collect();
+    let (store, root) = build(&values);
+    let mut sorted = values.clone();
+    sorted.sort_unstable();
+
+    for (k, &value) in sorted.iter().enumerate() {
+      prop_assert_eq!(store.select(root, k), Some(value));
+      prop_assert_eq!(store.rank(root, value), Some(k));
+    }
+    prop_assert_eq!(store.select(root, sorted.len()), None);
+
+    let less = sorted.iter().filter(|&&v| v < lo).count();
+    prop_assert_eq!(store.count_less(root, lo), less);
+    let within = sorted.iter().filter(|&&v| lo <= v && v < hi).count();
+    prop_assert_eq!(store.count_range(root, lo, hi), within);
+  }
+}
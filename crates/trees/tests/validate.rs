@@ -0,0 +1,158 @@
+mod common;
+
+use {
+  common::{ArtStore, Store, build},
+  mem::Alloc,
+  proptest::prelude::*,
+  trees::{
+    AdaptiveRadix, Art, Node, Tree,
+    validate::{self, Violation},
+  },
+};
+
+fn node(size: usize, left: Option<usize>, right: Option<usize>) -> Node<usize> {
+  Node { size, left, right }
+}
+
+#[test]
+fn test_valid_tree() {
+  let (store, root) = build(&[40, 10, 70, 20, 60, 30, 50]);
+
+  assert_eq!(validate::tree(&store, root), Ok(7));
+  assert_eq!(validate::sizes(&store, root), Ok(7));
+  assert_eq!(validate::size_balanced(&store, root), Ok(7));
+  assert_eq!(validate::size_balanced(&store, None), Ok(0));
+}
+
+#[test]
+fn test_broken_trees() {
+  // 2 with children 1 and 3
+  let valid = || {
+    let mut nodes = vec![Node::default(); 4];
+    nodes[1] = node(1, None, None);
+    nodes[2] = node(3, Some(1), Some(3));
+    nodes[3] = node(1, None, None);
+    Store::with_nodes(nodes)
+  };
+  assert_eq!(validate::size_balanced(&valid(), Some(2)), Ok(3));
+
+  let mut store = valid();
+  store.nodes_mut()[3].size = 2;
+  assert_eq!(
+    validate::sizes(&store, Some(2)),
+    Err(Violation::WrongSize { node: 3, stored: 2, actual: 1 })
+  );
+  // Sizes are not part of the plain tree shape
+  assert_eq!(validate::tree(&store, Some(2)), Ok(3));
+
+  let mut store = valid();
+  store.nodes_mut()[3].left = Some(2);
+  assert_eq!(
+    validate::tree(&store, Some(2)),
+    Err(Violation::Revisited { node: 2 })
+  );
+
+  let mut store = valid();
+  store.nodes_mut()[2] = node(3, Some(3), Some(1));
+  assert_eq!(
+    validate::tree(&store, Some(2)),
+    Err(Violation::Unordered { node: 3, ancestor: 2 })
+  );
+
+  let mut store = valid();
+  store.nodes_mut()[3].right = Some(9);
+  assert_eq!(
+    validate::tree(&store, Some(2)),
+    Err(Violation::Missing { node: 9 })
+  );
+}
+
+#[test]
+fn test_unbalanced() {
+  // 1 -> 2 -> 3 leaning right, with correct sizes
+  let mut nodes = vec![Node::default(); 4];
+  nodes[1] = node(3, None, Some(2));
+  nodes[2] = node(2, None, Some(3));
+  nodes[3] = node(1, None, None);
+  let store = Store::with_nodes(nodes);
+
+  assert_eq!(validate::sizes(&store, Some(1)), Ok(3));
+  let violation = validate::size_balanced(&store, Some(1)).unwrap_err();
+  assert_eq!(violation, Violation::Unbalanced { node: 1, nephew: 3 });
+  assert_eq!(violation.to_string(), "node 3 below 1 outweighs its uncle");
+}
+
+#[test]
+fn test_radix() {
+  let mut art = ArtStore::<usize>::new(0);
+  for idx in 1..500 {
+    art.insert_art(idx).unwrap();
+  }
+  for idx in (1..500).step_by(3) {
+    art.remove_art(idx);
+  }
+  let kept = || (1..500usize).filter(|idx| idx % 3 != 1);
+
+  assert_eq!(validate::adaptive_radix(&art, kept()), Ok(332));
+  assert_eq!(
+    validate::adaptive_radix(&art, kept().chain([1])),
+    Err(Violation::Absent { element: 1 })
+  );
+  assert_eq!(
+    validate::adaptive_radix(&art, kept().skip(1)),
+    Err(Violation::Count { expected: 331, actual: 332 })
+  );
+
+  let empty = Art::<Alloc<u64>>::default();
+  assert_eq!(validate::adaptive_radix(&empty, None::<usize>), Ok(0));
+}
+
+proptest! {
+  #[test]
+  fn prop_inserts_keep_sizes(
+    values in prop::collection::hash_set(1usize..500, 1..200)
+  ) {
+    let values: Vec<_> = values.into_iter().collect();
+    let (store, root) = build(&values);
+    prop_assert_eq!(validate::sizes(&store, root), Ok(values.len()));
+  }
+
+  #[test]
+  fn prop_removals_keep_sizes(
+    values in prop::collection::hash_set(1usize..300, 1..120),
+    removed in prop::collection::vec(any::<prop::sample::Index>(), 0..60)
+  ) {
+    let mut values: Vec<_> = values.into_iter().collect();
+    let (mut store, mut root) = build(&values);
+    for index in removed {
+      if values.is_empty() {
+        break;
+      }
+      let value = values.swap_remove(index.index(values.len()));
+      root = store.remove(root, value);
+      prop_assert_eq!(validate::sizes(&store, root), Ok(values.len()));
+    }
+  }
+
+  #[test]
+  fn prop_radix_matches_elements(
+    values in prop::collection::btree_set(1usize..100_000, 0..300),
+    removed in prop::collection::vec(any::<prop::sample::Index>(), 0..100)
+  ) {
+    let mut values: Vec<_> = values.into_iter().collect();
+    let mut art = ArtStore::<usize>::new(0);
+    for &value in &values {
+      art.insert_art(value).unwrap();
+    }
+    for index in removed {
+      if values.is_empty() {
+        break;
+      }
+      art.remove_art(values.remove(index.index(values.len())));
+    }
+    prop_assert_eq!(
+      validate::adaptive_radix(&art, values.iter().copied()),
+      Ok(values.len())
+    );
+  }
+}
@@ -0,0 +1,235 @@
+//! `dunes`, a command line tool for inspecting and editing doublets files
+
+use {
+  clap::{Parser, Subcommand},
+  doublets::{Doublets, Flow, Index, Link, Links, RawLink, Store},
+  mem::{FileMapped, RawMem},
+  std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+  },
+};
+
+type FileStore = Store<usize, FileMapped<RawLink<usize>>>;
+
+type Result<T = (), E = Box<dyn Error>> = std::result::Result<T, E>;
+
+/// Inspect and edit a doublets store file
+///
+/// Without a command, reads commands from standard input one per line.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+  /// Store file, created when it does not exist
+  file: PathBuf,
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+/// One line of the interactive mode
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct Line {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Show how many links the store holds
+  Stats,
+  /// Show a link
+  Get { index: usize },
+  /// List links by source and target, all links without either
+  Find {
+    #[arg(long)]
+    source: Option<usize>,
+    #[arg(long)]
+    target: Option<usize>,
+  },
+  /// Create a link, or a point without a source and target
+  Create {
+    #[arg(requires = "target")]
+    source: Option<usize>,
+    target: Option<usize>,
+  },
+  /// Point an existing link at a new source and target
+  Update { index: usize, source: usize, target: usize },
+  /// Delete a link
+  Delete { index: usize },
+  /// Write every link to a file, `-` for standard output
+  Export {
+    path: PathBuf,
+    /// Write Links Notation instead of a binary snapshot
+    #[arg(long)]
+    lino: bool,
+  },
+  /// Read links from a file, `-` for standard input
+  Import {
+    path: PathBuf,
+    /// Read Links Notation instead of a binary snapshot
+    #[arg(long)]
+    lino: bool,
+  },
+  /// Check the indexes and free list against the links
+  Check {
+    /// Rebuild the indexes and free list when they are damaged and that
+    /// fixes every problem
+    #[arg(long)]
+    repair: bool,
+  },
+}
+
+fn main() -> ExitCode {
+  let cli = Cli::parse();
+  let result = open(&cli.file).and_then(|mut store| match cli.command {
+    Some(command) => execute(&mut store, command),
+    None => repl(&mut store),
+  });
+  match result {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(err) => {
+      eprintln!("error: {err}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn open(path: &Path) -> Result<FileStore> {
+  let mem = FileMapped::from_path(path)?;
+  Ok(Store::open(mem)?)
+}
+
+/// Run commands read from standard input until it ends or `exit`
+fn repl(store: &mut FileStore) -> Result {
+  let mut stdout = io::stdout();
+  let mut line = String::new();
+  loop {
+    write!(stdout, "> ")?;
+    stdout.flush()?;
+    // Not holding on to stdin keeps `import -` usable from here
+    line.clear();
+    if io::stdin().read_line(&mut line)? == 0 {
+      writeln!(stdout)?;
+      return Ok(());
+    }
+    let words: Vec<_> = line.split_whitespace().collect();
+    match words[..] {
+      [] => continue,
+      ["exit" | "quit"] => return Ok(()),
+      _ => {}
+    }
+    match Line::try_parse_from(words) {
+      Ok(Line { command }) => {
+        if let Err(err) = execute(store, command) {
+          eprintln!("error: {err}");
+        }
+      }
+      Err(err) => err.print()?,
+    }
+  }
+}
+
+fn execute(store: &mut FileStore, command: Command) -> Result {
+  let mut out = BufWriter::new(io::stdout().lock());
+  match command {
+    Command::Stats => {
+      let mut points = 0;
+      store.each([], &mut |link: Link<usize>| {
+        points += link.is_full() as usize;
+        Flow::Continue
+      });
+      writeln!(out, "links: {}", store.count_all())?;
+      writeln!(out, "points: {points}")?;
+      writeln!(out, "capacity: {}", store.mem().as_slice().len())?;
+    }
+    Command::Get { index } => {
+      let link = store.get(index).ok_or(doublets::Error::NotExists(index))?;
+      writeln!(out, "({link:?})")?;
+    }
+    Command::Find { source, target } => {
+      let query = [
+        usize::ANY,
+        source.unwrap_or(usize::ANY),
+        target.unwrap_or(usize::ANY),
+      ];
+      let mut result = Ok(());
+      store.each(query, &mut |link: Link<usize>| {
+        result = writeln!(out, "({link:?})");
+        Flow::from(result.is_ok())
+      });
+      result?;
+    }
+    Command::Create { source, target } => {
+      let index = match source.zip(target) {
+        Some((source, target)) => store.create_link(source, target)?,
+        None => store.create_point()?,
+      };
+      writeln!(out, "({:?})", store.get(index).unwrap())?;
+    }
+    Command::Update { index, source, target } => {
+      store.update_link(index, source, target)?;
+      writeln!(out, "({:?})", store.get(index).unwrap())?;
+    }
+    Command::Delete { index } => {
+      let link = store.get(index).ok_or(doublets::Error::NotExists(index))?;
+      store.delete_link(index)?;
+      writeln!(out, "({link:?})")?;
+    }
+    Command::Export { path, lino } => {
+      let writer: Box<dyn Write> = if path == Path::new("-") {
+        Box::new(io::stdout().lock())
+      } else {
+        Box::new(BufWriter::new(File::create(path)?))
+      };
+      if lino {
+        store.write_lino(writer)?;
+      } else {
+        store.export(writer)?;
+      }
+    }
+    Command::Import { path, lino } => {
+      let mut reader: Box<dyn Read> = if path == Path::new("-") {
+        Box::new(io::stdin().lock())
+      } else {
+        Box::new(BufReader::new(File::open(path)?))
+      };
+      if lino {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        store.load_lino(&text)?;
+      } else {
+        store.import(reader)?;
+      }
+      writeln!(out, "links: {}", store.count_all())?;
+    }
+    Command::Check { repair } => {
+      let mut report = store.verify();
+      for problem in &report.problems {
+        writeln!(out, "{problem}")?;
+      }
+      // Dangling references are in the links, rebuilding cannot fix them
+      let repairable = report.is_repairable();
+      if repair && repairable && !report.is_ok() {
+        store.rebuild_indexes()?;
+        report = store.verify();
+        writeln!(out, "rebuilt indexes")?;
+      }
+      if !report.is_ok() {
+        out.flush()?;
+        let count = report.problems.len();
+        if repair && !repairable {
+          let message = "dangling references cannot be repaired";
+          return Err(format!("{count} problems found, {message}").into());
+        }
+        return Err(format!("{count} problems found").into());
+      }
+      writeln!(out, "ok: {} links", report.links)?;
+    }
+  }
+  out.flush()?;
+  Ok(())
+}
@@ -0,0 +1,165 @@
+// Tests for the `dunes` command line tool
+//
+// Every command reopens the file, so these also check that edits made by
+// one run are what the next run finds.
+
+use std::{
+  io::Write,
+  path::Path,
+  process::{Command, Output, Stdio},
+};
+
+fn dunes(file: &Path, args: &[&str]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_dunes"))
+    .arg(file)
+    .args(args)
+    .output()
+    .unwrap()
+}
+
+fn stdout(file: &Path, args: &[&str]) -> String {
+  let output = dunes(file, args);
+  assert!(
+    output.status.success(),
+    "{:?}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_commands() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("db.links");
+
+  assert_eq!(stdout(&file, &["create"]), "(1: 1 1)\n");
+  assert_eq!(stdout(&file, &["create"]), "(2: 2 2)\n");
+  assert_eq!(stdout(&file, &["create", "1", "2"]), "(3: 1 2)\n");
+  assert_eq!(stdout(&file, &["create", "2", "1"]), "(4: 2 1)\n");
+  assert_eq!(stdout(&file, &["update", "4", "2", "3"]), "(4: 2 3)\n");
+
+  assert_eq!(stdout(&file, &["get", "3"]), "(3: 1 2)\n");
+  assert_eq!(stdout(&file, &["find", "--source", "2"]), "(2: 2 2)\n(4: 2 3)\n");
+  assert_eq!(
+    stdout(&file, &["find", "--source", "1", "--target", "2"]),
+    "(3: 1 2)\n"
+  );
+  assert!(stdout(&file, &["stats"]).starts_with("links: 4\npoints: 2\n"));
+  assert_eq!(stdout(&file, &["check"]), "ok: 4 links\n");
+
+  assert_eq!(stdout(&file, &["delete", "4"]), "(4: 2 3)\n");
+  assert_eq!(stdout(&file, &["find"]), "(1: 1 1)\n(2: 2 2)\n(3: 1 2)\n");
+}
+
+#[test]
+fn test_errors() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("db.links");
+
+  let output = dunes(&file, &["get", "7"]);
+  assert!(!output.status.success());
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "error: Link 7 does not exist\n"
+  );
+
+  // A source needs a target
+  assert!(!dunes(&file, &["create", "1"]).status.success());
+}
+
+#[test]
+fn test_export_import() {
+  let dir = tempfile::tempdir().unwrap();
+  let (from, to) = (dir.path().join("from.links"), dir.path().join("to.links"));
+  for args in [&["create"][..], &["create"], &["create", "1", "2"]] {
+    stdout(&from, args);
+  }
+
+  for lino in [false, true] {
+    let dump = dir.path().join("dump");
+    let flag: &[_] = if lino { &["--lino"] } else { &[] };
+    stdout(&from, &[&["export", dump.to_str().unwrap()], flag].concat());
+    let _ = std::fs::remove_file(&to);
+    assert_eq!(
+      stdout(&to, &[&["import", dump.to_str().unwrap()], flag].concat()),
+      "links: 3\n"
+    );
+    assert_eq!(stdout(&to, &["find"]), stdout(&from, &["find"]));
+  }
+  assert_eq!(
+    stdout(&from, &["export", "-", "--lino"]),
+    "(1: 1 1)\n(2: 2 2)\n(3: 1 2)\n"
+  );
+}
+
+#[test]
+fn test_repl() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("db.links");
+
+  let mut child = Command::new(env!("CARGO_BIN_EXE_dunes"))
+    .arg(&file)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  let input =
+    "create\n\ncreate 1 1\nget 9\nfrobnicate\nfind --target 1\nexit\nstats\n";
+  child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+  let output = child.wait_with_output().unwrap();
+
+  assert!(output.status.success());
+  // Blank lines prompt again and nothing runs after `exit`
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  assert_eq!(stdout, "> (1: 1 1)\n> > (2: 1 1)\n> > > (1: 1 1)\n(2: 1 1)\n> ");
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.contains("error: Link 9 does not exist"));
+  assert!(stderr.contains("unrecognized subcommand 'frobnicate'"));
+}
+
+#[test]
+fn test_check_repair() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("db.links");
+  for args in [&["create"][..], &["create"], &["create", "1", "2"]] {
+    stdout(&file, args);
+  }
+
+  // Size of the source tree node of link 3, the third of its eight words
+  let mut bytes = std::fs::read(&file).unwrap();
+  let offset = (3 * 8 + 2) * size_of::<usize>();
+  bytes[offset] += 1;
+  std::fs::write(&file, bytes).unwrap();
+
+  let output = dunes(&file, &["check"]);
+  assert!(!output.status.success());
+  assert!(
+    String::from_utf8_lossy(&output.stdout)
+      .starts_with("source tree node 3 keeps size")
+  );
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "error: 1 problems found\n"
+  );
+
+  assert!(
+    stdout(&file, &["check", "--repair"])
+      .ends_with("rebuilt indexes\nok: 3 links\n")
+  );
+  assert_eq!(stdout(&file, &["check"]), "ok: 3 links\n");
+
+  // Target of link 3, its second word, now points past the last link
+  let mut bytes = std::fs::read(&file).unwrap();
+  let offset = (3 * 8 + 1) * size_of::<usize>();
+  bytes[offset] = 9;
+  std::fs::write(&file, bytes).unwrap();
+
+  let output = dunes(&file, &["check", "--repair"]);
+  assert!(!output.status.success());
+  assert!(!String::from_utf8_lossy(&output.stdout).contains("rebuilt"));
+  assert!(
+    String::from_utf8_lossy(&output.stderr)
+      .ends_with("problems found, dangling references cannot be repaired\n")
+  );
+}